@@ -1,5 +1,5 @@
 import * as p5 from "p5";
-import { CANVAS_HEIGHT, CANVAS_WIDTH, ELECTRON_COLOR, ELECTRON_M, ELECTRON_Q, ELECTRON_RADIUS, FPS, ION_COLOR, ION_RADIUS, TIME_SCALE, TO_CM_POW_2, TO_MM_POW_2, TO_UM, UPDATE_EVERY_N, VOLUME_SCALE } from "./cfg";
+import { CANVAS_HEIGHT, CANVAS_WIDTH, ELECTRON_COLOR, ELECTRON_M, ELECTRON_Q, FPS, HOLE_COLOR, ION_COLOR, TIME_SCALE, TO_CM_POW_2, TO_MM_POW_2, TO_UM, UPDATE_EVERY_N, VOLUME_SCALE } from "./cfg";
 import * as utils from "utils";
 utils;
 
@@ -86,11 +86,11 @@ export default class App {
     this.ctx.clearRect(0, 0, CANVAS_WIDTH, CANVAS_HEIGHT);
 
     this.ions.forEach((ion) => {
-      this.drawCircle(ion.x, ion.y, ION_RADIUS, ION_COLOR, 'black', 1);
+      this.drawCircle(ion.x, ion.y, ion.radius, ION_COLOR, 'black', 1);
     });
 
     this.cs.get_electrons().forEach((electron) => {
-      this.drawCircle(electron.x, electron.y, ELECTRON_RADIUS, ELECTRON_COLOR);
+      this.drawCircle(electron.x, electron.y, electron.radius, electron.charge > 0 ? HOLE_COLOR : ELECTRON_COLOR);
     });
   }
 
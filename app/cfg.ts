@@ -4,6 +4,7 @@ export const UPDATE_EVERY_N = 30;
 export const FPS = 60;
 
 export const ELECTRON_COLOR = 'blue';
+export const HOLE_COLOR = 'orange';
 export const ION_COLOR = 'red';
 export const ION_RADIUS = 10;
 export const ELECTRON_RADIUS = 3;
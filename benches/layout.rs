@@ -0,0 +1,81 @@
+// Throughput of one wall and ion prediction round, `Rc<RefCell<Electron>>` layout against the
+// struct-of-arrays store, on its own and through `CrystalStructure::batched`.
+// Run with `cargo bench --bench layout`.
+use std::time::{Duration, Instant};
+
+use utils::crystal_structure::CrystalStructure;
+use utils::scattering::ElectronScattering;
+use utils::soa::{IonList, ParticleStore, WallList};
+use utils::utils::seed_random;
+
+const ROUNDS: u32 = 5;
+
+fn per_second(count: usize, elapsed: Duration) -> f64 {
+    count as f64 / elapsed.as_secs_f64()
+}
+
+fn bench(label: &str, size: f64, ion_distance: f64, count: i32, acc: f64) {
+    seed_random(11);
+    let mut cs = CrystalStructure::new(size, size, ion_distance, 1.0, 0);
+    cs.electron_scattering = ElectronScattering::Disabled;
+    cs.add_carriers(0, count, 1.0);
+    cs.update(acc, 0.0);
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        cs.update_collidables();
+    }
+    let current = start.elapsed() / ROUNDS;
+
+    cs.batched = true;
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        cs.update_collidables();
+    }
+    let batched = start.elapsed() / ROUNDS;
+    cs.batched = false;
+    cs.update_collidables();
+
+    let (ions, walls) = (IonList::from_structure(&cs), WallList::from_structure(&cs));
+    let start = Instant::now();
+    let store = ParticleStore::from_structure(&cs);
+    let gather = start.elapsed();
+    let start = Instant::now();
+    let mut predictions = Vec::new();
+    for _ in 0..ROUNDS {
+        predictions = store.predict(&ions, &walls);
+    }
+    let soa = start.elapsed() / ROUNDS;
+
+    let agree = predictions
+        .iter()
+        .zip(cs.electrons.iter())
+        .filter(|(prediction, electron)| {
+            let time = electron.borrow().time_to_bounce;
+            (prediction.time - time).abs() <= 1e-9 * time.max(1.0)
+        })
+        .count();
+    assert_eq!(agree, store.len(), "struct-of-arrays prediction differs");
+
+    let n = store.len();
+    println!(
+        "{:<8} acc {:<4} {:>6} carriers {:>5} ions  current {:>10.3e}/s  soa {:>10.3e}/s  speed-up {:>5.1}x  (gather {:.3?})  batched engine {:>5.1}x",
+        label,
+        acc,
+        n,
+        ions.len(),
+        per_second(n, current),
+        per_second(n, soa),
+        current.as_secs_f64() / soa.as_secs_f64(),
+        gather,
+        current.as_secs_f64() / batched.as_secs_f64()
+    );
+}
+
+fn main() {
+    for acc in [0.0, 0.05] {
+        bench("sparse", 8000.0, 400.0, 10_000, acc);
+        bench("dense", 4000.0, 100.0, 10_000, acc);
+        bench("dense", 4000.0, 100.0, 40_000, acc);
+    }
+}
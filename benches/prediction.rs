@@ -0,0 +1,50 @@
+// Times one collision prediction round on large structures, serial against parallel.
+// Run with `cargo bench --features parallel --bench prediction`.
+use std::time::{Duration, Instant};
+
+use utils::crystal_structure::CrystalStructure;
+use utils::scattering::ElectronScattering;
+use utils::utils::seed_random;
+
+const ROUNDS: u32 = 2;
+
+fn time_prediction(cs: &mut CrystalStructure, parallel: bool) -> (Duration, Vec<f64>) {
+    cs.parallel = parallel;
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        cs.update_collidables();
+    }
+    let elapsed = start.elapsed() / ROUNDS;
+    let times = cs
+        .electrons
+        .iter()
+        .map(|electron| electron.borrow().time_to_bounce)
+        .collect();
+    (elapsed, times)
+}
+
+fn bench(label: &str, count: i32, electron_scattering: ElectronScattering) {
+    seed_random(7);
+    let mut cs = CrystalStructure::new(8000.0, 8000.0, 400.0, 1.0, 0);
+    cs.electron_scattering = electron_scattering;
+    cs.add_carriers(0, count, 1.0);
+
+    let (serial, serial_times) = time_prediction(&mut cs, false);
+    let (parallel, parallel_times) = time_prediction(&mut cs, true);
+    assert_eq!(serial_times, parallel_times, "parallel prediction differs");
+    println!(
+        "{:<12} {:>7} carriers  serial {:>10.3?}  parallel {:>10.3?}  speed-up {:.2}x",
+        label,
+        cs.electrons.len(),
+        serial,
+        parallel,
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
+
+fn main() {
+    println!("{} threads", rayon::current_num_threads());
+    bench("ions only", 10_000, ElectronScattering::Disabled);
+    bench("ions only", 100_000, ElectronScattering::Disabled);
+    bench("hard disk", 10_000, ElectronScattering::HardDisk);
+}
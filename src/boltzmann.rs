@@ -0,0 +1,426 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+use std::f64::consts::PI;
+
+use crate::species::{field_from_acc, Species};
+
+// Fraction of the stability limit used as the time step.
+const COURANT: f64 = 0.5;
+// Angles used to integrate the hard-disk cross-section.
+const SCATTERING_ANGLES: usize = 48;
+
+// Square grid of velocity cells covering [-v_max, v_max] in both components.
+// Distributions on the grid are stored row by row and normalised to sum(f) * dv^2 = 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VelocityGrid {
+    pub v_max: f64,
+    pub cells: usize,
+}
+
+impl VelocityGrid {
+    pub fn new(v_max: f64, cells: usize) -> Self {
+        VelocityGrid { v_max, cells }
+    }
+
+    pub fn spacing(&self) -> f64 {
+        2.0 * self.v_max / self.cells as f64
+    }
+
+    // Centre of cell `i` along x and `j` along y.
+    pub fn velocity(&self, i: usize, j: usize) -> Vector2<f64> {
+        let dv = self.spacing();
+        Vector2::new(
+            -self.v_max + (i as f64 + 0.5) * dv,
+            -self.v_max + (j as f64 + 0.5) * dv,
+        )
+    }
+
+    fn index(&self, i: usize, j: usize) -> usize {
+        j * self.cells + i
+    }
+
+    fn velocities(&self) -> impl Iterator<Item = Vector2<f64>> + '_ {
+        (0..self.cells).flat_map(move |j| (0..self.cells).map(move |i| self.velocity(i, j)))
+    }
+
+    fn normalise(&self, f: &mut [f64]) {
+        let total = f.iter().sum::<f64>() * self.spacing().powi(2);
+        if total > 0.0 {
+            f.iter_mut().for_each(|value| *value /= total);
+        }
+    }
+
+    // Maxwellian at `temperature` (k_B = 1) shifted by `drift`, normalised on the grid.
+    pub fn maxwellian(&self, temperature: f64, mass: f64, drift: Vector2<f64>) -> Vec<f64> {
+        let mut f: Vec<f64> = self
+            .velocities()
+            .map(|vel| (-mass * (vel - drift).magnitude_squared() / (2.0 * temperature)).exp())
+            .collect();
+        self.normalise(&mut f);
+        f
+    }
+
+    // Normalised histogram of sampled velocities, e.g. the carriers of a particle simulation.
+    // Velocities outside the grid are dropped.
+    pub fn histogram(&self, velocities: impl Iterator<Item = Vector2<f64>>) -> Vec<f64> {
+        let mut f = vec![0.0; self.cells * self.cells];
+        let dv = self.spacing();
+        for vel in velocities {
+            let i = ((vel.x + self.v_max) / dv).floor();
+            let j = ((vel.y + self.v_max) / dv).floor();
+            if i >= 0.0 && j >= 0.0 && (i as usize) < self.cells && (j as usize) < self.cells {
+                f[self.index(i as usize, j as usize)] += 1.0;
+            }
+        }
+        self.normalise(&mut f);
+        f
+    }
+
+    // Distribution of the velocity along the field axis, integrated over the other component.
+    pub fn marginal_x(&self, f: &[f64]) -> Vec<f64> {
+        let dv = self.spacing();
+        (0..self.cells)
+            .map(|i| (0..self.cells).map(|j| f[self.index(i, j)]).sum::<f64>() * dv)
+            .collect()
+    }
+
+    // Bilinear interpolation between cell centres, zero outside the grid.
+    fn interpolate(&self, f: &[f64], vel: Vector2<f64>) -> f64 {
+        let dv = self.spacing();
+        let x = (vel.x + self.v_max) / dv - 0.5;
+        let y = (vel.y + self.v_max) / dv - 0.5;
+        let (i, j) = (x.floor(), y.floor());
+        let (tx, ty) = (x - i, y - j);
+        let value = |i: f64, j: f64| {
+            if i < 0.0 || j < 0.0 || i as usize >= self.cells || j as usize >= self.cells {
+                0.0
+            } else {
+                f[self.index(i as usize, j as usize)]
+            }
+        };
+        value(i, j) * (1.0 - tx) * (1.0 - ty)
+            + value(i + 1.0, j) * tx * (1.0 - ty)
+            + value(i, j + 1.0) * (1.0 - tx) * ty
+            + value(i + 1.0, j + 1.0) * tx * ty
+    }
+
+    fn mean(&self, f: &[f64], weight: impl Fn(Vector2<f64>) -> f64) -> f64 {
+        let dv2 = self.spacing().powi(2);
+        self.velocities()
+            .zip(f.iter())
+            .map(|(vel, value)| weight(vel) * value * dv2)
+            .sum()
+    }
+}
+
+// Collision operator of the spatially homogeneous Boltzmann equation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CollisionKernel {
+    // Relaxation towards the Maxwellian at `temperature` with mean free time `tau`.
+    RelaxationTime { tau: f64, temperature: f64 },
+    // Elastic scattering off fixed hard disks of the given areal density, `radius` being the
+    // contact distance of carrier and disk. The 2D cross-section is R / 2 * |sin(theta / 2)|,
+    // 2R in total and 8R / 3 for momentum transfer.
+    HardDisk { density: f64, radius: f64 },
+}
+
+// Steady state reached by `BoltzmannSolver::solve`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SteadyState {
+    pub distribution: Vec<f64>,
+    pub drift_velocity: f64,
+    pub mean_energy: f64,
+    pub mobility: f64,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+impl SteadyState {
+    // sigma = n * |q| * mu for a carrier density `density`.
+    pub fn conductivity(&self, density: f64, charge: f64) -> f64 {
+        density * charge.abs() * self.mobility
+    }
+}
+
+// Deterministic solver for df/dt + div_v((a - supp * v) f) = C[f] on a velocity grid,
+// a noise-free reference for the drift and velocity histograms of the particle simulation.
+// The field term uses first order upwind fluxes, so the drift balance is exact up to the
+// carriers leaving the grid, which are put back by renormalising every step.
+// Dirac species are not supported since their acceleration depends on the momentum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoltzmannSolver {
+    pub grid: VelocityGrid,
+    pub kernel: CollisionKernel,
+    pub mass: f64,
+    pub charge: f64,
+    pub field: f64,
+    pub acc: Vector2<f64>,
+    pub supp: f64,
+    pub distribution: Vec<f64>,
+    equilibrium: Vec<f64>,
+}
+
+impl BoltzmannSolver {
+    // Starts from the Maxwellian at `temperature`, `acc` is the reference electron acceleration
+    // as in `CrystalStructure::update`.
+    pub fn new(
+        grid: VelocityGrid,
+        species: &Species,
+        kernel: CollisionKernel,
+        acc: f64,
+        supp: f64,
+        temperature: f64,
+    ) -> Self {
+        let field = field_from_acc(acc);
+        let origin = Vector2::new(0.0, 0.0);
+        let equilibrium = match kernel {
+            CollisionKernel::RelaxationTime { temperature, .. } => {
+                grid.maxwellian(temperature, species.mass, origin)
+            }
+            CollisionKernel::HardDisk { .. } => Vec::new(),
+        };
+        BoltzmannSolver {
+            grid,
+            kernel,
+            mass: species.mass,
+            charge: species.charge,
+            field,
+            acc: species.acceleration(field),
+            supp,
+            distribution: grid.maxwellian(temperature, species.mass, origin),
+            equilibrium,
+        }
+    }
+
+    // Largest stable explicit step.
+    pub fn time_step(&self) -> f64 {
+        let dv = self.grid.spacing();
+        let v_max = self.grid.v_max;
+        let flow = self.acc.x.abs() + self.acc.y.abs() + 2.0 * self.supp * v_max;
+        let rate = match self.kernel {
+            CollisionKernel::RelaxationTime { tau, .. } => 1.0 / tau,
+            CollisionKernel::HardDisk { density, radius } => {
+                density * 2.0 * radius * v_max * 2.0f64.sqrt()
+            }
+        };
+        COURANT / (flow / dv + rate)
+    }
+
+    // Rate of change of the distribution, df/dt.
+    pub fn derivative(&self, f: &[f64]) -> Vec<f64> {
+        let mut df = self.collisions(f);
+        let grid = &self.grid;
+        let (n, dv) = (grid.cells, grid.spacing());
+        // Upwind flux through the face between cells `from` and `to`, `speed` taken at the face.
+        let mut transfer = |from: usize, to: Option<usize>, speed: f64, upwind: f64| {
+            let flux = speed * upwind / dv;
+            df[from] -= flux;
+            if let Some(to) = to {
+                df[to] += flux;
+            }
+        };
+        for j in 0..n {
+            for i in 0..n {
+                let here = grid.index(i, j);
+                let vel = grid.velocity(i, j);
+                // Faces on the +x and +y sides, the outer grid faces only let carriers out.
+                let face_x = vel.x + dv / 2.0;
+                let speed = self.acc.x - self.supp * face_x;
+                let next = if i + 1 < n {
+                    Some(grid.index(i + 1, j))
+                } else {
+                    None
+                };
+                if speed > 0.0 {
+                    transfer(here, next, speed, f[here]);
+                } else if let Some(next) = next {
+                    transfer(next, Some(here), -speed, f[next]);
+                }
+                if i == 0 {
+                    let speed = self.acc.x - self.supp * (vel.x - dv / 2.0);
+                    if speed < 0.0 {
+                        transfer(here, None, -speed, f[here]);
+                    }
+                }
+
+                let face_y = vel.y + dv / 2.0;
+                let speed = self.acc.y - self.supp * face_y;
+                let next = if j + 1 < n {
+                    Some(grid.index(i, j + 1))
+                } else {
+                    None
+                };
+                if speed > 0.0 {
+                    transfer(here, next, speed, f[here]);
+                } else if let Some(next) = next {
+                    transfer(next, Some(here), -speed, f[next]);
+                }
+                if j == 0 {
+                    let speed = self.acc.y - self.supp * (vel.y - dv / 2.0);
+                    if speed < 0.0 {
+                        transfer(here, None, -speed, f[here]);
+                    }
+                }
+            }
+        }
+        df
+    }
+
+    fn collisions(&self, f: &[f64]) -> Vec<f64> {
+        match self.kernel {
+            CollisionKernel::RelaxationTime { tau, .. } => f
+                .iter()
+                .zip(self.equilibrium.iter())
+                .map(|(value, equilibrium)| (equilibrium - value) / tau)
+                .collect(),
+            CollisionKernel::HardDisk { density, radius } => {
+                let angles: Vec<(f64, f64)> = (0..SCATTERING_ANGLES)
+                    .map(|k| {
+                        let theta = -PI + (k as f64 + 0.5) * 2.0 * PI / SCATTERING_ANGLES as f64;
+                        (theta, (theta / 2.0).sin().abs())
+                    })
+                    .collect();
+                // Weights scaled to the exact total cross-section, so isotropic carriers stay put.
+                let norm = 2.0 * radius / angles.iter().map(|(_, weight)| weight).sum::<f64>();
+                self.grid
+                    .velocities()
+                    .zip(f.iter())
+                    .map(|(vel, value)| {
+                        let gain: f64 = angles
+                            .iter()
+                            .map(|(theta, weight)| {
+                                let (sin, cos) = theta.sin_cos();
+                                let incoming = Vector2::new(
+                                    cos * vel.x - sin * vel.y,
+                                    sin * vel.x + cos * vel.y,
+                                );
+                                weight * self.grid.interpolate(f, incoming)
+                            })
+                            .sum();
+                        density * vel.magnitude() * (norm * gain - 2.0 * radius * value)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // Advances the distribution by `time` and returns how fast it still changes,
+    // the L1 norm of df/dt after renormalising.
+    pub fn step(&mut self, time: f64) -> f64 {
+        let df = self.derivative(&self.distribution);
+        let previous = self.distribution.clone();
+        self.distribution
+            .iter_mut()
+            .zip(df.iter())
+            .for_each(|(value, change)| *value = (*value + change * time).max(0.0));
+        self.grid.normalise(&mut self.distribution);
+        let change: f64 = self
+            .distribution
+            .iter()
+            .zip(previous.iter())
+            .map(|(value, previous)| (value - previous).abs())
+            .sum();
+        change * self.grid.spacing().powi(2) / time
+    }
+
+    // Steps until that change drops below `tolerance` or `max_iterations` are used.
+    pub fn solve(&mut self, max_iterations: usize, tolerance: f64) -> SteadyState {
+        let time = self.time_step();
+        let mut iterations = 0;
+        let mut converged = false;
+        while iterations < max_iterations && !converged {
+            converged = self.step(time) < tolerance;
+            iterations += 1;
+        }
+        SteadyState {
+            distribution: self.distribution.clone(),
+            drift_velocity: self.drift_velocity(),
+            mean_energy: self.mean_energy(),
+            mobility: self.mobility(),
+            iterations,
+            converged,
+        }
+    }
+
+    // Mean velocity along the field axis.
+    pub fn drift_velocity(&self) -> f64 {
+        self.grid.mean(&self.distribution, |vel| vel.x)
+    }
+
+    pub fn mean_energy(&self) -> f64 {
+        self.grid.mean(&self.distribution, |vel| {
+            0.5 * self.mass * vel.magnitude_squared()
+        })
+    }
+
+    // Drift velocity per unit field, positive for both electrons and holes.
+    pub fn mobility(&self) -> f64 {
+        if self.field == 0.0 {
+            return 0.0;
+        }
+        self.drift_velocity() * self.charge.signum() / self.field
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::random_normal;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn relaxation_time_drift_matches_drude() {
+        let grid = VelocityGrid::new(6.0, 60);
+        let kernel = CollisionKernel::RelaxationTime {
+            tau: 2.0,
+            temperature: 1.0,
+        };
+        let mut solver = BoltzmannSolver::new(grid, &Species::electron(), kernel, 0.1, 0.0, 1.0);
+        let state = solver.solve(10_000, 1e-10);
+
+        assert!(state.converged);
+        assert!((state.drift_velocity - 0.2).abs() < 1e-3);
+        assert!((state.mobility - 2.0).abs() < 1e-2);
+        assert!((state.conductivity(0.5, -1.0) - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn hard_disks_relax_momentum() {
+        let grid = VelocityGrid::new(6.0, 60);
+        let kernel = CollisionKernel::HardDisk {
+            density: 0.01,
+            radius: 2.0,
+        };
+        let solver = BoltzmannSolver::new(grid, &Species::electron(), kernel, 0.0, 0.0, 1.0);
+        let isotropic = grid.maxwellian(1.0, 1.0, Vector2::new(0.0, 0.0));
+        let shifted = grid.maxwellian(1.0, 1.0, Vector2::new(0.3, 0.0));
+
+        let change = |f: &[f64], weight: &dyn Fn(Vector2<f64>) -> f64| {
+            grid.mean(&solver.collisions(f), weight)
+        };
+        // Number and speed are conserved, momentum decays with the transport cross-section 8R / 3.
+        assert!(change(&isotropic, &|vel| vel.magnitude()).abs() < 1e-3);
+        assert!(change(&shifted, &|_| 1.0).abs() < 1e-3);
+        let expected = -0.01 * 8.0 * 2.0 / 3.0 * grid.mean(&shifted, |vel| vel.magnitude() * vel.x);
+        let momentum = change(&shifted, &|vel| vel.x);
+        assert!((momentum - expected).abs() < 0.03 * expected.abs());
+    }
+
+    #[test]
+    fn histogram_matches_sampled_maxwellian() {
+        seed_random(71);
+        let grid = VelocityGrid::new(5.0, 20);
+        let samples = (0..50_000).map(|_| Vector2::new(random_normal(), random_normal()));
+        let histogram = grid.histogram(samples);
+        let exact = grid.maxwellian(1.0, 1.0, Vector2::new(0.0, 0.0));
+        let error: f64 = grid
+            .marginal_x(&histogram)
+            .iter()
+            .zip(grid.marginal_x(&exact).iter())
+            .map(|(sampled, exact)| (sampled - exact).abs() * grid.spacing())
+            .sum();
+
+        assert!(error < 0.03);
+    }
+}
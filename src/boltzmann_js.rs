@@ -0,0 +1,103 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    boltzmann::{BoltzmannSolver, CollisionKernel, SteadyState, VelocityGrid},
+    species::Species,
+};
+
+#[wasm_bindgen(js_name = BoltzmannSolver)]
+pub struct BoltzmannSolverJs {
+    solver: BoltzmannSolver,
+    state: Option<SteadyState>,
+}
+
+#[wasm_bindgen(js_class = BoltzmannSolver)]
+impl BoltzmannSolverJs {
+    // Electrons relaxing towards the Maxwellian at `temperature` with mean free time `tau`.
+    pub fn relaxation_time(
+        v_max: f64,
+        cells: usize,
+        tau: f64,
+        temperature: f64,
+        acc: f64,
+        supp: f64,
+    ) -> BoltzmannSolverJs {
+        let kernel = CollisionKernel::RelaxationTime { tau, temperature };
+        BoltzmannSolverJs::new(v_max, cells, kernel, acc, supp, temperature)
+    }
+
+    // Electrons scattering off fixed disks, `radius` is the contact distance of electron and ion.
+    pub fn hard_disk(
+        v_max: f64,
+        cells: usize,
+        density: f64,
+        radius: f64,
+        acc: f64,
+        supp: f64,
+        temperature: f64,
+    ) -> BoltzmannSolverJs {
+        let kernel = CollisionKernel::HardDisk { density, radius };
+        BoltzmannSolverJs::new(v_max, cells, kernel, acc, supp, temperature)
+    }
+
+    // Returns whether the steady state was reached.
+    pub fn solve(&mut self, max_iterations: usize, tolerance: f64) -> bool {
+        let state = self.solver.solve(max_iterations, tolerance);
+        let converged = state.converged;
+        self.state = Some(state);
+        converged
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn iterations(&self) -> usize {
+        self.state.as_ref().map_or(0, |state| state.iterations)
+    }
+
+    pub fn drift_velocity(&self) -> f64 {
+        self.solver.drift_velocity()
+    }
+
+    pub fn mean_energy(&self) -> f64 {
+        self.solver.mean_energy()
+    }
+
+    pub fn mobility(&self) -> f64 {
+        self.solver.mobility()
+    }
+
+    pub fn conductivity(&self, density: f64) -> f64 {
+        density * self.solver.charge.abs() * self.solver.mobility()
+    }
+
+    // Row by row, cell (i, j) is centred on (-v_max + (i + 1/2) dv, -v_max + (j + 1/2) dv).
+    pub fn distribution(&self) -> Vec<f64> {
+        self.solver.distribution.clone()
+    }
+
+    pub fn marginal_x(&self) -> Vec<f64> {
+        self.solver.grid.marginal_x(&self.solver.distribution)
+    }
+}
+
+impl BoltzmannSolverJs {
+    fn new(
+        v_max: f64,
+        cells: usize,
+        kernel: CollisionKernel,
+        acc: f64,
+        supp: f64,
+        temperature: f64,
+    ) -> BoltzmannSolverJs {
+        BoltzmannSolverJs {
+            solver: BoltzmannSolver::new(
+                VelocityGrid::new(v_max, cells),
+                &Species::electron(),
+                kernel,
+                acc,
+                supp,
+                temperature,
+            ),
+            state: None,
+        }
+    }
+}
@@ -1,6 +1,9 @@
-use nalgebra::Vector2;
+use nalgebra::{Matrix2, Vector2};
 
-use crate::{cfg::ELECTRON_RADIUS, electron::Electron, utils::calc_time_to_border_collision};
+use crate::{
+    electron::Electron,
+    utils::{calc_time_to_border_collision, tensor_collision},
+};
 
 #[derive(Clone, Copy)]
 pub enum BorderType {
@@ -26,41 +29,59 @@ impl Border {
         }
     }
 
+    // The vertical borders wrap carriers around instead of reflecting them.
+    pub fn is_periodic(&self) -> bool {
+        self.a == 1.0 && self.b == 0.0
+    }
+
     pub fn bounce(&mut self, other: &mut Electron, width: f64) {
-        if self.a == 1.0 && self.b == 0.0 {
+        if self.is_periodic() {
             match self.border_type {
                 BorderType::Inner => other.pos.x -= width,
                 BorderType::Outer => other.pos.x += width,
             }
         } else if self.a == 0.0 && self.b == 1.0 {
-            other.vel.y *= -1.0;
+            if other.mass_tensor.is_some() {
+                let normal = Vector2::new(0.0, 1.0);
+                other.vel = tensor_collision(
+                    other.vel,
+                    other.inverse_mass(),
+                    Vector2::new(0.0, 0.0),
+                    Matrix2::zeros(),
+                    normal,
+                )
+                .0;
+            } else {
+                other.vel.y *= -1.0;
+            }
         } else {
             panic!("Not implemented: Invalid border");
         }
     }
 
-    pub fn calc_time_to_collision(&self, other: &Electron, width: f64) -> f64 {
-        let (dist, vel, acc) = if self.a == 1.0 && self.b == 0.0 {
-            let pos = other.pos.x;
+    // Distance a carrier at `pos` still has to travel towards the border before it is hit,
+    // negative once it is past it.
+    pub fn gap(&self, pos: Vector2<f64>, radius: f64, width: f64) -> f64 {
+        if self.a == 1.0 && self.b == 0.0 {
             match self.border_type {
-                BorderType::Inner => (width + ELECTRON_RADIUS - pos, other.vel.x, other.acc.x),
-                BorderType::Outer => (pos + ELECTRON_RADIUS - self.c, -other.vel.x, -other.acc.x),
+                BorderType::Inner => width + radius - pos.x,
+                BorderType::Outer => pos.x + radius - self.c,
             }
         } else {
-            let a = Vector2::new(self.a, self.b);
-            let pos = other.pos.dot(&a);
+            let pos = pos.dot(&Vector2::new(self.a, self.b));
             match self.border_type {
-                BorderType::Inner => (
-                    self.c - ELECTRON_RADIUS - pos,
-                    other.vel.dot(&a),
-                    other.acc.dot(&a),
-                ),
-                BorderType::Outer => (
-                    pos - ELECTRON_RADIUS - self.c,
-                    -other.vel.dot(&a),
-                    -other.acc.dot(&a),
-                ),
+                BorderType::Inner => self.c - radius - pos,
+                BorderType::Outer => pos - radius - self.c,
             }
+        }
+    }
+
+    pub fn calc_time_to_collision(&self, other: &Electron, width: f64) -> f64 {
+        let dist = self.gap(other.pos, other.radius, width);
+        let a = Vector2::new(self.a, self.b);
+        let (vel, acc) = match self.border_type {
+            BorderType::Inner => (other.vel.dot(&a), other.acc.dot(&a)),
+            BorderType::Outer => (-other.vel.dot(&a), -other.acc.dot(&a)),
         };
         calc_time_to_border_collision(dist, vel, acc)
     }
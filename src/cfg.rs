@@ -1,7 +1,7 @@
 pub static ION_RADIUS: f64 = 10.0;
 pub static ELECTRON_RADIUS: f64 = 3.0;
-pub static ION_ELEC_RADIUS: f64 = ION_RADIUS + ELECTRON_RADIUS;
-pub static ELEC_ELEC_RADIUS: f64 = 2.0 * ELECTRON_RADIUS;
+pub static ELECTRON_MASS: f64 = 1.0;
+pub static ELECTRON_CHARGE: f64 = -1.0;
 
 pub static INIT_ITERATIONS: usize = 1000;
 pub static EPSILON: f64 = 0.00001;
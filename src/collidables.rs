@@ -1,8 +1,8 @@
 use std::{cell::RefCell, rc::Weak};
 
 use crate::{
-    border::Border, collidable::Collidable, crystal_structure::RcRefCell, electron::Electron,
-    ion::Ion,
+    border::Border, collidable::Collidable, collision_stats::CollisionKind,
+    crystal_structure::RcRefCell, electron::Electron, impurity::Impurity, ion::Ion, trap::Trap,
 };
 use std::rc::Rc;
 
@@ -11,6 +11,10 @@ pub enum Collidables {
     Border(Weak<RefCell<Border>>),
     Ion(Weak<RefCell<Ion>>),
     Electron(Weak<RefCell<Electron>>),
+    Trap(Weak<RefCell<Trap>>),
+    Impurity(Weak<RefCell<Impurity>>),
+    // End of the look-ahead of a Dirac carrier, its predictions are redone there.
+    Horizon,
 }
 
 impl Collidables {
@@ -26,6 +30,14 @@ impl Collidables {
         Collidables::Electron(Rc::downgrade(electron))
     }
 
+    pub fn new_t(trap: &Rc<RefCell<Trap>>) -> Self {
+        Collidables::Trap(Rc::downgrade(trap))
+    }
+
+    pub fn new_c(impurity: &Rc<RefCell<Impurity>>) -> Self {
+        Collidables::Impurity(Rc::downgrade(impurity))
+    }
+
     pub fn empty() -> Self {
         Collidables::Border(Weak::new())
     }
@@ -47,27 +59,49 @@ impl Collidables {
                 .unwrap()
                 .borrow()
                 .calc_time_to_collision(electron),
+            Collidables::Trap(trap) => trap
+                .upgrade()
+                .unwrap()
+                .borrow()
+                .calc_time_to_collision(electron),
+            Collidables::Impurity(impurity) => impurity
+                .upgrade()
+                .unwrap()
+                .borrow()
+                .calc_time_to_collision(electron),
+            Collidables::Horizon => f64::INFINITY,
         }
     }
 
+    // Periodic crossings move the carrier without scattering it and are left out of its statistics.
     pub fn resolve_collision(&self, electron: &RcRefCell<Electron>, width: f64) {
-        electron.borrow_mut().update_stats();
         match self {
-            Collidables::Border(border) => border
-                .upgrade()
-                .unwrap()
-                .borrow_mut()
-                .bounce(&mut electron.borrow_mut(), width),
-            Collidables::Ion(ion) => ion
-                .upgrade()
-                .unwrap()
-                .borrow_mut()
-                .bounce(&mut electron.borrow_mut()),
-            Collidables::Electron(other) => other
-                .upgrade()
-                .unwrap()
-                .borrow_mut()
-                .bounce(&mut electron.borrow_mut()),
+            Collidables::Border(border) => {
+                let border = border.upgrade().unwrap();
+                if !border.borrow().is_periodic() {
+                    electron.borrow_mut().record_collision(CollisionKind::Wall);
+                }
+                border
+                    .borrow_mut()
+                    .bounce(&mut electron.borrow_mut(), width);
+            }
+            Collidables::Ion(ion) => {
+                electron.borrow_mut().record_collision(CollisionKind::Ion);
+                ion.upgrade()
+                    .unwrap()
+                    .borrow_mut()
+                    .bounce(&mut electron.borrow_mut());
+            }
+            Collidables::Electron(other) => {
+                let other = other.upgrade().unwrap();
+                electron
+                    .borrow_mut()
+                    .record_collision(CollisionKind::Electron);
+                other.borrow_mut().record_collision(CollisionKind::Electron);
+                other.borrow_mut().bounce(&mut electron.borrow_mut());
+            }
+            // Captures and impurity transits change the carrier's state and are handled by the structure.
+            Collidables::Trap(_) | Collidables::Impurity(_) | Collidables::Horizon => {}
         };
     }
 }
@@ -84,6 +118,13 @@ impl std::cmp::PartialEq for Collidables {
             (Collidables::Ion(ion1), Collidables::Ion(ion2)) => {
                 ion1.upgrade().unwrap().borrow().pos == ion2.upgrade().unwrap().borrow().pos
             }
+            (Collidables::Trap(trap1), Collidables::Trap(trap2)) => {
+                trap1.upgrade().unwrap().borrow().pos == trap2.upgrade().unwrap().borrow().pos
+            }
+            (Collidables::Impurity(impurity1), Collidables::Impurity(impurity2)) => {
+                impurity1.upgrade().unwrap().borrow().pos
+                    == impurity2.upgrade().unwrap().borrow().pos
+            }
             (Collidables::Border(border1), Collidables::Border(border2)) => {
                 border1.upgrade().unwrap().borrow().a == border2.upgrade().unwrap().borrow().a
                     && border1.upgrade().unwrap().borrow().b
@@ -91,6 +132,7 @@ impl std::cmp::PartialEq for Collidables {
                     && border1.upgrade().unwrap().borrow().c
                         == border2.upgrade().unwrap().borrow().c
             }
+            (Collidables::Horizon, Collidables::Horizon) => true,
             _ => false,
         }
     }
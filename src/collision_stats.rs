@@ -0,0 +1,162 @@
+// Partner of a scattering event. Crossing a periodic border is not a collision and has no kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionKind {
+    Ion,
+    Electron,
+    Wall,
+    Impurity,
+}
+
+impl CollisionKind {
+    // Kinds by the names used in the JS bindings.
+    pub fn from_name(name: &str) -> Option<CollisionKind> {
+        match name {
+            "ion" => Some(CollisionKind::Ion),
+            "electron" => Some(CollisionKind::Electron),
+            "wall" => Some(CollisionKind::Wall),
+            "impurity" => Some(CollisionKind::Impurity),
+            _ => None,
+        }
+    }
+}
+
+// Number of collisions of one kind and the mean time between them.
+// The first interval starts when the carrier is created.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FreeTime {
+    pub count: i32,
+    pub mean: f64,
+    since: f64,
+}
+
+impl FreeTime {
+    fn record(&mut self) {
+        let total = self.mean * self.count as f64;
+        self.count += 1;
+        self.mean = (total + self.since) / self.count as f64;
+        self.since = 0.0;
+    }
+
+    // Pools the intervals of two carriers, each one weighted by its count.
+    fn merge(self, other: FreeTime) -> FreeTime {
+        let count = self.count + other.count;
+        if count == 0 {
+            return FreeTime::default();
+        }
+        FreeTime {
+            count,
+            mean: (self.mean * self.count as f64 + other.mean * other.count as f64) / count as f64,
+            since: 0.0,
+        }
+    }
+}
+
+// Collision counters of a carrier, or of a whole structure once merged, broken down by partner.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CollisionStats {
+    pub ion: FreeTime,
+    pub electron: FreeTime,
+    pub wall: FreeTime,
+    pub impurity: FreeTime,
+}
+
+impl CollisionStats {
+    pub fn advance(&mut self, time: f64) {
+        self.ion.since += time;
+        self.electron.since += time;
+        self.wall.since += time;
+        self.impurity.since += time;
+    }
+
+    pub fn record(&mut self, kind: CollisionKind) {
+        self.get_mut(kind).record();
+    }
+
+    pub fn get(&self, kind: CollisionKind) -> FreeTime {
+        match kind {
+            CollisionKind::Ion => self.ion,
+            CollisionKind::Electron => self.electron,
+            CollisionKind::Wall => self.wall,
+            CollisionKind::Impurity => self.impurity,
+        }
+    }
+
+    fn get_mut(&mut self, kind: CollisionKind) -> &mut FreeTime {
+        match kind {
+            CollisionKind::Ion => &mut self.ion,
+            CollisionKind::Electron => &mut self.electron,
+            CollisionKind::Wall => &mut self.wall,
+            CollisionKind::Impurity => &mut self.impurity,
+        }
+    }
+
+    pub fn merge(self, other: CollisionStats) -> CollisionStats {
+        CollisionStats {
+            ion: self.ion.merge(other.ion),
+            electron: self.electron.merge(other.electron),
+            wall: self.wall.merge(other.wall),
+            impurity: self.impurity.merge(other.impurity),
+        }
+    }
+
+    pub fn total(&self) -> i32 {
+        self.ion.count + self.electron.count + self.wall.count + self.impurity.count
+    }
+
+    // Mean time between collisions of any kind, from the rates: 1/tau = sum of 1/tau_k.
+    pub fn mean_free_time(&self) -> f64 {
+        let rate = [self.ion, self.electron, self.wall, self.impurity]
+            .iter()
+            .filter(|free_time| free_time.count > 0 && free_time.mean > 0.0)
+            .fold(0.0, |rate, free_time| rate + 1.0 / free_time.mean);
+        if rate == 0.0 {
+            return 0.0;
+        }
+        1.0 / rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_keep_separate_clocks() {
+        let mut stats = CollisionStats::default();
+        stats.advance(2.0);
+        stats.record(CollisionKind::Ion);
+        stats.advance(1.0);
+        stats.record(CollisionKind::Wall);
+        stats.advance(3.0);
+        stats.record(CollisionKind::Ion);
+
+        assert_eq!(stats.ion.count, 2);
+        assert_eq!(stats.ion.mean, 3.0);
+        assert_eq!(stats.wall.count, 1);
+        assert_eq!(stats.wall.mean, 3.0);
+        assert_eq!(stats.electron.count, 0);
+        assert_eq!(stats.total(), 3);
+        assert_eq!(stats.mean_free_time(), 1.5);
+    }
+
+    #[test]
+    fn merge_weights_by_count() {
+        let mut first = CollisionStats::default();
+        first.advance(1.0);
+        first.record(CollisionKind::Electron);
+        let mut second = CollisionStats::default();
+        for _ in 0..3 {
+            second.advance(5.0);
+            second.record(CollisionKind::Electron);
+        }
+
+        let merged = first.merge(second);
+        assert_eq!(merged.electron.count, 4);
+        assert_eq!(merged.electron.mean, 4.0);
+        assert_eq!(
+            CollisionKind::from_name("electron"),
+            Some(CollisionKind::Electron)
+        );
+        assert_eq!(CollisionKind::from_name("periodic"), None);
+    }
+}
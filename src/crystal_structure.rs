@@ -1,21 +1,83 @@
-use std::cell::RefCell;
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
 
-use std::f64::INFINITY;
 use std::rc::{Rc, Weak};
 
 use nalgebra::Vector2;
 
+use crate::boltzmann::VelocityGrid;
 use crate::border::{Border, BorderType};
-use crate::cfg::{ELECTRON_RADIUS, ELEC_ELEC_RADIUS, INIT_ITERATIONS, ION_ELEC_RADIUS, ION_RADIUS};
+use crate::cfg::{EPSILON, INIT_ITERATIONS, ION_RADIUS};
+use crate::collidable::Collidable;
 use crate::collidables::Collidables;
+use crate::collision_stats::{CollisionKind, CollisionStats};
+use crate::dirac::{first_contact, sample_step, HORIZON};
 use crate::electron::Electron;
-use crate::ion::Ion;
-
-use crate::utils::set_panic_hook;
-use js_sys::Math::random;
+#[cfg(feature = "parallel")]
+use crate::electron::Kinematics;
+use crate::generation::{CarrierStats, GenerationRecombination, ImpactIonisation};
+use crate::impurity::Impurity;
+use crate::ion::{Ion, ORBIT_HORIZON};
+use crate::ion_import::{self, IonImportError, IonRecord};
+use crate::langevin::Langevin;
+use crate::placement::{grid_sites, shuffle, InitStrategy, PlacementError};
+use crate::scattering::{
+    momentum_relaxing_collision, random_angle_collision, ElectronScattering, ScatteringLaw,
+};
+use crate::soa::{IonList, ParticleStore, Target, WallList};
+use crate::sommerfeld::{FermiDirac, PauliBlocking};
+use crate::species::{field_from_acc, Species};
+use crate::thermal::{OpticalPhonon, PhononResampling, ThermalVibration};
+use crate::thermostat::{self, Thermostat};
+use crate::trap::Trap;
+
+use crate::utils::{random, set_panic_hook};
 
 pub type RcRefCell<T> = Rc<RefCell<T>>;
 
+// Structures with fewer carriers are always predicted serially, the threads would not pay off.
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_CARRIERS: usize = 256;
+
+// Structures with fewer carriers are predicted by the scalar loop, gathering the columns would not pay off.
+const BATCHED_MIN_CARRIERS: usize = 64;
+
+// Index of the partner found by the parallel prediction, turned into a `Collidables` afterwards.
+#[cfg(feature = "parallel")]
+#[derive(Clone, Copy)]
+enum Partner {
+    Border(usize),
+    Ion(usize),
+    Electron(usize),
+}
+
+// Whether a carrier of radius `radius` at `pos` lies inside the box and keeps clear of the
+// `(position, radius)` pairs of the ions and carriers.
+fn keeps_clear(
+    pos: Vector2<f64>,
+    radius: f64,
+    x_size: f64,
+    y_size: f64,
+    mut ions: impl Iterator<Item = (Vector2<f64>, f64)>,
+    mut carriers: impl Iterator<Item = (Vector2<f64>, f64)>,
+) -> bool {
+    let inside =
+        pos.x >= radius && pos.y >= radius && pos.x <= x_size - radius && pos.y <= y_size - radius;
+    inside
+        && ions
+            .all(|(ion_pos, ion_radius)| (ion_pos - pos).magnitude() >= 2.0 * radius + ion_radius)
+        && carriers
+            .all(|(el_pos, el_radius)| (el_pos - pos).magnitude() >= 2.0 * (radius + el_radius))
+}
+
+// Number of carriers of one species that crossed the periodic x borders.
+// `left` counts crossings in the +x direction, `right` those in the -x direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Flux {
+    pub left: i32,
+    pub right: i32,
+}
+
 pub struct CrystalStructure {
     pub x_size: f64,
     pub y_size: f64,
@@ -24,10 +86,42 @@ pub struct CrystalStructure {
     pub borders: Vec<RcRefCell<Border>>,
     pub ions: Vec<RcRefCell<Ion>>,
     pub electrons: Vec<RcRefCell<Electron>>,
+    pub species: Vec<Species>,
+    pub traps: Vec<RcRefCell<Trap>>,
+    pub impurities: Vec<RcRefCell<Impurity>>,
     pub next_collision: (Weak<RefCell<Electron>>, Collidables),
     pub time_to_bounce: f64,
-    pub elec_left: i32,
-    pub elec_right: i32,
+    pub flux: Vec<Flux>,
+    pub time: f64,
+    pub vibration: Option<ThermalVibration>,
+    next_resample: f64,
+    pub generation: Option<GenerationRecombination>,
+    next_generation: f64,
+    pub impact_ionisation: Option<ImpactIonisation>,
+    pub carrier_stats: CarrierStats,
+    pub optical_phonon: Option<OpticalPhonon>,
+    pub phonons_emitted: i32,
+    // Ion species without an entry keep the default elastic bounce.
+    pub scattering_laws: HashMap<u32, Box<dyn ScatteringLaw>>,
+    pub electron_scattering: ElectronScattering,
+    // Momentum conserving carrier-carrier collisions and artificial momentum relaxing events.
+    pub electron_collisions: i32,
+    pub momentum_relaxing_events: i32,
+    pub impurity_scatterings: i32,
+    pub pauli_blocking: Option<PauliBlocking>,
+    // Scattering events rejected because the final momentum cell was full.
+    pub pauli_blocked: i32,
+    pub thermostat: Option<Thermostat>,
+    pub langevin: Option<Langevin>,
+    next_langevin: f64,
+    // Drag of the last tick, the Langevin noise strength follows it.
+    supp: f64,
+    // Predicts collisions and searches free placement sites on all cores when built with the
+    // `parallel` feature. Off by default, callers opt in.
+    pub parallel: bool,
+    // Predicts wall and ion contacts with the struct-of-arrays kernels of `soa`. Their times agree
+    // with the scalar solvers to rounding only, so runs differ in the last bits when it is set.
+    pub batched: bool,
 }
 
 impl CrystalStructure {
@@ -38,6 +132,39 @@ impl CrystalStructure {
         init_velocity: f64,
         num_electrons: i32,
     ) -> CrystalStructure {
+        let mut crystal_structure = CrystalStructure::empty(x_size, y_size, ion_distance);
+        crystal_structure.init_ions();
+        crystal_structure.init_electrons(init_velocity, num_electrons);
+        crystal_structure
+    }
+
+    // Builds the structure around externally supplied ion sites instead of the regular lattice.
+    // The sites are validated before any electron is placed.
+    pub fn with_ions(
+        x_size: f64,
+        y_size: f64,
+        ions: &[IonRecord],
+        init_velocity: f64,
+        num_electrons: i32,
+    ) -> Result<CrystalStructure, IonImportError> {
+        ion_import::validate(ions, x_size, y_size)?;
+
+        let mut crystal_structure = CrystalStructure::empty(x_size, y_size, 0.0);
+        crystal_structure.ions = ions
+            .iter()
+            .map(|record| {
+                Rc::new(RefCell::new(Ion::of_species(
+                    record.pos,
+                    record.radius(),
+                    record.species.unwrap_or(0),
+                )))
+            })
+            .collect();
+        crystal_structure.init_electrons(init_velocity, num_electrons);
+        Ok(crystal_structure)
+    }
+
+    fn empty(x_size: f64, y_size: f64, ion_distance: f64) -> CrystalStructure {
         let mut crystal_structure = CrystalStructure {
             x_size,
             y_size,
@@ -46,17 +173,37 @@ impl CrystalStructure {
             borders: Vec::new(),
             ions: Vec::new(),
             electrons: Vec::new(),
+            species: vec![Species::electron()],
+            traps: Vec::new(),
+            impurities: Vec::new(),
             next_collision: (Weak::new(), Collidables::empty()),
             time_to_bounce: f64::INFINITY,
-            elec_left: 0,
-            elec_right: 0,
+            flux: vec![Flux::default()],
+            time: 0.0,
+            vibration: None,
+            next_resample: f64::INFINITY,
+            generation: None,
+            next_generation: f64::INFINITY,
+            impact_ionisation: None,
+            carrier_stats: CarrierStats::default(),
+            optical_phonon: None,
+            phonons_emitted: 0,
+            scattering_laws: HashMap::new(),
+            electron_scattering: ElectronScattering::default(),
+            electron_collisions: 0,
+            momentum_relaxing_events: 0,
+            impurity_scatterings: 0,
+            pauli_blocking: None,
+            pauli_blocked: 0,
+            thermostat: None,
+            langevin: None,
+            next_langevin: f64::INFINITY,
+            supp: 0.0,
+            parallel: false,
+            batched: false,
         };
         set_panic_hook();
         crystal_structure.init_borders();
-        crystal_structure.init_ions();
-        crystal_structure.init_electrons(init_velocity, num_electrons);
-
-        crystal_structure.update_collidables();
         crystal_structure
     }
 
@@ -87,225 +234,2388 @@ impl CrystalStructure {
         ))));
     }
 
-    fn init_ions(&mut self) {
-        let mut x = ((self.x_size - 2.0 * ION_RADIUS) % self.ion_distance) / 2.0 + ION_RADIUS;
-        while x + ION_RADIUS <= self.x_size {
-            let mut y = ((self.y_size - 2.0 * ION_RADIUS) % self.ion_distance) / 2.0 + ION_RADIUS;
-            while y + ION_RADIUS <= self.y_size {
-                self.ions
-                    .push(Rc::new(RefCell::new(Ion::new(Vector2::new(x, y)))));
-                y += self.ion_distance;
-            }
-            x += self.ion_distance;
+    fn init_ions(&mut self) {
+        let mut x = ((self.x_size - 2.0 * ION_RADIUS) % self.ion_distance) / 2.0 + ION_RADIUS;
+        while x + ION_RADIUS <= self.x_size {
+            let mut y = ((self.y_size - 2.0 * ION_RADIUS) % self.ion_distance) / 2.0 + ION_RADIUS;
+            while y + ION_RADIUS <= self.y_size {
+                self.ions
+                    .push(Rc::new(RefCell::new(Ion::new(Vector2::new(x, y)))));
+                y += self.ion_distance;
+            }
+            x += self.ion_distance;
+        }
+    }
+
+    fn init_electrons(&mut self, init_velocity: f64, num_electrons: i32) {
+        self.add_carriers(0, num_electrons, init_velocity);
+    }
+
+    // Bipolar structure holding electrons and holes of the given effective mass.
+    // Holes are registered as species 1.
+    pub fn semiconductor(
+        x_size: f64,
+        y_size: f64,
+        ion_distance: f64,
+        init_velocity: f64,
+        num_electrons: i32,
+        num_holes: i32,
+        hole_mass: f64,
+    ) -> CrystalStructure {
+        let mut crystal_structure =
+            CrystalStructure::new(x_size, y_size, ion_distance, init_velocity, num_electrons);
+        let holes = crystal_structure.add_species(Species::hole(hole_mass));
+        crystal_structure.add_carriers(holes, num_holes, init_velocity);
+        crystal_structure
+    }
+
+    // Registers a new carrier species and returns its id.
+    pub fn add_species(&mut self, species: Species) -> usize {
+        self.species.push(species);
+        self.flux.push(Flux::default());
+        self.species.len() - 1
+    }
+
+    // Places carriers of an existing species at random free positions with random directions.
+    // Carriers that do not fit after `INIT_ITERATIONS` attempts are skipped.
+    pub fn add_carriers(&mut self, species_id: usize, count: i32, init_velocity: f64) {
+        for _ in 0..count {
+            if let Some(electron) = self.place_carrier(species_id, init_velocity) {
+                self.electrons.push(Rc::new(RefCell::new(electron)));
+            }
+        }
+        self.update_collidables();
+    }
+
+    // Places carriers with kinetic energies drawn from the Fermi-Dirac distribution.
+    pub fn add_fermi_carriers(&mut self, species_id: usize, count: i32, distribution: FermiDirac) {
+        for _ in 0..count {
+            if let Some(mut electron) = self.place_carrier(species_id, 1.0) {
+                electron.set_kinetic_energy(distribution.sample_energy());
+                self.electrons.push(Rc::new(RefCell::new(electron)));
+            }
+        }
+        self.update_collidables();
+    }
+
+    fn place_carrier(&self, species_id: usize, init_velocity: f64) -> Option<Electron> {
+        let species = &self.species[species_id];
+        let radius = species.radius;
+
+        for _ in 0..INIT_ITERATIONS {
+            let x = (random() * (self.x_size - 2.0 * radius)) + radius;
+            let y = (random() * (self.y_size - 2.0 * radius)) + radius;
+            let angle = random() * 2.0 * std::f64::consts::PI;
+            let vel_x = angle.cos() * init_velocity;
+            let vel_y = angle.sin() * init_velocity;
+            let mut electron = Electron::of_species(
+                species_id,
+                species,
+                Vector2::new(x, y),
+                Vector2::new(vel_x, vel_y),
+            );
+            electron.set_field(field_from_acc(self.acc));
+            electron.born_at = self.time;
+
+            if self.is_free(electron.pos, radius) {
+                return Some(electron);
+            }
+        }
+        None
+    }
+
+    // Adds a batch of carriers of one species following `strategy`, returning how many were placed.
+    // Either every carrier is placed or none is, a full structure is reported instead.
+    pub fn place_carriers(
+        &mut self,
+        species_id: usize,
+        strategy: &InitStrategy,
+    ) -> Result<usize, PlacementError> {
+        let before = self.electrons.len();
+        let placed = match strategy {
+            InitStrategy::Random { count, velocities } => {
+                let radius = self.species[species_id].radius;
+                (0..*count).try_for_each(|placed| {
+                    let pos = self
+                        .random_free_position(radius)
+                        .or_else(|| self.free_sites(radius).first().copied())
+                        .ok_or(PlacementError::NoRoom {
+                            requested: *count,
+                            placed,
+                        })?;
+                    let vel = velocities.sample(self.species[species_id].mass);
+                    self.push_carrier(species_id, pos, vel);
+                    Ok(())
+                })
+            }
+            InitStrategy::Lattice { count, velocities } => {
+                if self.ion_distance <= 0.0 {
+                    return Err(PlacementError::NoLattice);
+                }
+                let radius = self.species[species_id].radius;
+                let offset = Vector2::new(self.ion_distance, self.ion_distance) / 2.0;
+                let mut sites: Vec<Vector2<f64>> = self
+                    .ions
+                    .iter()
+                    .map(|ion| ion.borrow().site + offset)
+                    .filter(|&site| self.is_free(site, radius))
+                    .collect();
+                if sites.len() < *count {
+                    return Err(PlacementError::NoRoom {
+                        requested: *count,
+                        placed: sites.len(),
+                    });
+                }
+                shuffle(&mut sites);
+                sites.into_iter().take(*count).for_each(|site| {
+                    let vel = velocities.sample(self.species[species_id].mass);
+                    self.push_carrier(species_id, site, vel);
+                });
+                Ok(())
+            }
+            InitStrategy::Beam {
+                count,
+                y,
+                width,
+                speed,
+            } => {
+                let radius = self.species[species_id].radius;
+                let mut sites: Vec<Vector2<f64>> = self
+                    .free_sites(radius)
+                    .into_iter()
+                    .filter(|site| (site.y - y).abs() <= width / 2.0)
+                    .collect();
+                sites.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+                if sites.len() < *count {
+                    return Err(PlacementError::NoRoom {
+                        requested: *count,
+                        placed: sites.len(),
+                    });
+                }
+                sites.into_iter().take(*count).for_each(|site| {
+                    self.push_carrier(species_id, site, Vector2::new(*speed, 0.0));
+                });
+                Ok(())
+            }
+            InitStrategy::Explicit(records) => {
+                records.iter().enumerate().try_for_each(|(index, record)| {
+                    let species = record.species.unwrap_or(species_id);
+                    if species >= self.species.len() {
+                        return Err(PlacementError::UnknownSpecies { index, species });
+                    }
+                    let radius = self.species[species].radius;
+                    let pos = record.pos;
+                    if !(pos.x >= radius
+                        && pos.y >= radius
+                        && pos.x <= self.x_size - radius
+                        && pos.y <= self.y_size - radius)
+                    {
+                        return Err(PlacementError::OutOfBox { index, pos });
+                    }
+                    if self.overlaps(pos, radius) {
+                        return Err(PlacementError::Overlap { index });
+                    }
+                    self.push_carrier(species, pos, record.vel);
+                    Ok(())
+                })
+            }
+        };
+        if let Err(err) = placed {
+            self.electrons.truncate(before);
+            return Err(err);
+        }
+        self.update_collidables();
+        Ok(self.electrons.len() - before)
+    }
+
+    fn push_carrier(&mut self, species_id: usize, pos: Vector2<f64>, vel: Vector2<f64>) {
+        let mut electron = Electron::of_species(species_id, &self.species[species_id], pos, vel);
+        electron.set_field(field_from_acc(self.acc));
+        electron.born_at = self.time;
+        self.electrons.push(Rc::new(RefCell::new(electron)));
+    }
+
+    fn random_free_position(&self, radius: f64) -> Option<Vector2<f64>> {
+        (0..INIT_ITERATIONS)
+            .map(|_| {
+                let x = (random() * (self.x_size - 2.0 * radius)) + radius;
+                let y = (random() * (self.y_size - 2.0 * radius)) + radius;
+                Vector2::new(x, y)
+            })
+            .find(|&pos| self.is_free(pos, radius))
+    }
+
+    // Every free node of a grid as fine as the clearance `is_free` keeps between carriers, in random order.
+    // It finds room wherever random trials keep missing the last gaps. The nodes are checked on all
+    // cores with the `parallel` feature; the random trials of `random_free_position` and
+    // `place_carrier` each depend on the carriers placed before them and stay serial.
+    fn free_sites(&self, radius: f64) -> Vec<Vector2<f64>> {
+        let ions: Vec<(Vector2<f64>, f64)> = self
+            .ions
+            .iter()
+            .map(|ion| {
+                let ion = ion.borrow();
+                (ion.pos, ion.radius)
+            })
+            .collect();
+        let carriers: Vec<(Vector2<f64>, f64)> = self
+            .electrons
+            .iter()
+            .map(|el| {
+                let el = el.borrow();
+                (el.pos, el.radius)
+            })
+            .collect();
+        let (x_size, y_size) = (self.x_size, self.y_size);
+        let free = |site: &Vector2<f64>| {
+            keeps_clear(
+                *site,
+                radius,
+                x_size,
+                y_size,
+                ions.iter().copied(),
+                carriers.iter().copied(),
+            )
+        };
+        let sites = grid_sites(self.x_size, self.y_size, 4.0 * radius, radius);
+
+        #[cfg(feature = "parallel")]
+        let mut sites: Vec<Vector2<f64>> = if self.parallel {
+            use rayon::prelude::*;
+            sites.into_par_iter().filter(free).collect()
+        } else {
+            sites.into_iter().filter(free).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let mut sites: Vec<Vector2<f64>> = sites.into_iter().filter(free).collect();
+
+        shuffle(&mut sites);
+        sites
+    }
+
+    // Whether a carrier at `pos` would touch an ion or another carrier.
+    fn overlaps(&self, pos: Vector2<f64>, radius: f64) -> bool {
+        self.ions.iter().any(|ion| {
+            let ion = ion.borrow();
+            (ion.pos - pos).magnitude() < radius + ion.radius
+        }) || self.electrons.iter().any(|el| {
+            let el = el.borrow();
+            (el.pos - pos).magnitude() < radius + el.radius
+        })
+    }
+
+    // Whether a carrier of the given radius keeps clear of every ion and carrier at `pos`.
+    fn is_free(&self, pos: Vector2<f64>, radius: f64) -> bool {
+        keeps_clear(
+            pos,
+            radius,
+            self.x_size,
+            self.y_size,
+            self.ions.iter().map(|ion| {
+                let ion = ion.borrow();
+                (ion.pos, ion.radius)
+            }),
+            self.electrons.iter().map(|el| {
+                let el = el.borrow();
+                (el.pos, el.radius)
+            }),
+        )
+    }
+
+    // Adds a single carrier while the simulation is running.
+    pub fn add_carrier(&mut self, mut electron: Electron) -> RcRefCell<Electron> {
+        self.drop_langevin(&mut electron);
+        electron.set_field(field_from_acc(self.acc));
+        self.draw_langevin(&mut electron);
+        let electron = Rc::new(RefCell::new(electron));
+        self.electrons.push(electron.clone());
+        self.update_collidables();
+        electron
+    }
+
+    // Takes a carrier out of the simulation and returns its final state.
+    pub fn remove_carrier(&mut self, electron: &RcRefCell<Electron>) -> Electron {
+        self.electrons.retain(|other| !Rc::ptr_eq(other, electron));
+        self.update_collidables();
+        let removed = electron.borrow().clone();
+        removed
+    }
+
+    pub fn add_trap(&mut self, trap: Trap) {
+        self.traps.push(Rc::new(RefCell::new(trap)));
+        self.update_collidables();
+    }
+
+    pub fn add_impurity(&mut self, impurity: Impurity) {
+        self.impurities.push(Rc::new(RefCell::new(impurity)));
+        self.update_collidables();
+    }
+
+    // Carriers of a species currently held by traps.
+    pub fn trapped_count(&self, species: usize) -> usize {
+        self.traps
+            .iter()
+            .filter(|trap| match &trap.borrow().occupant {
+                Some(electron) => electron.species == species,
+                None => false,
+            })
+            .count()
+    }
+
+    pub fn set_generation(&mut self, generation: Option<GenerationRecombination>) {
+        self.generation = generation;
+        self.schedule_generation();
+    }
+
+    fn schedule_generation(&mut self) {
+        self.next_generation = match self.generation {
+            Some(generation) if generation.generation_rate > 0.0 => {
+                self.time - (1.0 - random()).ln() / generation.generation_rate
+            }
+            _ => f64::INFINITY,
+        };
+    }
+
+    // Creates an electron-hole pair at random free positions.
+    fn generate_pair(&mut self, generation: GenerationRecombination) {
+        let electron = self.place_carrier(generation.electron_species, generation.init_velocity);
+        if let Some(electron) = electron {
+            self.electrons.push(Rc::new(RefCell::new(electron)));
+            match self.place_carrier(generation.hole_species, generation.init_velocity) {
+                Some(hole) => {
+                    self.electrons.push(Rc::new(RefCell::new(hole)));
+                    self.carrier_stats.generated += 1;
+                }
+                None => {
+                    self.electrons.pop();
+                }
+            }
+        }
+    }
+
+    pub fn set_impact_ionisation(&mut self, impact_ionisation: Option<ImpactIonisation>) {
+        self.impact_ionisation = impact_ionisation;
+    }
+
+    // Lets a hot carrier that just bounced off `ion` create an electron-hole pair next to it.
+    // The new carriers leave the ion radially, the pair is skipped when there is no room for it.
+    fn impact_ionise(
+        &mut self,
+        carrier: &RcRefCell<Electron>,
+        ion: &RcRefCell<Ion>,
+        ionisation: ImpactIonisation,
+    ) {
+        let (energy, contact) = {
+            let carrier = carrier.borrow();
+            (carrier.kinetic_energy(), carrier.pos)
+        };
+        let shared = match ionisation.shared_energy(energy) {
+            Some(shared) if random() < ionisation.probability => shared,
+            _ => return,
+        };
+
+        let ion = *ion.borrow();
+        let electron = self.place_near_ion(&ion, contact, ionisation.electron_species, shared);
+        if let Some(electron) = electron {
+            self.electrons.push(Rc::new(RefCell::new(electron)));
+            match self.place_near_ion(&ion, contact, ionisation.hole_species, shared) {
+                Some(hole) => {
+                    self.electrons.push(Rc::new(RefCell::new(hole)));
+                    carrier.borrow_mut().set_kinetic_energy(shared);
+                    self.carrier_stats.impact_ionisations += 1;
+                }
+                None => {
+                    self.electrons.pop();
+                }
+            }
+        }
+    }
+
+    // Places a carrier with the given kinetic energy on a free spot around `ion`,
+    // within a quarter turn either side of the contact point.
+    fn place_near_ion(
+        &self,
+        ion: &Ion,
+        contact: Vector2<f64>,
+        species_id: usize,
+        energy: f64,
+    ) -> Option<Electron> {
+        let species = &self.species[species_id];
+        let distance = ion.radius + 2.0 * species.radius + EPSILON;
+        let normal = contact - ion.pos;
+        let contact_angle = normal.y.atan2(normal.x);
+
+        for _ in 0..INIT_ITERATIONS {
+            let angle = contact_angle + (random() - 0.5) * std::f64::consts::PI;
+            let direction = Vector2::new(angle.cos(), angle.sin());
+            let pos = ion.pos + direction * distance;
+            if !self.is_free(pos, species.radius) {
+                continue;
+            }
+            let mut electron = Electron::of_species(species_id, species, pos, direction);
+            electron.set_kinetic_energy(energy);
+            electron.set_field(field_from_acc(self.acc));
+            electron.born_at = self.time;
+            return Some(electron);
+        }
+        None
+    }
+
+    // Chooses how carriers leave ions of the given species, `None` restores the elastic bounce.
+    pub fn set_scattering_law(&mut self, species: u32, law: Option<Box<dyn ScatteringLaw>>) {
+        match law {
+            Some(law) => self.scattering_laws.insert(species, law),
+            None => self.scattering_laws.remove(&species),
+        };
+    }
+
+    pub fn set_electron_scattering(&mut self, electron_scattering: ElectronScattering) {
+        self.electron_scattering = electron_scattering;
+        self.update_collidables();
+    }
+
+    pub fn set_pauli_blocking(&mut self, pauli_blocking: Option<PauliBlocking>) {
+        self.pauli_blocking = pauli_blocking;
+    }
+
+    // Whether one of the carriers just scattered landed in a momentum cell already filled
+    // by the others. Without blocking every state is free.
+    fn is_pauli_blocked(&self, scattered: &[&RcRefCell<Electron>]) -> bool {
+        let blocking = match self.pauli_blocking {
+            Some(blocking) => blocking,
+            None => return false,
+        };
+        let mut grid = blocking.grid(
+            self.electrons
+                .iter()
+                .filter(|electron| !scattered.iter().any(|other| Rc::ptr_eq(electron, other)))
+                .map(|electron| electron.borrow().momentum()),
+        );
+        scattered.iter().any(|electron| {
+            let momentum = electron.borrow().momentum();
+            let full = grid.is_full(momentum);
+            grid.occupy(momentum);
+            full
+        })
+    }
+
+    pub fn set_optical_phonon(&mut self, optical_phonon: Option<OpticalPhonon>) {
+        self.optical_phonon = optical_phonon;
+    }
+
+    // Takes one optical phonon quantum out of the carrier's kinetic energy, keeping its direction.
+    fn emit_phonon(&mut self, carrier: &RcRefCell<Electron>, phonon: OpticalPhonon) {
+        let energy = match phonon.energy_after(carrier.borrow().kinetic_energy()) {
+            Some(energy) if random() < phonon.probability => energy,
+            _ => return,
+        };
+        let before = carrier.borrow().clone();
+        carrier.borrow_mut().set_kinetic_energy(energy);
+        if self.is_pauli_blocked(&[carrier]) {
+            *carrier.borrow_mut() = before;
+            self.pauli_blocked += 1;
+            return;
+        }
+        self.phonons_emitted += 1;
+    }
+
+    // Switches the frozen-phonon disorder on or off.
+    // Enabling it immediately draws a new displacement for every ion, disabling it puts the ions back on their sites.
+    pub fn set_vibration(&mut self, vibration: Option<ThermalVibration>) {
+        self.vibration = vibration;
+        match vibration {
+            Some(vibration) => {
+                self.ions.iter().for_each(|ion| self.resample_ion(ion));
+                self.next_resample = match vibration.resample_period() {
+                    Some(period) => self.time + period,
+                    None => f64::INFINITY,
+                };
+            }
+            None => {
+                self.ions.iter().for_each(|ion| {
+                    let mut ion = ion.borrow_mut();
+                    ion.pos = ion.site;
+                });
+                self.next_resample = f64::INFINITY;
+            }
+        }
+        self.update_collidables();
+    }
+
+    // Lets every ion recoil in collisions, tethered to its site by a harmonic spring.
+    // An infinite mass pins the ions again.
+    pub fn set_mobile_ions(&mut self, mass: f64, stiffness: f64) {
+        self.ions.iter().for_each(|ion| {
+            let mut ion = ion.borrow_mut();
+            ion.set_mobile(mass, stiffness);
+            if !ion.is_mobile() {
+                ion.pos = ion.site;
+            }
+        });
+        self.update_collidables();
+    }
+
+    fn has_mobile_ions(&self) -> bool {
+        self.ions.iter().any(|ion| ion.borrow().is_mobile())
+    }
+
+    // Kinetic plus spring energy stored in the lattice.
+    pub fn lattice_energy(&self) -> f64 {
+        self.ions
+            .iter()
+            .fold(0.0, |acc, ion| acc + ion.borrow().energy())
+    }
+
+    // Moves the ion to a new thermally displaced position around its site.
+    // Positions overlapping an electron, another ion, a trap or an impurity's cutoff circle are rejected,
+    // the ion stays put if no free position is found.
+    fn resample_ion(&self, ion: &RcRefCell<Ion>) {
+        let vibration = match self.vibration {
+            Some(vibration) => vibration,
+            None => return,
+        };
+        let radius = ion.borrow().radius;
+        let site = ion.borrow().site;
+        let others: Vec<(Vector2<f64>, f64)> = self
+            .electrons
+            .iter()
+            .map(|electron| {
+                let electron = electron.borrow();
+                (electron.pos, electron.radius)
+            })
+            .chain(
+                self.ions
+                    .iter()
+                    .filter(|other| !Rc::ptr_eq(other, ion))
+                    .map(|other| {
+                        let other = other.borrow();
+                        (other.pos, other.radius)
+                    }),
+            )
+            .chain(self.traps.iter().map(|trap| {
+                let trap = trap.borrow();
+                (trap.pos, trap.radius)
+            }))
+            .chain(self.impurities.iter().map(|impurity| {
+                let impurity = impurity.borrow();
+                (impurity.pos, impurity.cutoff)
+            }))
+            .collect();
+
+        for _ in 0..INIT_ITERATIONS {
+            let pos = site + vibration.sample_displacement();
+            if others
+                .iter()
+                .all(|&(other, other_radius)| (other - pos).magnitude() >= radius + other_radius)
+            {
+                ion.borrow_mut().pos = pos;
+                return;
+            }
+        }
+    }
+
+    // Predicts the next collision of every carrier and the earliest one overall.
+    // Public so that benchmarks can time a single prediction round.
+    pub fn update_collidables(&mut self) {
+        if self.predict_in_parallel() || self.predict_batched() {
+            return;
+        }
+        // Without carrier-carrier scattering or recombination no pair can meet, the O(N^2) scan is skipped.
+        let pairs =
+            self.electron_scattering != ElectronScattering::Disabled || self.generation.is_some();
+        let mut min: (Weak<RefCell<Electron>>, Collidables, f64) =
+            (Weak::new(), Collidables::empty(), f64::INFINITY);
+        self.electrons.iter().for_each(|electron| {
+            // Carriers orbiting an impurity leave it at a scheduled time and meet nothing before.
+            if electron.borrow().transit.is_some() {
+                let mut electron = electron.borrow_mut();
+                electron.collidable = Collidables::empty();
+                electron.time_to_bounce = f64::INFINITY;
+                return;
+            }
+            let mut collidables: Vec<Collidables> = Vec::new();
+
+            // The direction filters assume straight paths, Dirac carriers turn within the horizon.
+            let dirac = electron.borrow().dirac.is_some();
+            self.borders.iter().for_each(|border| {
+                if dirac || CrystalStructure::filter_border(&electron.borrow(), &border.borrow()) {
+                    collidables.push(Collidables::new_b(border));
+                }
+            });
+
+            self.ions.iter().for_each(|ion| {
+                let ion_ref = ion.borrow();
+                let candidate = if ion_ref.is_mobile() {
+                    ion_ref.within_reach(&electron.borrow(), ORBIT_HORIZON)
+                } else if dirac {
+                    CrystalStructure::within_horizon(&electron.borrow(), &ion_ref)
+                } else {
+                    CrystalStructure::filter_ion(&electron.borrow(), &ion_ref)
+                };
+                if candidate {
+                    collidables.push(Collidables::new_i(ion));
+                }
+            });
+            if pairs {
+                self.electrons.iter().for_each(|other| {
+                    if !electron.borrow().eq(&other.borrow())
+                        && other.borrow().transit.is_none()
+                        && self.interacts(&electron.borrow(), &other.borrow())
+                    {
+                        collidables.push(Collidables::new_e(other));
+                    }
+                });
+            }
+            self.traps.iter().for_each(|trap| {
+                if !trap.borrow().is_occupied() {
+                    collidables.push(Collidables::new_t(trap));
+                }
+            });
+            self.impurities.iter().for_each(|impurity| {
+                collidables.push(Collidables::new_c(impurity));
+            });
+
+            let timed_collidables: Vec<(Collidables, f64)> = collidables
+                .iter()
+                .map(|c| (c.clone(), self.time_to_collision(&electron.borrow(), c)))
+                .collect();
+            let (mut collidable, mut time_to_bounce) = timed_collidables
+                .into_iter()
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                .unwrap_or((Collidables::empty(), f64::INFINITY));
+            if dirac && time_to_bounce > HORIZON {
+                collidable = Collidables::Horizon;
+                time_to_bounce = HORIZON;
+            }
+
+            electron.borrow_mut().collidable = collidable.clone();
+            electron.borrow_mut().time_to_bounce = time_to_bounce;
+
+            if time_to_bounce < min.2 {
+                min = (Rc::downgrade(electron), collidable, time_to_bounce);
+            }
+        });
+        self.next_collision = (min.0, min.1);
+        self.time_to_bounce = min.2;
+    }
+
+    // Parallel prediction for plain structures: no traps, impurities, Dirac or transit carriers.
+    // Every carrier is predicted from a snapshot with the same candidates in the same order as the
+    // serial loop, and the earliest collision is picked serially, so the results are identical.
+    #[cfg(feature = "parallel")]
+    fn predict_in_parallel(&mut self) -> bool {
+        use rayon::prelude::*;
+
+        if !self.parallel
+            || self.electrons.len() < PARALLEL_MIN_CARRIERS
+            || !self.traps.is_empty()
+            || !self.impurities.is_empty()
+            || self.electrons.iter().any(|electron| {
+                let electron = electron.borrow();
+                electron.dirac.is_some() || electron.transit.is_some()
+            })
+        {
+            return false;
+        }
+
+        let borders: Vec<Border> = self.borders.iter().map(|border| *border.borrow()).collect();
+        let ions: Vec<Ion> = self.ions.iter().map(|ion| *ion.borrow()).collect();
+        let carriers: Vec<Kinematics> = self
+            .electrons
+            .iter()
+            .map(|electron| electron.borrow().kinematics())
+            .collect();
+        let (width, electron_scattering, generation) =
+            (self.x_size, self.electron_scattering, self.generation);
+        let pairs = electron_scattering != ElectronScattering::Disabled || generation.is_some();
+
+        let predictions: Vec<Option<(Partner, f64)>> = carriers
+            .par_iter()
+            .map(|carrier| {
+                let electron = Electron::from_kinematics(carrier);
+                let mut candidates: Vec<(Partner, f64)> = Vec::new();
+                for (i, border) in borders.iter().enumerate() {
+                    if CrystalStructure::filter_border(&electron, border) {
+                        let time = border.calc_time_to_collision(&electron, width);
+                        candidates.push((Partner::Border(i), time));
+                    }
+                }
+                for (i, ion) in ions.iter().enumerate() {
+                    let candidate = if ion.is_mobile() {
+                        ion.within_reach(&electron, ORBIT_HORIZON)
+                    } else {
+                        CrystalStructure::filter_ion(&electron, ion)
+                    };
+                    if candidate {
+                        candidates.push((Partner::Ion(i), ion.calc_time_to_collision(&electron)));
+                    }
+                }
+                if pairs {
+                    for (i, other) in carriers.iter().enumerate() {
+                        // Same test as `Electron::eq`.
+                        let same = other.pos == carrier.pos && other.vel == carrier.vel;
+                        if !same
+                            && CrystalStructure::pair_interacts(
+                                electron_scattering,
+                                generation,
+                                carrier.species,
+                                other.species,
+                            )
+                        {
+                            let other = Electron::from_kinematics(other);
+                            let distance = electron_scattering.contact_distance(&electron, &other);
+                            let time = other.calc_time_to_contact(&electron, distance);
+                            candidates.push((Partner::Electron(i), time));
+                        }
+                    }
+                }
+                candidates
+                    .into_iter()
+                    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            })
+            .collect();
+
+        let mut min: (Weak<RefCell<Electron>>, Collidables, f64) =
+            (Weak::new(), Collidables::empty(), f64::INFINITY);
+        for (electron, prediction) in self.electrons.iter().zip(predictions) {
+            let (collidable, time_to_bounce) = match prediction {
+                Some((Partner::Border(i), time)) => (Collidables::new_b(&self.borders[i]), time),
+                Some((Partner::Ion(i), time)) => (Collidables::new_i(&self.ions[i]), time),
+                Some((Partner::Electron(i), time)) => {
+                    (Collidables::new_e(&self.electrons[i]), time)
+                }
+                None => (Collidables::empty(), f64::INFINITY),
+            };
+            electron.borrow_mut().collidable = collidable.clone();
+            electron.borrow_mut().time_to_bounce = time_to_bounce;
+            if time_to_bounce < min.2 {
+                min = (Rc::downgrade(electron), collidable, time_to_bounce);
+            }
+        }
+        self.next_collision = (min.0, min.1);
+        self.time_to_bounce = min.2;
+        true
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn predict_in_parallel(&mut self) -> bool {
+        false
+    }
+
+    // Batched prediction for the same plain structures as the parallel one.
+    // Wall and ion contacts come from the struct-of-arrays kernels, which resolve ties like the
+    // serial loop. Carrier pairs follow ions in the serial candidate order, so they are checked
+    // afterwards by the scalar solver and only replace a strictly earlier prediction.
+    fn predict_batched(&mut self) -> bool {
+        if !self.batched
+            || self.electrons.len() < BATCHED_MIN_CARRIERS
+            || !self.traps.is_empty()
+            || !self.impurities.is_empty()
+            || self.electrons.iter().any(|electron| {
+                let electron = electron.borrow();
+                electron.dirac.is_some() || electron.transit.is_some()
+            })
+        {
+            return false;
+        }
+
+        let predictions = ParticleStore::from_structure(self).predict(
+            &IonList::from_structure(self),
+            &WallList::from_structure(self),
+        );
+        let pairs =
+            self.electron_scattering != ElectronScattering::Disabled || self.generation.is_some();
+
+        let mut min: (Weak<RefCell<Electron>>, Collidables, f64) =
+            (Weak::new(), Collidables::empty(), f64::INFINITY);
+        for (electron, prediction) in self.electrons.iter().zip(predictions) {
+            let (mut collidable, mut time_to_bounce) = match prediction.target {
+                Target::Wall(i) => (Collidables::new_b(&self.borders[i]), prediction.time),
+                Target::Ion(i) => (Collidables::new_i(&self.ions[i]), prediction.time),
+                Target::None => (Collidables::empty(), f64::INFINITY),
+            };
+            if pairs {
+                for other in self.electrons.iter() {
+                    if electron.borrow().eq(&other.borrow())
+                        || !self.interacts(&electron.borrow(), &other.borrow())
+                    {
+                        continue;
+                    }
+                    let candidate = Collidables::new_e(other);
+                    let time = self.time_to_collision(&electron.borrow(), &candidate);
+                    if time < time_to_bounce {
+                        collidable = candidate;
+                        time_to_bounce = time;
+                    }
+                }
+            }
+            electron.borrow_mut().collidable = collidable.clone();
+            electron.borrow_mut().time_to_bounce = time_to_bounce;
+            if time_to_bounce < min.2 {
+                min = (Rc::downgrade(electron), collidable, time_to_bounce);
+            }
+        }
+        self.next_collision = (min.0, min.1);
+        self.time_to_bounce = min.2;
+        true
+    }
+
+    fn time_to_collision(&self, electron: &Electron, collidable: &Collidables) -> f64 {
+        match collidable {
+            Collidables::Electron(other) => {
+                let other = other.upgrade().unwrap();
+                let other = other.borrow();
+                let distance = self.electron_scattering.contact_distance(electron, &other);
+                if electron.dirac.is_some() || other.dirac.is_some() {
+                    let closing_speed = electron.max_speed(HORIZON) + other.max_speed(HORIZON);
+                    let step = sample_step(electron.radius.min(other.radius), closing_speed);
+                    let gap = |t: f64| {
+                        (electron.position_at(t) - other.position_at(t)).magnitude() - distance
+                    };
+                    return first_contact(gap, step, HORIZON);
+                }
+                other.calc_time_to_contact(electron, distance)
+            }
+            _ if electron.dirac.is_some() => self.dirac_time_to_collision(electron, collidable),
+            _ => collidable.calc_time_to_collision(electron, self.x_size),
+        }
+    }
+
+    // Paths of Dirac carriers are not polynomial, so the first contact within the horizon
+    // is bracketed on the sampled distance to the partner.
+    // Impurity orbits assume the parabolic dispersion and are not entered by Dirac carriers.
+    fn dirac_time_to_collision(&self, electron: &Electron, collidable: &Collidables) -> f64 {
+        let speed = electron.max_speed(HORIZON);
+        match collidable {
+            Collidables::Border(border) => {
+                let border = border.upgrade().unwrap();
+                let border = border.borrow();
+                let gap =
+                    |t: f64| border.gap(electron.position_at(t), electron.radius, self.x_size);
+                first_contact(gap, sample_step(electron.radius, speed), HORIZON)
+            }
+            Collidables::Ion(ion) => {
+                let ion = ion.upgrade().unwrap();
+                let ion = ion.borrow();
+                let closing_speed = speed + ion.max_speed();
+                let step = sample_step(electron.radius.min(ion.radius), closing_speed);
+                let gap = |t: f64| {
+                    (electron.position_at(t) - ion.position_at(t)).magnitude()
+                        - ion.radius
+                        - electron.radius
+                };
+                first_contact(gap, step, HORIZON)
+            }
+            Collidables::Trap(trap) => {
+                let trap = trap.upgrade().unwrap();
+                let trap = trap.borrow();
+                let gap = |t: f64| (electron.position_at(t) - trap.pos).magnitude() - trap.radius;
+                first_contact(
+                    gap,
+                    sample_step(electron.radius.min(trap.radius), speed),
+                    HORIZON,
+                )
+            }
+            _ => f64::INFINITY,
+        }
+    }
+
+    // Whether a Dirac carrier can reach a pinned ion before its horizon.
+    fn within_horizon(electron: &Electron, ion: &Ion) -> bool {
+        let distance = (ion.pos - electron.pos).magnitude() - ion.radius - electron.radius;
+        distance <= electron.max_speed(HORIZON) * HORIZON
+    }
+
+    fn filter_border(electron: &Electron, border: &Border) -> bool {
+        match border.border_type {
+            BorderType::Inner => {
+                electron.vel.x > 0.0 && border.a == 1.0 && border.c > 0.0
+                    || electron.vel.y > 0.0 && border.b == 1.0 && border.c > 0.0
+            }
+            BorderType::Outer => {
+                electron.vel.x < 0.0 && border.a == 1.0 && border.c == 0.0
+                    || electron.vel.y < 0.0 && border.b == 1.0 && border.c == 0.0
+            }
+        }
+    }
+
+    pub(crate) fn filter_ion(electron: &Electron, ion: &Ion) -> bool {
+        let ion_elec_radius = ion.radius + electron.radius;
+        if electron.vel.x > 0.0 && electron.vel.y > 0.0 {
+            (electron.pos.x - ion_elec_radius <= ion.pos.x)
+                && (electron.pos.y - ion_elec_radius <= ion.pos.y)
+        } else if electron.vel.x > 0.0 && electron.vel.y < 0.0 {
+            (electron.pos.x - ion_elec_radius <= ion.pos.x)
+                && (electron.pos.y + ion_elec_radius >= ion.pos.y)
+        } else if electron.vel.x < 0.0 && electron.vel.y > 0.0 {
+            (electron.pos.x + ion_elec_radius >= ion.pos.x)
+                && (electron.pos.y - ion_elec_radius <= ion.pos.y)
+        } else if electron.vel.x < 0.0 && electron.vel.y < 0.0 {
+            (electron.pos.x + ion_elec_radius >= ion.pos.x)
+                && (electron.pos.y + ion_elec_radius >= ion.pos.y)
+        } else if electron.vel.x == 0.0 && electron.vel.y > 0.0 {
+            electron.pos.y < ion.pos.y
+                && electron.pos.x - ion_elec_radius - 1.0 <= ion.pos.x
+                && ion.pos.x <= electron.pos.x + ion_elec_radius + 1.0
+        } else if electron.vel.x == 0.0 && electron.vel.y < 0.0 {
+            electron.pos.y > ion.pos.y
+                && electron.pos.x - ion_elec_radius - 1.0 <= ion.pos.x
+                && ion.pos.x <= electron.pos.x + ion_elec_radius + 1.0
+        } else if electron.vel.y == 0.0 && electron.vel.x > 0.0 {
+            electron.pos.x < ion.pos.x
+                && electron.pos.y - ion_elec_radius - 1.0 <= ion.pos.y
+                && ion.pos.y <= electron.pos.y + ion_elec_radius + 1.0
+        } else if electron.vel.y == 0.0 && electron.vel.x < 0.0 {
+            electron.pos.x > ion.pos.x
+                && electron.pos.y - ion_elec_radius - 1.0 <= ion.pos.y
+                && ion.pos.y <= electron.pos.y + ion_elec_radius + 1.0
+        } else {
+            false
+        }
+    }
+
+    pub fn update(&mut self, acc: f64, supp: f64) {
+        let mut time: f64 = 1.0;
+
+        if self.acc != acc || self.supp != supp {
+            self.acc = acc;
+            self.supp = supp;
+            let field = field_from_acc(acc);
+            self.electrons.iter().for_each(|electron| {
+                let mut electron = electron.borrow_mut();
+                electron.set_field(field);
+                self.draw_langevin(&mut electron);
+            });
+            self.update_collidables();
+        }
+
+        // Contacts with mobile ions are only predicted `ORBIT_HORIZON` ahead, so predictions are refreshed every tick.
+        if self.has_mobile_ions() {
+            self.update_collidables();
+        }
+
+        while time > 0.0 {
+            let time_to_scheduled = (self.next_scheduled_time() - self.time).max(0.0);
+            if self.time_to_bounce > time && time_to_scheduled > time {
+                self.advance(time, supp);
+                break;
+            }
+
+            if time_to_scheduled < self.time_to_bounce {
+                self.advance(time_to_scheduled, supp);
+                time -= time_to_scheduled;
+                self.resolve_scheduled_events();
+                self.update_collidables();
+                continue;
+            }
+
+            let time_to_bounce = self.time_to_bounce;
+            self.advance(time_to_bounce, supp);
+            self.resolve_next_collision();
+            time -= time_to_bounce;
+            self.update_collidables();
+        }
+
+        if let Some(mut thermostat) = self.thermostat {
+            let mut carriers = self.thermalised_carriers();
+            let before: Vec<Vector2<f64>> = carriers.iter().map(|carrier| carrier.vel).collect();
+            thermostat.apply(&mut carriers, 1.0);
+            // A changed velocity no longer starts the drawn Langevin step, the bridge is redrawn.
+            for (carrier, vel) in carriers.iter_mut().zip(before) {
+                if carrier.vel != vel {
+                    self.drop_langevin(carrier);
+                    self.draw_langevin(carrier);
+                }
+            }
+            drop(carriers);
+            self.thermostat = Some(thermostat);
+            self.update_collidables();
+        }
+    }
+
+    // Switches the thermal noise on or off, carriers follow the plain drag when it is off.
+    pub fn set_langevin(&mut self, langevin: Option<Langevin>) {
+        self.langevin = langevin;
+        self.next_langevin = match langevin {
+            Some(langevin) => self.time + langevin.step,
+            None => f64::INFINITY,
+        };
+        self.electrons.iter().for_each(|electron| {
+            let mut electron = electron.borrow_mut();
+            self.drop_langevin(&mut electron);
+            self.draw_langevin(&mut electron);
+        });
+        self.update_collidables();
+    }
+
+    // Draws the exact Ornstein-Uhlenbeck transition of a carrier to the end of the current step.
+    // The carrier then moves with the constant acceleration that ends on the drawn position,
+    // so collision prediction stays polynomial, and takes the drawn velocity at the step end.
+    // Dirac carriers and carriers orbiting an impurity follow the plain drag until the next draw.
+    fn draw_langevin(&self, electron: &mut Electron) {
+        let langevin = match self.langevin {
+            Some(langevin) => langevin,
+            None => return,
+        };
+        let time = self.next_langevin - self.time;
+        if electron.dirac.is_some() || electron.transit.is_some() || time <= EPSILON {
+            return;
+        }
+        let field_acc = self.species[electron.species].acceleration(field_from_acc(self.acc));
+        let (pos, vel) = langevin.transition(
+            electron.pos,
+            electron.vel,
+            field_acc,
+            self.supp,
+            electron.mass,
+            time,
+        );
+        electron.acc = (pos - electron.pos - electron.vel * time) * 2.0 / time.powi(2);
+        electron.langevin_vel = Some(vel);
+    }
+
+    // Puts a carrier back on its field acceleration.
+    fn drop_langevin(&self, electron: &mut Electron) {
+        if electron.langevin_vel.take().is_some() {
+            electron.set_field(field_from_acc(self.acc));
+        }
+    }
+
+    pub fn set_thermostat(&mut self, thermostat: Option<Thermostat>) {
+        self.thermostat = thermostat;
+    }
+
+    // Carriers a thermostat acts on. Dirac carriers move at a fixed speed and carriers
+    // orbiting an impurity follow a precomputed path, so both are left alone.
+    fn thermalised_carriers(&self) -> Vec<RefMut<'_, Electron>> {
+        self.electrons
+            .iter()
+            .map(|electron| electron.borrow_mut())
+            .filter(|electron| electron.dirac.is_none() && electron.transit.is_none())
+            .collect()
+    }
+
+    // Temperature of the carrier motion relative to the drift, as seen by the thermostats.
+    pub fn carrier_temperature(&self) -> f64 {
+        thermostat::temperature(&self.thermalised_carriers())
+    }
+
+    fn resolve_next_collision(&mut self) {
+        let electron = self.next_collision.0.upgrade().unwrap();
+        self.update_flux(&electron.borrow());
+        // A carrier whose scattering was blocked passes through its partner and meets it again on the way out.
+        if self.is_separating(&electron.borrow(), &self.next_collision.1) {
+            return;
+        }
+
+        match self.next_collision.1.clone() {
+            Collidables::Trap(trap) => {
+                let mut captured = self.remove_carrier(&electron);
+                self.drop_langevin(&mut captured);
+                trap.upgrade()
+                    .unwrap()
+                    .borrow_mut()
+                    .capture(captured, self.time);
+                self.carrier_stats.captured += 1;
+            }
+            Collidables::Electron(other)
+                if self.recombines(&electron, &other.upgrade().unwrap()) =>
+            {
+                let other = other.upgrade().unwrap();
+                for carrier in [&electron, &other].iter() {
+                    let removed = self.remove_carrier(carrier);
+                    self.carrier_stats
+                        .record_lifetime(self.time - removed.born_at);
+                }
+                self.carrier_stats.recombined += 1;
+            }
+            Collidables::Impurity(impurity) => {
+                self.drop_langevin(&mut electron.borrow_mut());
+                let transit = impurity
+                    .upgrade()
+                    .unwrap()
+                    .borrow()
+                    .transit(&electron.borrow(), self.time);
+                if let Some(transit) = transit {
+                    let mut electron = electron.borrow_mut();
+                    electron.record_collision(CollisionKind::Impurity);
+                    electron.transit = Some(transit);
+                    self.impurity_scatterings += 1;
+                }
+            }
+            Collidables::Electron(other) => {
+                self.resolve_electron_collision(&electron, &other.upgrade().unwrap());
+            }
+            Collidables::Ion(ion) => {
+                self.resolve_ion_collision(&electron, &ion.upgrade().unwrap());
+            }
+            Collidables::Horizon => {}
+            collidable => collidable.resolve_collision(&electron, self.x_size),
+        }
+        electron.borrow_mut().align_momentum();
+
+        // The noise drawn for the rest of the step assumed the pre-collision state.
+        let partner = match &self.next_collision.1 {
+            Collidables::Electron(other) => other.upgrade(),
+            _ => None,
+        };
+        for carrier in std::iter::once(electron).chain(partner) {
+            let mut carrier = carrier.borrow_mut();
+            if carrier.langevin_vel.is_some() {
+                self.drop_langevin(&mut carrier);
+                self.draw_langevin(&mut carrier);
+            }
+        }
+    }
+
+    // Bounces the carrier with the scattering law of the ion's species, then lets the
+    // inelastic processes and the lattice react to the collision.
+    // A blocked final state undoes the collision and skips the inelastic processes.
+    fn resolve_ion_collision(&mut self, electron: &RcRefCell<Electron>, ion: &RcRefCell<Ion>) {
+        let before = (electron.borrow().clone(), *ion.borrow());
+        let species = ion.borrow().species;
+        match self.scattering_laws.get(&species) {
+            Some(law) => {
+                electron.borrow_mut().record_collision(CollisionKind::Ion);
+                ion.borrow_mut()
+                    .scatter(&mut electron.borrow_mut(), law.as_ref());
+            }
+            None => Collidables::new_i(ion).resolve_collision(electron, self.x_size),
+        }
+        electron.borrow_mut().align_momentum();
+        if self.is_pauli_blocked(&[electron]) {
+            *electron.borrow_mut() = before.0;
+            *ion.borrow_mut() = before.1;
+            self.pauli_blocked += 1;
+            return;
+        }
+
+        if let Some(ionisation) = self.impact_ionisation {
+            self.impact_ionise(electron, ion, ionisation);
+        }
+        if let Some(phonon) = self.optical_phonon {
+            self.emit_phonon(electron, phonon);
+        }
+        if let Some(ThermalVibration {
+            resampling: PhononResampling::OnScatter,
+            ..
+        }) = self.vibration
+        {
+            self.resample_ion(ion);
+        }
+    }
+
+    // Disabled interaction still lets a pair that may recombine meet, a pair that does not
+    // passes through and is not counted as a collision.
+    fn resolve_electron_collision(
+        &mut self,
+        electron: &RcRefCell<Electron>,
+        other: &RcRefCell<Electron>,
+    ) {
+        if self.electron_scattering == ElectronScattering::Disabled {
+            return;
+        }
+        let before = (electron.borrow().clone(), other.borrow().clone());
+        electron
+            .borrow_mut()
+            .record_collision(CollisionKind::Electron);
+        other.borrow_mut().record_collision(CollisionKind::Electron);
+        {
+            let (mut first, mut second) = (electron.borrow_mut(), other.borrow_mut());
+            match self.electron_scattering {
+                ElectronScattering::HardDisk => second.bounce(&mut first),
+                ElectronScattering::Disabled => {}
+                ElectronScattering::MonteCarlo { .. } => {
+                    random_angle_collision(&mut first, &mut second)
+                }
+                ElectronScattering::MomentumRelaxing => {
+                    momentum_relaxing_collision(&mut first, &mut second)
+                }
+            }
+            first.align_momentum();
+            second.align_momentum();
+        }
+        if self.is_pauli_blocked(&[electron, other]) {
+            *electron.borrow_mut() = before.0;
+            *other.borrow_mut() = before.1;
+            self.pauli_blocked += 1;
+            return;
+        }
+        if self.electron_scattering.conserves_momentum() {
+            self.electron_collisions += 1;
+        } else {
+            self.momentum_relaxing_events += 1;
+        }
+    }
+
+    fn is_separating(&self, electron: &Electron, collidable: &Collidables) -> bool {
+        let (pos, vel) = match collidable {
+            Collidables::Ion(ion) => {
+                let ion = ion.upgrade().unwrap();
+                let ion = ion.borrow();
+                (ion.pos, ion.vel)
+            }
+            Collidables::Electron(other) => {
+                let other = other.upgrade().unwrap();
+                let other = other.borrow();
+                (other.pos, other.vel)
+            }
+            // A released carrier starts at the centre and would be caught again on its way out.
+            Collidables::Trap(trap) => {
+                let trap = trap.upgrade().unwrap();
+                let pos = trap.borrow().pos;
+                (pos, Vector2::new(0.0, 0.0))
+            }
+            _ => return false,
+        };
+        (electron.pos - pos).dot(&(electron.vel - vel)) > 0.0
+    }
+
+    fn interacts(&self, first: &Electron, second: &Electron) -> bool {
+        CrystalStructure::pair_interacts(
+            self.electron_scattering,
+            self.generation,
+            first.species,
+            second.species,
+        )
+    }
+
+    fn pair_interacts(
+        electron_scattering: ElectronScattering,
+        generation: Option<GenerationRecombination>,
+        first: usize,
+        second: usize,
+    ) -> bool {
+        electron_scattering != ElectronScattering::Disabled
+            || CrystalStructure::is_electron_hole_pair(generation, first, second)
+    }
+
+    fn is_electron_hole_pair(
+        generation: Option<GenerationRecombination>,
+        first: usize,
+        second: usize,
+    ) -> bool {
+        match generation {
+            Some(generation) => {
+                (first, second) == (generation.electron_species, generation.hole_species)
+                    || (first, second) == (generation.hole_species, generation.electron_species)
+            }
+            None => false,
+        }
+    }
+
+    fn recombines(&self, first: &RcRefCell<Electron>, second: &RcRefCell<Electron>) -> bool {
+        let pair = (first.borrow().species, second.borrow().species);
+        match self.generation {
+            Some(generation)
+                if CrystalStructure::is_electron_hole_pair(self.generation, pair.0, pair.1) =>
+            {
+                random() < generation.recombination_probability
+            }
+            _ => false,
+        }
+    }
+
+    fn advance(&mut self, time: f64, supp: f64) {
+        self.electrons
+            .iter()
+            .for_each(|electron| electron.borrow_mut().update(time, supp));
+        self.ions
+            .iter()
+            .for_each(|ion| ion.borrow_mut().update(time));
+        self.time_to_bounce -= time;
+        self.time += time;
+    }
+
+    fn next_scheduled_time(&self) -> f64 {
+        let transits_end = self
+            .electrons
+            .iter()
+            .filter_map(|electron| electron.borrow().transit.map(|transit| transit.ends_at));
+        self.traps
+            .iter()
+            .map(|trap| trap.borrow().release_at)
+            .chain(transits_end)
+            .fold(
+                self.next_resample
+                    .min(self.next_generation)
+                    .min(self.next_langevin),
+                f64::min,
+            )
+    }
+
+    fn resolve_scheduled_events(&mut self) {
+        if self.next_langevin - self.time <= EPSILON {
+            self.next_langevin += self
+                .langevin
+                .map_or(f64::INFINITY, |langevin| langevin.step);
+            self.electrons.iter().for_each(|electron| {
+                let mut electron = electron.borrow_mut();
+                if let Some(vel) = electron.langevin_vel {
+                    electron.vel = vel;
+                }
+                self.drop_langevin(&mut electron);
+                self.draw_langevin(&mut electron);
+            });
+        }
+
+        if self.next_resample - self.time <= EPSILON {
+            self.ions.iter().for_each(|ion| self.resample_ion(ion));
+            self.next_resample += self
+                .vibration
+                .and_then(|vibration| vibration.resample_period())
+                .unwrap_or(f64::INFINITY);
+        }
+
+        for electron in self.electrons.iter() {
+            let ends_at = electron.borrow().transit.map(|transit| transit.ends_at);
+            if matches!(ends_at, Some(ends_at) if ends_at - self.time <= EPSILON) {
+                electron.borrow_mut().finish_transit();
+            }
+        }
+
+        for trap in self.traps.clone().iter() {
+            if trap.borrow().release_at - self.time > EPSILON {
+                continue;
+            }
+            // The occupant reappears at the trap centre, the release waits while it would overlap.
+            let (pos, radius) = match &trap.borrow().occupant {
+                Some(electron) => (trap.borrow().pos, electron.radius),
+                None => continue,
+            };
+            if self.overlaps(pos, radius) {
+                trap.borrow_mut().schedule_release(self.time);
+                continue;
+            }
+            let released = trap.borrow_mut().release();
+            if let Some(electron) = released {
+                self.add_carrier(electron);
+                self.carrier_stats.released += 1;
+            }
+        }
+
+        if self.next_generation - self.time <= EPSILON {
+            if let Some(generation) = self.generation {
+                self.generate_pair(generation);
+            }
+            self.schedule_generation();
+        }
+    }
+
+    // Mean electron velocity along the field axis.
+    pub fn drift_velocity(&self) -> f64 {
+        if self.electrons.is_empty() {
+            return 0.0;
+        }
+        let sum = self
+            .electrons
+            .iter()
+            .fold(0.0, |acc, electron| acc + electron.borrow().vel.x);
+        sum / self.electrons.len() as f64
+    }
+
+    // Mean kinetic energy of the carriers of one species, k_B * T for a classical 2D gas
+    // and about E_F / 2 for a degenerate one.
+    pub fn mean_kinetic_energy(&self, species: usize) -> f64 {
+        let (sum, count) = self
+            .electrons
+            .iter()
+            .map(|electron| electron.borrow())
+            .filter(|electron| electron.species == species)
+            .fold((0.0, 0), |(sum, count), electron| {
+                (sum + electron.kinetic_energy(), count + 1)
+            });
+        if count == 0 {
+            return 0.0;
+        }
+        sum / count as f64
+    }
+
+    // Mean time between collisions of any kind, pooled over the carriers, periodic crossings excluded.
+    // This is the tau an idealised relaxation time model needs to match the structure.
+    pub fn mean_free_time(&self) -> f64 {
+        self.collision_stats().mean_free_time()
+    }
+
+    // Collision counters of all carriers pooled, broken down by partner.
+    // Carriers removed from the structure take their counters with them.
+    pub fn collision_stats(&self) -> CollisionStats {
+        self.electrons
+            .iter()
+            .fold(CollisionStats::default(), |stats, electron| {
+                stats.merge(electron.borrow().collisions)
+            })
+    }
+
+    // Velocity histogram of one species on the grid of the Boltzmann solver.
+    pub fn velocity_histogram(&self, species: usize, grid: &VelocityGrid) -> Vec<f64> {
+        grid.histogram(
+            self.electrons
+                .iter()
+                .map(|electron| electron.borrow())
+                .filter(|electron| electron.species == species)
+                .map(|electron| electron.vel),
+        )
+    }
+
+    // Number of electrons per unit area.
+    pub fn electron_density(&self) -> f64 {
+        self.electrons.len() as f64 / (self.x_size * self.y_size)
+    }
+
+    fn update_flux(&mut self, electron: &Electron) {
+        let periodic = match &self.next_collision.1 {
+            Collidables::Border(border) => border.upgrade().unwrap().borrow().a == 1.0,
+            _ => false,
+        };
+        if !periodic {
+            return;
+        }
+        let flux = &mut self.flux[electron.species];
+        if electron.vel.x > 0.0 {
+            flux.left += 1;
+        } else if electron.vel.x < 0.0 {
+            flux.right += 1;
+        }
+    }
+
+    // Mean velocity along the field axis of the carriers of one species.
+    pub fn species_drift_velocity(&self, species: usize) -> f64 {
+        self.species_mean_velocity(species).x
+    }
+
+    // Mean velocity vector of one species, anisotropic carriers also drift across the field.
+    pub fn species_mean_velocity(&self, species: usize) -> Vector2<f64> {
+        let (sum, count) = self
+            .electrons
+            .iter()
+            .map(|electron| electron.borrow())
+            .filter(|electron| electron.species == species)
+            .fold((Vector2::new(0.0, 0.0), 0), |(sum, count), electron| {
+                (sum + electron.vel, count + 1)
+            });
+        if count == 0 {
+            return Vector2::new(0.0, 0.0);
+        }
+        sum / count as f64
+    }
+
+    // Drift velocity per unit field, positive for both electrons and holes.
+    pub fn mobility(&self, species: usize) -> f64 {
+        let field = field_from_acc(self.acc);
+        if field == 0.0 {
+            return 0.0;
+        }
+        self.species_drift_velocity(species) * self.species[species].charge.signum() / field
+    }
+
+    // Mobility averaged over free and trapped carriers, trapped ones do not drift.
+    pub fn effective_mobility(&self, species: usize) -> f64 {
+        let free = self
+            .electrons
+            .iter()
+            .filter(|electron| electron.borrow().species == species)
+            .count();
+        let total = free + self.trapped_count(species);
+        if total == 0 {
+            return 0.0;
+        }
+        self.mobility(species) * free as f64 / total as f64
+    }
+
+    // Charge carried per unit time in the +x direction by one species, counted at the periodic borders.
+    pub fn species_current(&self, species: usize) -> f64 {
+        if self.time == 0.0 {
+            return 0.0;
+        }
+        let flux = self.flux[species];
+        self.species[species].charge * (flux.left - flux.right) as f64 / self.time
+    }
+
+    // Sum of the contributions of every carrier species.
+    pub fn net_current(&self) -> f64 {
+        (0..self.species.len())
+            .map(|species| self.species_current(species))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::placement::{CarrierRecord, VelocityDistribution};
+    use crate::scattering::Restitution;
+    use crate::thermostat::ThermostatKind;
+    use crate::utils::seed_random;
+    use nalgebra::Matrix2;
+
+    fn get_cs() -> CrystalStructure {
+        CrystalStructure {
+            x_size: 800.0,
+            y_size: 600.0,
+            ion_distance: 100.0,
+            acc: 0.0,
+            borders: Vec::new(),
+            ions: Vec::new(),
+            electrons: Vec::new(),
+            species: vec![Species::electron()],
+            traps: Vec::new(),
+            impurities: Vec::new(),
+            next_collision: (Weak::new(), Collidables::empty()),
+            time_to_bounce: f64::INFINITY,
+            flux: vec![Flux::default()],
+            time: 0.0,
+            vibration: None,
+            next_resample: f64::INFINITY,
+            generation: None,
+            next_generation: f64::INFINITY,
+            impact_ionisation: None,
+            carrier_stats: CarrierStats::default(),
+            optical_phonon: None,
+            phonons_emitted: 0,
+            scattering_laws: HashMap::new(),
+            electron_scattering: ElectronScattering::default(),
+            electron_collisions: 0,
+            momentum_relaxing_events: 0,
+            impurity_scatterings: 0,
+            pauli_blocking: None,
+            pauli_blocked: 0,
+            thermostat: None,
+            langevin: None,
+            next_langevin: f64::INFINITY,
+            supp: 0.0,
+            parallel: false,
+            batched: false,
+        }
+    }
+
+    fn borders() -> Vec<Border> {
+        vec![
+            Border::new(1.0, 0.0, 0.0, BorderType::Outer),
+            Border::new(0.0, 1.0, 0.0, BorderType::Outer),
+            Border::new(1.0, 0.0, 800.0, BorderType::Inner),
+            Border::new(0.0, 1.0, 600.0, BorderType::Inner),
+        ]
+    }
+
+    #[test]
+    fn with_ions_places_imported_sites() {
+        let records = vec![
+            IonRecord::new(Vector2::new(100.0, 100.0)),
+            IonRecord {
+                pos: Vector2::new(300.0, 250.0),
+                radius: Some(15.0),
+                species: Some(2),
+            },
+        ];
+        let cs = CrystalStructure::with_ions(800.0, 600.0, &records, 1.0, 0).unwrap();
+
+        assert_eq!(cs.ions.len(), 2);
+        assert_eq!(cs.ions[1].borrow().pos, Vector2::new(300.0, 250.0));
+        assert_eq!(cs.ions[1].borrow().radius, 15.0);
+        assert_eq!(cs.ions[1].borrow().species, 2);
+        assert_eq!(cs.borders.len(), 4);
+    }
+
+    #[test]
+    fn with_ions_rejects_overlap() {
+        let records = vec![
+            IonRecord::new(Vector2::new(100.0, 100.0)),
+            IonRecord::new(Vector2::new(110.0, 100.0)),
+        ];
+        let result = CrystalStructure::with_ions(800.0, 600.0, &records, 1.0, 0);
+
+        assert!(matches!(
+            result,
+            Err(IonImportError::Overlap {
+                first: 0,
+                second: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn vibration_resamples_on_schedule() {
+        seed_random(3);
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(400.0, 300.0)))));
+        cs.electrons.push(Rc::new(RefCell::new(Electron::new(
+            Vector2::new(100.0, 100.0),
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ))));
+        cs.update_collidables();
+
+        cs.set_vibration(Some(ThermalVibration::new(
+            1.0,
+            1.0,
+            PhononResampling::Every(2.0),
+        )));
+        let first = cs.ions[0].borrow().pos;
+        assert_ne!(first, cs.ions[0].borrow().site);
+
+        cs.update(0.0, 0.0);
+        assert_eq!(cs.ions[0].borrow().pos, first);
+        cs.update(0.0, 0.0);
+        assert_ne!(cs.ions[0].borrow().pos, first);
+        assert_eq!(cs.time, 2.0);
+
+        cs.set_vibration(None);
+        assert_eq!(cs.ions[0].borrow().pos, Vector2::new(400.0, 300.0));
+    }
+
+    #[test]
+    fn resampled_ions_keep_clear_of_ions_traps_and_impurities() {
+        seed_random(9);
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(400.0, 300.0)))));
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(412.0, 300.0)))));
+        cs.add_trap(Trap::new(Vector2::new(400.0, 312.0), 3.0, 1.0));
+        cs.add_impurity(Impurity::new(Vector2::new(388.0, 300.0), 1.0, 5.0));
+
+        // Wide enough that most draws would land on a neighbour.
+        cs.set_vibration(Some(ThermalVibration::new(
+            25.0,
+            1.0,
+            PhononResampling::Every(1.0),
+        )));
+        for _ in 0..50 {
+            cs.update(0.0, 0.0);
+            let (first, second) = (cs.ions[0].borrow(), cs.ions[1].borrow());
+            assert!((first.pos - second.pos).magnitude() >= first.radius + second.radius);
+            for ion in [&first, &second] {
+                let trap = cs.traps[0].borrow();
+                assert!((ion.pos - trap.pos).magnitude() >= ion.radius + trap.radius);
+                let impurity = cs.impurities[0].borrow();
+                assert!((ion.pos - impurity.pos).magnitude() >= ion.radius + impurity.cutoff);
+            }
+        }
+    }
+
+    #[test]
+    fn mobile_ion_takes_energy() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(100.0, 100.0)))));
+        cs.electrons.push(Rc::new(RefCell::new(Electron::new(
+            Vector2::new(86.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ))));
+        cs.set_mobile_ions(10.0, 0.1);
+
+        cs.update(0.0, 0.0);
+
+        let electron_energy = cs.electrons[0].borrow().vel.norm_squared() / 2.0;
+        assert!(cs.ions[0].borrow().vel.x > 0.0);
+        assert!(cs.electrons[0].borrow().vel.x < 0.0);
+        assert!(cs.lattice_energy() > 0.0);
+        assert!((electron_energy + cs.lattice_energy() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn add_carriers_of_new_species() {
+        seed_random(5);
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.init_ions();
+        let heavy = cs.add_species(Species::new("heavy", 4.0, -2.0, 4.0));
+        cs.add_carriers(heavy, 5, 1.0);
+        cs.update(0.5, 0.0);
+
+        assert_eq!(heavy, 1);
+        assert_eq!(cs.electrons.len(), 5);
+        cs.electrons.iter().for_each(|electron| {
+            let electron = electron.borrow();
+            assert_eq!(electron.species, heavy);
+            assert_eq!(electron.radius, 4.0);
+            assert_eq!(electron.acc, Vector2::new(0.25, 0.0));
+        });
+    }
+
+    #[test]
+    fn flux_counted_per_species() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        let holes = cs.add_species(Species::hole(2.0));
+        cs.electrons.push(Rc::new(RefCell::new(Electron::new(
+            Vector2::new(796.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ))));
+        cs.electrons.push(Rc::new(RefCell::new(Electron::of_species(
+            holes,
+            &cs.species[holes],
+            Vector2::new(795.0, 300.0),
+            Vector2::new(2.0, 0.0),
+        ))));
+        cs.electrons.push(Rc::new(RefCell::new(Electron::new(
+            Vector2::new(100.0, 596.0),
+            Vector2::new(1.0, 2.0),
+            Vector2::new(0.0, 0.0),
+        ))));
+        cs.update_collidables();
+
+        for _ in 0..4 {
+            cs.update(0.0, 0.0);
+        }
+
+        assert_eq!(cs.flux[0], Flux { left: 1, right: 0 });
+        assert_eq!(cs.flux[holes], Flux { left: 1, right: 0 });
+        assert_eq!(cs.species_current(0), -0.25);
+        assert_eq!(cs.species_current(holes), 0.25);
+        assert_eq!(cs.net_current(), 0.0);
+    }
+
+    #[test]
+    fn holes_drift_against_electrons() {
+        seed_random(9);
+        let mut cs = CrystalStructure::semiconductor(400.0, 300.0, 100.0, 0.0, 5, 5, 2.0);
+        cs.update(0.2, 0.0);
+
+        assert_eq!(cs.species[1].name, "hole");
+        assert!(cs.species_drift_velocity(0) > 0.0);
+        assert!(cs.species_drift_velocity(1) < 0.0);
+        assert!(cs.mobility(0) > 0.0);
+        assert!(cs.mobility(1) > 0.0);
+    }
+
+    #[test]
+    fn trap_captures_and_releases() {
+        seed_random(4);
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.add_trap(Trap::new(Vector2::new(200.0, 100.0), 5.0, 2.0));
+        cs.add_carrier(Electron::new(
+            Vector2::new(180.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+
+        for _ in 0..8 {
+            cs.update(0.0, 0.0);
+        }
+        assert!(cs.electrons.is_empty());
+        assert_eq!(cs.trapped_count(0), 1);
+        assert_eq!(cs.carrier_stats.captured, 1);
+
+        for _ in 0..100 {
+            cs.update(0.0, 0.0);
+        }
+        let stats = cs.carrier_stats;
+        assert!(stats.released >= 1);
+        assert_eq!(
+            (stats.captured - stats.released) as usize,
+            cs.trapped_count(0)
+        );
+        assert_eq!(cs.electrons.len() + cs.trapped_count(0), 1);
+    }
+
+    #[test]
+    fn trap_release_waits_for_ions_and_takes_the_field() {
+        seed_random(6);
+        let mut cs = get_cs();
+        cs.init_borders();
+        let mut trap = Trap::new(Vector2::new(200.0, 100.0), 5.0, 0.5);
+        trap.capture(
+            Electron::new(
+                Vector2::new(195.0, 100.0),
+                Vector2::new(1.0, 0.0),
+                Vector2::new(0.0, 0.0),
+            ),
+            0.0,
+        );
+        cs.add_trap(trap);
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(211.0, 100.0)))));
+
+        for _ in 0..20 {
+            cs.update(0.1, 0.0);
+        }
+        assert!(cs.electrons.is_empty());
+        assert_eq!(cs.trapped_count(0), 1);
+
+        cs.ions.clear();
+        while cs.electrons.is_empty() {
+            cs.update(0.1, 0.0);
+        }
+        let mut expected = Electron::new(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        expected.set_field(field_from_acc(0.1));
+        assert_eq!(cs.electrons[0].borrow().acc, expected.acc);
+        assert_eq!(cs.trapped_count(0), 0);
+    }
+
+    #[test]
+    fn electron_hole_recombine() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        let holes = cs.add_species(Species::hole(1.0));
+        cs.set_generation(Some(GenerationRecombination::new(0.0, 1.0, 0, holes, 1.0)));
+        cs.add_carrier(Electron::new(
+            Vector2::new(100.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+        cs.add_carrier(Electron::of_species(
+            holes,
+            &cs.species[holes],
+            Vector2::new(110.0, 100.0),
+            Vector2::new(-2.0, 0.0),
+        ));
+
+        cs.update(0.0, 0.0);
+
+        assert!(cs.electrons.is_empty());
+        assert_eq!(cs.carrier_stats.recombined, 1);
+        assert_eq!(cs.carrier_stats.mean_lifetime(), 1.0);
+    }
+
+    #[test]
+    fn electron_hole_pass_through_without_scattering() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        let holes = cs.add_species(Species::hole(1.0));
+        cs.set_generation(Some(GenerationRecombination::new(0.0, 0.0, 0, holes, 1.0)));
+        cs.set_electron_scattering(ElectronScattering::Disabled);
+        let electron = cs.add_carrier(Electron::new(
+            Vector2::new(100.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+        let hole = cs.add_carrier(Electron::of_species(
+            holes,
+            &cs.species[holes],
+            Vector2::new(110.0, 100.0),
+            Vector2::new(-2.0, 0.0),
+        ));
+
+        for _ in 0..10 {
+            cs.update(0.0, 0.0);
+        }
+
+        assert_eq!(cs.electrons.len(), 2);
+        assert_eq!(electron.borrow().vel, Vector2::new(2.0, 0.0));
+        assert_eq!(hole.borrow().vel, Vector2::new(-2.0, 0.0));
+        assert_eq!(cs.electron_collisions, 0);
+        assert_eq!(cs.collision_stats().electron.count, 0);
+        assert_eq!(cs.carrier_stats.recombined, 0);
+    }
+
+    #[test]
+    fn hot_carrier_ionises_ion() {
+        seed_random(5);
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(100.0, 90.0)))));
+        let holes = cs.add_species(Species::hole(1.0));
+        cs.set_impact_ionisation(Some(ImpactIonisation::new(10.0, 4.0, 1.0, 0, holes)));
+        cs.add_carrier(Electron::new(
+            Vector2::new(85.0, 90.0),
+            Vector2::new(5.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+
+        cs.update(0.0, 0.0);
+
+        assert_eq!(cs.carrier_stats.impact_ionisations, 1);
+        assert_eq!(cs.electrons.len(), 3);
+        assert_eq!(cs.electrons[2].borrow().species, holes);
+        let energy: f64 = cs
+            .electrons
+            .iter()
+            .map(|electron| {
+                let electron = electron.borrow();
+                0.5 * electron.mass * electron.vel.magnitude_squared()
+            })
+            .sum();
+        assert!((energy - (12.5 - 4.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cold_carrier_does_not_ionise() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(100.0, 90.0)))));
+        let holes = cs.add_species(Species::hole(1.0));
+        cs.set_impact_ionisation(Some(ImpactIonisation::new(20.0, 4.0, 1.0, 0, holes)));
+        cs.add_carrier(Electron::new(
+            Vector2::new(85.0, 90.0),
+            Vector2::new(5.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+
+        cs.update(0.0, 0.0);
+
+        assert_eq!(cs.carrier_stats.impact_ionisations, 0);
+        assert_eq!(cs.electrons.len(), 1);
+        assert_eq!(cs.electrons[0].borrow().vel, Vector2::new(-5.0, 0.0));
+    }
+
+    #[test]
+    fn ion_collision_emits_optical_phonon() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(100.0, 90.0)))));
+        cs.set_optical_phonon(Some(OpticalPhonon::new(4.5, 1.0)));
+        cs.add_carrier(Electron::new(
+            Vector2::new(85.0, 90.0),
+            Vector2::new(5.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+
+        cs.update(0.0, 0.0);
+
+        assert_eq!(cs.phonons_emitted, 1);
+        let vel = cs.electrons[0].borrow().vel;
+        assert!((vel - Vector2::new(-4.0, 0.0)).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn scattering_law_chosen_per_species() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions.push(Rc::new(RefCell::new(Ion::of_species(
+            Vector2::new(100.0, 90.0),
+            10.0,
+            1,
+        ))));
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(100.0, 40.0)))));
+        cs.set_scattering_law(1, Some(Box::new(Restitution::new(0.5))));
+        cs.add_carrier(Electron::new(
+            Vector2::new(85.0, 90.0),
+            Vector2::new(4.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+        cs.add_carrier(Electron::new(
+            Vector2::new(84.0, 40.0),
+            Vector2::new(4.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+
+        cs.update(0.0, 0.0);
+
+        assert_eq!(cs.electrons[0].borrow().vel, Vector2::new(-2.0, 0.0));
+        assert_eq!(cs.electrons[1].borrow().vel, Vector2::new(-4.0, 0.0));
+    }
+
+    fn head_on_pair(cs: &mut CrystalStructure, gap: f64) {
+        cs.init_borders();
+        cs.add_carrier(Electron::new(
+            Vector2::new(200.0, 300.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+        cs.add_carrier(Electron::new(
+            Vector2::new(200.0 + gap, 300.0),
+            Vector2::new(-2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+    }
+
+    #[test]
+    fn disabled_electron_scattering_passes_through() {
+        let mut cs = get_cs();
+        head_on_pair(&mut cs, 10.0);
+        cs.set_electron_scattering(ElectronScattering::Disabled);
+
+        for _ in 0..3 {
+            cs.update(0.0, 0.0);
         }
-    }
 
-    fn init_electrons(&mut self, init_velocity: f64, num_electrons: i32) {
-        for _ in 0..num_electrons {
-            'a: for _ in 0..INIT_ITERATIONS {
-                let x = (random() * (self.x_size - 2.0 * ELECTRON_RADIUS)) + ELECTRON_RADIUS;
-                let y = (random() * (self.y_size - 2.0 * ELECTRON_RADIUS)) + ELECTRON_RADIUS;
-                let angle = random() * 2.0 * std::f64::consts::PI;
-                let vel_x = angle.cos() * init_velocity;
-                let vel_y = angle.sin() * init_velocity;
-                let electron = Electron::new(
-                    Vector2::new(x, y),
-                    Vector2::new(vel_x, vel_y),
-                    Vector2::new(0.0, 0.0),
-                );
-
-                for ion in self.ions.iter() {
-                    let dist = (ion.borrow().pos - electron.pos).magnitude();
-                    if dist < ELEC_ELEC_RADIUS + ION_RADIUS {
-                        continue 'a;
-                    };
-                }
+        assert_eq!(cs.electrons[0].borrow().pos.x, 206.0);
+        assert_eq!(cs.electron_collisions, 0);
+    }
 
-                for el in self.electrons.iter() {
-                    let dist = (el.borrow().pos - electron.pos).magnitude();
-                    if dist < 2.0 * ELEC_ELEC_RADIUS {
-                        continue 'a;
-                    };
-                }
+    #[test]
+    fn monte_carlo_uses_cross_section() {
+        seed_random(14);
+        let mut cs = get_cs();
+        head_on_pair(&mut cs, 30.0);
+        cs.set_electron_scattering(ElectronScattering::MonteCarlo {
+            cross_section: 20.0,
+        });
 
-                self.electrons.push(Rc::new(RefCell::new(electron)));
-                break;
-            }
+        for _ in 0..3 {
+            cs.update(0.0, 0.0);
         }
+
+        assert_eq!(cs.electron_collisions, 1);
+        let momentum = cs.electrons[0].borrow().vel + cs.electrons[1].borrow().vel;
+        assert!(momentum.norm() < 1e-12);
     }
 
-    fn update_collidables(&mut self) {
-        let mut min: (Weak<RefCell<Electron>>, Collidables, f64) = (
-            Rc::downgrade(&self.electrons[0]),
-            Collidables::empty(),
-            INFINITY,
-        );
-        self.electrons.iter().for_each(|electron| {
-            let mut collidables: Vec<Collidables> = Vec::new();
+    #[test]
+    fn momentum_relaxing_counted_separately() {
+        seed_random(15);
+        let mut cs = get_cs();
+        head_on_pair(&mut cs, 10.0);
+        cs.set_electron_scattering(ElectronScattering::MomentumRelaxing);
 
-            self.borders.iter().for_each(|border| {
-                if CrystalStructure::filter_border(&electron.borrow(), &border.borrow()) {
-                    collidables.push(Collidables::new_b(border));
-                }
-            });
+        cs.update(0.0, 0.0);
 
-            self.ions.iter().for_each(|ion| {
-                if CrystalStructure::filter_ion(&electron.borrow(), &ion.borrow()) {
-                    collidables.push(Collidables::new_i(ion));
-                }
-            });
-            self.electrons.iter().for_each(|other| {
-                if !electron.borrow().eq(&other.borrow()) {
-                    collidables.push(Collidables::new_e(other));
-                }
-            });
+        assert_eq!(cs.electron_collisions, 0);
+        assert_eq!(cs.momentum_relaxing_events, 1);
+        cs.electrons
+            .iter()
+            .for_each(|electron| assert!((electron.borrow().vel.norm() - 2.0).abs() < 1e-12));
+    }
 
-            let timed_collidables: Vec<(Collidables, f64)> = collidables
-                .iter()
-                .map(|c| {
-                    (
-                        c.clone(),
-                        c.calc_time_to_collision(&electron.borrow(), self.x_size),
-                    )
-                })
-                .collect();
-            let (collidable, time_to_bounce) = timed_collidables
-                .iter()
-                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
-                .unwrap();
+    #[test]
+    fn impurity_turns_carrier_back() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.add_impurity(Impurity::new(Vector2::new(200.0, 300.0), -2.0, 20.0));
+        cs.add_carrier(Electron::new(
+            Vector2::new(100.0, 300.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
 
-            electron.borrow_mut().collidable = collidable.clone();
-            electron.borrow_mut().time_to_bounce = *time_to_bounce;
+        for _ in 0..46 {
+            cs.update(0.0, 0.0);
+        }
+        assert!(cs.electrons[0].borrow().transit.is_some());
+        for _ in 0..45 {
+            cs.update(0.0, 0.0);
+        }
 
-            if time_to_bounce < &min.2 {
-                min = (Rc::downgrade(electron), collidable.clone(), *time_to_bounce);
-            }
-        });
-        self.next_collision = (min.0, min.1);
-        self.time_to_bounce = min.2;
+        let electron = cs.electrons[0].borrow();
+        assert!(electron.transit.is_none());
+        assert_eq!(cs.impurity_scatterings, 1);
+        assert!((electron.vel - Vector2::new(-2.0, 0.0)).norm() < 1e-9);
+        assert!(electron.pos.x < 180.0);
     }
 
-    fn filter_border(electron: &Electron, border: &Border) -> bool {
-        match border.border_type {
-            BorderType::Inner => {
-                electron.vel.x > 0.0 && border.a == 1.0 && border.c > 0.0
-                    || electron.vel.y > 0.0 && border.b == 1.0 && border.c > 0.0
-            }
-            BorderType::Outer => {
-                electron.vel.x < 0.0 && border.a == 1.0 && border.c == 0.0
-                    || electron.vel.y < 0.0 && border.b == 1.0 && border.c == 0.0
-            }
+    #[test]
+    fn anisotropic_carriers_drift_across_field() {
+        seed_random(16);
+        let mut cs = get_cs();
+        cs.init_borders();
+        let tensor = Matrix2::new(1.0, 0.8, 0.8, 1.0);
+        let tilted = cs.add_species(Species::anisotropic("tilted", tensor, -1.0, 3.0).unwrap());
+        cs.add_carriers(tilted, 10, 0.0);
+
+        for _ in 0..5 {
+            cs.update(0.1, 0.0);
         }
+
+        let drift = cs.species_mean_velocity(tilted);
+        assert!(drift.x > 0.0);
+        assert!(drift.y < 0.0);
     }
 
-    fn filter_ion(electron: &Electron, ion: &Ion) -> bool {
-        if electron.vel.x > 0.0 && electron.vel.y > 0.0 {
-            (electron.pos.x - ION_ELEC_RADIUS <= ion.pos.x)
-                && (electron.pos.y - ION_ELEC_RADIUS <= ion.pos.y)
-        } else if electron.vel.x > 0.0 && electron.vel.y < 0.0 {
-            (electron.pos.x - ION_ELEC_RADIUS <= ion.pos.x)
-                && (electron.pos.y + ION_ELEC_RADIUS >= ion.pos.y)
-        } else if electron.vel.x < 0.0 && electron.vel.y > 0.0 {
-            (electron.pos.x + ION_ELEC_RADIUS >= ion.pos.x)
-                && (electron.pos.y - ION_ELEC_RADIUS <= ion.pos.y)
-        } else if electron.vel.x < 0.0 && electron.vel.y < 0.0 {
-            (electron.pos.x + ION_ELEC_RADIUS >= ion.pos.x)
-                && (electron.pos.y + ION_ELEC_RADIUS >= ion.pos.y)
-        } else if electron.vel.x == 0.0 && electron.vel.y > 0.0 {
-            electron.pos.y < ion.pos.y
-                && electron.pos.x - ION_ELEC_RADIUS - 1.0 <= ion.pos.x
-                && ion.pos.x <= electron.pos.x + ION_ELEC_RADIUS + 1.0
-        } else if electron.vel.x == 0.0 && electron.vel.y < 0.0 {
-            electron.pos.y > ion.pos.y
-                && electron.pos.x - ION_ELEC_RADIUS - 1.0 <= ion.pos.x
-                && ion.pos.x <= electron.pos.x + ION_ELEC_RADIUS + 1.0
-        } else if electron.vel.y == 0.0 && electron.vel.x > 0.0 {
-            electron.pos.x < ion.pos.x
-                && electron.pos.y - ION_ELEC_RADIUS - 1.0 <= ion.pos.y
-                && ion.pos.y <= electron.pos.x + ION_ELEC_RADIUS + 1.0
-        } else if electron.vel.y == 0.0 && electron.vel.x < 0.0 {
-            electron.pos.x > ion.pos.x
-                && electron.pos.y - ION_ELEC_RADIUS - 1.0 <= ion.pos.y
-                && ion.pos.y <= electron.pos.x + ION_ELEC_RADIUS + 1.0
-        } else {
-            false
-        }
+    #[test]
+    fn dirac_carrier_reflects_off_ion() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(100.0, 90.0)))));
+        let dirac = cs.add_species(Species::dirac("graphene", 4.0, -1.0, 3.0));
+        cs.add_carrier(Electron::of_species(
+            dirac,
+            &cs.species[dirac].clone(),
+            Vector2::new(85.0, 90.0),
+            Vector2::new(0.5, 0.0),
+        ));
+
+        cs.update(0.0, 0.0);
+
+        let electron = cs.electrons[0].borrow();
+        assert!((electron.vel - Vector2::new(-4.0, 0.0)).magnitude() < 1e-9);
+        assert!((electron.dirac.unwrap().momentum - Vector2::new(-0.5, 0.0)).magnitude() < 1e-9);
+        assert!((electron.pos.x - 85.0).abs() < 1e-6);
     }
 
-    pub fn update(&mut self, acc: f64, supp: f64) {
-        let mut time: f64 = 1.0;
+    #[test]
+    fn dirac_carriers_drift_at_fermi_velocity() {
+        seed_random(17);
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.init_ions();
+        let dirac = cs.add_species(Species::dirac("graphene", 3.0, -1.0, 3.0));
+        cs.add_carriers(dirac, 10, 1.0);
 
-        if self.acc != acc {
-            self.acc = acc;
-            let acc_vec = Vector2::new(acc, 0.0);
-            self.electrons.iter().for_each(|electron| {
-                electron.borrow_mut().acc = acc_vec;
-            });
-            self.update_collidables();
+        for _ in 0..20 {
+            cs.update(0.2, 0.0);
         }
 
-        while time > 0.0 {
-            if self.time_to_bounce > time {
-                self.electrons
-                    .iter()
-                    .for_each(|electron| electron.borrow_mut().update(time, supp));
-                self.time_to_bounce -= time;
-                return;
-            }
+        assert!(cs.electrons.iter().all(|electron| {
+            let electron = electron.borrow();
+            (electron.vel.magnitude() - 3.0).abs() < 1e-9
+                && electron.pos.y >= 0.0
+                && electron.pos.y <= cs.y_size
+        }));
+        assert!(cs.species_drift_velocity(dirac) > 0.0);
+        assert!(cs.mobility(dirac) > 0.0);
+    }
 
-            self.electrons
-                .iter()
-                .for_each(|electron| electron.borrow_mut().update(self.time_to_bounce, supp));
+    #[test]
+    fn pauli_blocked_carrier_passes_ion() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(100.0, 90.0)))));
+        cs.set_pauli_blocking(Some(PauliBlocking::new(1.0, 1)));
+        cs.add_carrier(Electron::new(
+            Vector2::new(85.0, 90.0),
+            Vector2::new(5.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
+        // Holds the reflected state.
+        cs.add_carrier(Electron::new(
+            Vector2::new(500.0, 300.0),
+            Vector2::new(-4.5, 0.5),
+            Vector2::new(0.0, 0.0),
+        ));
 
-            self.update_elec_stats(&self.next_collision.0.upgrade().unwrap().borrow());
-            self.next_collision
-                .1
-                .resolve_collision(&self.next_collision.0.upgrade().unwrap(), self.x_size);
-            time -= self.time_to_bounce;
-            self.update_collidables();
+        for _ in 0..8 {
+            cs.update(0.0, 0.0);
         }
+
+        assert_eq!(cs.pauli_blocked, 1);
+        let electron = cs.electrons[0].borrow();
+        assert_eq!(electron.vel, Vector2::new(5.0, 0.0));
+        assert!((electron.pos.x - 125.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn degenerate_carriers_fill_fermi_disk() {
+        seed_random(18);
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.add_fermi_carriers(0, 200, FermiDirac::new(0.0, 2.0));
+
+        assert!((cs.mean_kinetic_energy(0) - 1.0).abs() < 0.15);
+        assert!(cs
+            .electrons
+            .iter()
+            .all(|electron| electron.borrow().kinetic_energy() <= 2.0 + 1e-12));
+    }
+
+    #[test]
+    fn placement_strategies() {
+        seed_random(19);
+        let mut cs = CrystalStructure::new(800.0, 600.0, 100.0, 0.0, 0);
+        let maxwell = VelocityDistribution::Maxwell { temperature: 1.0 };
+        let lattice = InitStrategy::Lattice {
+            count: 10,
+            velocities: maxwell,
+        };
+        assert_eq!(cs.place_carriers(0, &lattice), Ok(10));
+        assert!(cs.electrons.iter().all(|electron| {
+            let interstitial = electron.borrow().pos - Vector2::new(50.0, 50.0);
+            cs.ions.iter().any(|ion| ion.borrow().site == interstitial)
+        }));
+
+        let beam = InitStrategy::Beam {
+            count: 4,
+            y: 300.0,
+            width: 30.0,
+            speed: 2.0,
+        };
+        assert_eq!(cs.place_carriers(0, &beam), Ok(4));
+        assert!(cs.electrons[10..].iter().all(|electron| {
+            let electron = electron.borrow();
+            electron.vel == Vector2::new(2.0, 0.0)
+                && (electron.pos.y - 300.0).abs() <= 15.0
+                && electron.pos.x < 100.0
+        }));
+    }
+
+    #[test]
+    fn placement_reports_full_structure() {
+        seed_random(20);
+        let mut cs = CrystalStructure::new(60.0, 60.0, 100.0, 0.0, 0);
+        let random = InitStrategy::Random {
+            count: 100,
+            velocities: VelocityDistribution::Monoenergetic { speed: 1.0 },
+        };
+        let err = cs.place_carriers(0, &random).unwrap_err();
+
+        assert!(matches!(err, PlacementError::NoRoom { requested: 100, placed } if placed > 0));
+        assert!(cs.electrons.is_empty());
+    }
+
+    #[test]
+    fn explicit_carriers_are_validated() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(100.0, 100.0)))));
+        let hole = cs.add_species(Species::hole(2.0));
+        let records = vec![
+            CarrierRecord {
+                pos: Vector2::new(200.0, 200.0),
+                vel: Vector2::new(1.0, 0.0),
+                species: Some(hole),
+            },
+            CarrierRecord {
+                pos: Vector2::new(105.0, 100.0),
+                vel: Vector2::new(1.0, 0.0),
+                species: None,
+            },
+        ];
+
+        assert_eq!(
+            cs.place_carriers(0, &InitStrategy::Explicit(records.clone())),
+            Err(PlacementError::Overlap { index: 1 })
+        );
+        assert!(cs.electrons.is_empty());
+        assert_eq!(
+            cs.place_carriers(0, &InitStrategy::Explicit(records[..1].to_vec())),
+            Ok(1)
+        );
+        assert_eq!(cs.electrons[0].borrow().species, hole);
+        cs.ion_distance = 0.0;
+        assert_eq!(
+            cs.place_carriers(
+                0,
+                &InitStrategy::Lattice {
+                    count: 1,
+                    velocities: VelocityDistribution::Monoenergetic { speed: 1.0 },
+                }
+            ),
+            Err(PlacementError::NoLattice)
+        );
     }
 
-    fn update_elec_stats(&mut self, electron: &Electron) {
-        match self.next_collision.1 {
-            Collidables::Border(_) if electron.vel.x > 0.0 => {
-                self.elec_left += 1;
+    #[test]
+    fn thermostat_holds_temperature_under_field() {
+        let run = |thermostat: Option<Thermostat>| {
+            seed_random(23);
+            let mut cs = CrystalStructure::new(800.0, 600.0, 100.0, 1.0, 30);
+            cs.set_thermostat(thermostat);
+            for _ in 0..100 {
+                cs.update(0.05, 0.0);
             }
-            Collidables::Border(_) if electron.vel.x < 0.0 => {
-                self.elec_right += 1;
+            (cs.carrier_temperature(), cs.drift_velocity())
+        };
+        let (heated, _) = run(None);
+        let (rescaled, drift) = run(Some(Thermostat::new(ThermostatKind::Rescale, 0.5, 1.0)));
+        let (berendsen, _) = run(Some(Thermostat::new(ThermostatKind::Berendsen, 0.5, 2.0)));
+
+        assert!(heated > 1.0);
+        assert!((rescaled - 0.5).abs() < 1e-9);
+        assert!(drift > 0.0);
+        assert!((berendsen - 0.5).abs() < 0.25);
+    }
+
+    #[test]
+    fn thermostat_redraws_the_langevin_step() {
+        seed_random(25);
+        let mut cs = CrystalStructure::new(800.0, 600.0, 100.0, 1.0, 20);
+        // Without noise the transition is deterministic and the drawn step can be recomputed.
+        cs.set_langevin(Some(Langevin::new(0.0, 5.0)));
+        cs.set_thermostat(Some(Thermostat::new(ThermostatKind::Rescale, 0.5, 1.0)));
+        cs.update(0.05, 0.1);
+
+        let langevin = cs.langevin.unwrap();
+        let time = cs.next_langevin - cs.time;
+        let field = field_from_acc(cs.acc);
+        for electron in cs.electrons.iter() {
+            let electron = electron.borrow();
+            let field_acc = cs.species[electron.species].acceleration(field);
+            let (pos, vel) = langevin.transition(
+                electron.pos,
+                electron.vel,
+                field_acc,
+                0.1,
+                electron.mass,
+                time,
+            );
+            let acc = (pos - electron.pos - electron.vel * time) * 2.0 / time.powi(2);
+            assert!((electron.acc - acc).magnitude() < 1e-9);
+            assert!((electron.langevin_vel.unwrap() - vel).magnitude() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn langevin_noise_balances_drag() {
+        let run = |langevin: Option<Langevin>| {
+            seed_random(24);
+            let mut cs = CrystalStructure::new(800.0, 600.0, 100.0, 0.2, 30);
+            cs.set_langevin(langevin);
+            let mut temperature = 0.0;
+            for tick in 0..300 {
+                cs.update(0.0, 0.1);
+                if tick >= 100 {
+                    temperature += cs.carrier_temperature() / 200.0;
+                }
             }
-            _ => {}
+            temperature
         };
+
+        assert!(run(None) < 0.01);
+        assert!((run(Some(Langevin::new(1.0, 1.0))) - 1.0).abs() < 0.2);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn released_carrier_keeps_its_langevin_velocity() {
+        seed_random(13);
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.set_langevin(Some(Langevin::new(0.0, 1.0)));
+        cs.add_trap(Trap::new(Vector2::new(200.0, 100.0), 5.0, 3.0));
+        cs.add_carrier(Electron::new(
+            Vector2::new(194.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        ));
 
-    fn get_cs() -> CrystalStructure {
-        CrystalStructure {
-            x_size: 800.0,
-            y_size: 600.0,
-            ion_distance: 100.0,
-            acc: 0.0,
-            borders: Vec::new(),
-            ions: Vec::new(),
-            electrons: Vec::new(),
-            next_collision: (Weak::new(), Collidables::empty()),
-            time_to_bounce: INFINITY,
-            elec_left: 0,
-            elec_right: 0,
+        for _ in 0..10 {
+            if cs.trapped_count(0) == 1 {
+                break;
+            }
+            cs.update(0.0, 0.1);
+        }
+        let occupant = cs.traps[0].borrow().occupant.clone().unwrap();
+        assert!(occupant.langevin_vel.is_none());
+
+        for _ in 0..200 {
+            if !cs.electrons.is_empty() {
+                break;
+            }
+            cs.update(0.0, 0.1);
         }
+        let released = cs.electrons[0].borrow().vel;
+        assert!(cs.electrons[0].borrow().langevin_vel.is_some());
+
+        // Without noise the drag only shortens the velocity it was released with.
+        cs.update(0.0, 0.1);
+        let vel = cs.electrons[0].borrow().vel;
+        assert!((vel.x * released.y - vel.y * released.x).abs() < 1e-9);
+        assert!(vel.dot(&released) > 0.0);
     }
 
-    fn borders() -> Vec<Border> {
-        vec![
-            Border::new(1.0, 0.0, 0.0, BorderType::Outer),
-            Border::new(0.0, 1.0, 0.0, BorderType::Outer),
-            Border::new(1.0, 0.0, 800.0, BorderType::Inner),
-            Border::new(0.0, 1.0, 600.0, BorderType::Inner),
-        ]
+    #[test]
+    fn pairs_generated_at_rate() {
+        seed_random(8);
+        let mut cs = get_cs();
+        cs.init_borders();
+        let holes = cs.add_species(Species::hole(1.0));
+        cs.set_generation(Some(GenerationRecombination::new(2.0, 0.0, 0, holes, 1.0)));
+
+        for _ in 0..10 {
+            cs.update(0.0, 0.0);
+        }
+
+        let generated = cs.carrier_stats.generated;
+        assert!(generated > 5 && generated < 40);
+        assert_eq!(cs.electrons.len(), 2 * generated as usize);
     }
 
     #[test]
@@ -466,6 +2776,44 @@ mod tests {
         assert!(!CrystalStructure::filter_ion(&electron, &ion_bottom));
     }
 
+    #[test]
+    fn filter_ion_right() {
+        let electron = Electron::new(
+            Vector2::new(50.0, 200.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let ion_right = Ion::new(Vector2::new(100.0, 200.0));
+        let ion_right_below = Ion::new(Vector2::new(100.0, 100.0));
+        let ion_right_above = Ion::new(Vector2::new(100.0, 300.0));
+        let ion_left = Ion::new(Vector2::new(0.0, 200.0));
+
+        assert!(CrystalStructure::filter_ion(&electron, &ion_right));
+
+        assert!(!CrystalStructure::filter_ion(&electron, &ion_right_below));
+        assert!(!CrystalStructure::filter_ion(&electron, &ion_right_above));
+        assert!(!CrystalStructure::filter_ion(&electron, &ion_left));
+    }
+
+    #[test]
+    fn filter_ion_left_off_diagonal() {
+        let electron = Electron::new(
+            Vector2::new(200.0, 50.0),
+            Vector2::new(-2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let ion_left = Ion::new(Vector2::new(100.0, 50.0));
+        let ion_left_above = Ion::new(Vector2::new(100.0, 150.0));
+        let ion_left_below = Ion::new(Vector2::new(100.0, -50.0));
+        let ion_right = Ion::new(Vector2::new(300.0, 50.0));
+
+        assert!(CrystalStructure::filter_ion(&electron, &ion_left));
+
+        assert!(!CrystalStructure::filter_ion(&electron, &ion_left_above));
+        assert!(!CrystalStructure::filter_ion(&electron, &ion_left_below));
+        assert!(!CrystalStructure::filter_ion(&electron, &ion_right));
+    }
+
     #[test]
     fn filter_ion_bottom() {
         let electron = Electron::new(
@@ -838,4 +3186,121 @@ mod tests {
         assert_eq!(cs.electrons[1].borrow().vel.x, 0.0);
         assert_eq!(cs.electrons[1].borrow().vel.y, -1.0);
     }
+
+    #[test]
+    fn collisions_by_partner_skip_periodic_crossings() {
+        let mut cs = get_cs();
+        cs.init_borders();
+        cs.ions
+            .push(Rc::new(RefCell::new(Ion::new(Vector2::new(100.0, 100.0)))));
+        for (pos, vel) in [
+            (Vector2::new(100.0, 70.0), Vector2::new(0.0, 20.0)),
+            (Vector2::new(400.0, 590.0), Vector2::new(0.0, 10.0)),
+            (Vector2::new(795.0, 300.0), Vector2::new(10.0, 0.0)),
+        ] {
+            cs.electrons.push(Rc::new(RefCell::new(Electron::new(
+                pos,
+                vel,
+                Vector2::new(0.0, 0.0),
+            ))));
+        }
+        cs.update_collidables();
+        cs.update(0.0, 0.0);
+
+        let (ion, wall, periodic) = (
+            cs.electrons[0].borrow(),
+            cs.electrons[1].borrow(),
+            cs.electrons[2].borrow(),
+        );
+        assert_eq!(ion.collisions.ion.count, 1);
+        assert!((ion.collisions.ion.mean - 0.85).abs() < 1e-9);
+        assert_eq!(wall.collisions.wall.count, 1);
+        assert!((wall.collisions.wall.mean - 0.7).abs() < 1e-9);
+        assert!((periodic.pos.x - 5.0).abs() < 1e-9);
+        assert_eq!(periodic.collisions.total(), 0);
+        assert_eq!(periodic.collisions.mean_free_time(), 0.0);
+
+        let stats = cs.collision_stats();
+        assert_eq!(
+            (stats.ion.count, stats.wall.count, stats.electron.count),
+            (1, 1, 0)
+        );
+    }
+
+    #[test]
+    fn batched_prediction_matches_serial() {
+        seed_random(23);
+        let mut cs = CrystalStructure::new(400.0, 300.0, 50.0, 1.0, 200);
+        cs.update(0.1, 0.0);
+        let predict = |cs: &mut CrystalStructure, batched: bool| {
+            cs.batched = batched;
+            cs.update_collidables();
+            let predictions: Vec<(Collidables, f64)> = cs
+                .electrons
+                .iter()
+                .map(|electron| {
+                    let electron = electron.borrow();
+                    (electron.collidable.clone(), electron.time_to_bounce)
+                })
+                .collect();
+            (predictions, cs.time_to_bounce)
+        };
+        let (serial, serial_min) = predict(&mut cs, false);
+        let (batched, batched_min) = predict(&mut cs, true);
+
+        assert!(serial
+            .iter()
+            .any(|(collidable, _)| matches!(collidable, Collidables::Electron(_))));
+        for ((serial, serial_time), (batched, batched_time)) in serial.iter().zip(batched.iter()) {
+            assert!(serial == batched);
+            assert!((serial_time - batched_time).abs() <= 1e-9 * serial_time.max(1.0));
+        }
+        assert!((serial_min - batched_min).abs() <= 1e-9);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_prediction_matches_serial() {
+        let run = |parallel: bool| {
+            seed_random(17);
+            let mut cs = CrystalStructure::new(400.0, 300.0, 50.0, 1.0, 300);
+            cs.parallel = parallel;
+            cs.update_collidables();
+            let predicted: Vec<f64> = cs
+                .electrons
+                .iter()
+                .map(|electron| electron.borrow().time_to_bounce)
+                .collect();
+            for _ in 0..3 {
+                cs.update(0.01, 0.0);
+            }
+            let states: Vec<(Vector2<f64>, Vector2<f64>)> = cs
+                .electrons
+                .iter()
+                .map(|electron| (electron.borrow().pos, electron.borrow().vel))
+                .collect();
+            (predicted, states, cs.time_to_bounce)
+        };
+        let serial = run(false);
+        let parallel = run(true);
+        assert_eq!(serial.0, parallel.0);
+        assert_eq!(serial.1, parallel.1);
+        assert_eq!(serial.2.to_bits(), parallel.2.to_bits());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_free_sites_match_serial() {
+        seed_random(5);
+        let mut cs = CrystalStructure::new(400.0, 300.0, 50.0, 1.0, 300);
+        let sites = |cs: &mut CrystalStructure, parallel: bool| {
+            cs.parallel = parallel;
+            seed_random(9);
+            cs.free_sites(1.0)
+        };
+        let serial = sites(&mut cs, false);
+        let parallel = sites(&mut cs, true);
+        assert!(!serial.is_empty());
+        assert_eq!(serial, parallel);
+    }
 }
@@ -1,11 +1,37 @@
-use js_sys::Array;
+use js_sys::{Array, JSON};
+use nalgebra::{Matrix2, Vector2};
 use wasm_bindgen::prelude::*;
 
-use crate::{crystal_structure::CrystalStructure, electron_js::ElectronJs, ion_js::IonJs};
+use crate::{
+    boltzmann::VelocityGrid,
+    collision_stats::CollisionKind,
+    crystal_structure::CrystalStructure,
+    electron_js::ElectronJs,
+    generation::{GenerationRecombination, ImpactIonisation},
+    impurity::Impurity,
+    impurity_js::ImpurityJs,
+    ion_import::{self, IonRecord},
+    ion_js::IonJs,
+    langevin::Langevin,
+    placement::{self, InitStrategy, VelocityDistribution},
+    precision,
+    precision_js::DivergenceReportJs,
+    scattering::{
+        ElectronScattering, ForwardPeaked, IsotropicRandom, Restitution, ScatteringLaw, Specular,
+        Thermalising,
+    },
+    sommerfeld::{FermiDirac, PauliBlocking},
+    species::Species,
+    species_js::SpeciesJs,
+    thermal::{OpticalPhonon, PhononResampling, ThermalVibration},
+    thermostat::{Thermostat, ThermostatKind},
+    trap::Trap,
+    trap_js::TrapJs,
+};
 
 #[wasm_bindgen(js_name = CrystalStructure)]
 pub struct CrystalStructureJs {
-    cs: CrystalStructure,
+    pub(crate) cs: CrystalStructure,
 }
 
 #[wasm_bindgen(js_class = CrystalStructure)]
@@ -23,6 +49,65 @@ impl CrystalStructureJs {
         }
     }
 
+    // Electrons and holes in one structure, holes are species 1.
+    pub fn semiconductor(
+        x_size: f64,
+        y_size: f64,
+        ion_distance: f64,
+        init_velocity: f64,
+        num_electrons: i32,
+        num_holes: i32,
+        hole_mass: f64,
+    ) -> CrystalStructureJs {
+        CrystalStructureJs {
+            cs: CrystalStructure::semiconductor(
+                x_size,
+                y_size,
+                ion_distance,
+                init_velocity,
+                num_electrons,
+                num_holes,
+                hole_mass,
+            ),
+        }
+    }
+
+    // Accepts an array of `{x, y, radius?, type?}` objects or `[x, y, radius?, type?]` arrays.
+    pub fn from_ions(
+        x_size: f64,
+        y_size: f64,
+        ions: Array,
+        init_velocity: f64,
+        num_electrons: i32,
+    ) -> Result<CrystalStructureJs, JsError> {
+        let json = JSON::stringify(&ions)
+            .map_err(|_| JsError::new("ions must be a JSON serializable array"))?;
+        let records = ion_import::parse_json(&String::from(json))?;
+        Self::with_ions(x_size, y_size, &records, init_velocity, num_electrons)
+    }
+
+    pub fn from_ions_csv(
+        x_size: f64,
+        y_size: f64,
+        csv: &str,
+        init_velocity: f64,
+        num_electrons: i32,
+    ) -> Result<CrystalStructureJs, JsError> {
+        let records = ion_import::parse_csv(csv)?;
+        Self::with_ions(x_size, y_size, &records, init_velocity, num_electrons)
+    }
+
+    pub fn from_ions_json(
+        x_size: f64,
+        y_size: f64,
+        json: &str,
+        init_velocity: f64,
+        num_electrons: i32,
+    ) -> Result<CrystalStructureJs, JsError> {
+        let records = ion_import::parse_json(json)?;
+        Self::with_ions(x_size, y_size, &records, init_velocity, num_electrons)
+    }
+
     #[wasm_bindgen(getter)]
     pub fn x_size(&self) -> f64 {
         self.cs.x_size
@@ -35,18 +120,444 @@ impl CrystalStructureJs {
 
     #[wasm_bindgen(getter)]
     pub fn elec_left(&self) -> i32 {
-        self.cs.elec_left
+        self.cs.flux[0].left
     }
 
     #[wasm_bindgen(getter)]
     pub fn elec_right(&self) -> i32 {
-        self.cs.elec_right
+        self.cs.flux[0].right
+    }
+
+    pub fn flux_left(&self, species: usize) -> i32 {
+        self.cs.flux[species].left
+    }
+
+    pub fn flux_right(&self, species: usize) -> i32 {
+        self.cs.flux[species].right
+    }
+
+    pub fn species_current(&self, species: usize) -> f64 {
+        self.cs.species_current(species)
+    }
+
+    pub fn net_current(&self) -> f64 {
+        self.cs.net_current()
+    }
+
+    pub fn species_drift_velocity(&self, species: usize) -> f64 {
+        self.cs.species_drift_velocity(species)
+    }
+
+    // Mean velocity of a species perpendicular to the field.
+    pub fn species_transverse_velocity(&self, species: usize) -> f64 {
+        self.cs.species_mean_velocity(species).y
+    }
+
+    pub fn mobility(&self, species: usize) -> f64 {
+        self.cs.mobility(species)
     }
 
     pub fn update(&mut self, acc: f64, supp: f64) {
         self.cs.update(acc, supp);
     }
 
+    // Enables frozen-phonon disorder at the given temperature.
+    // A non-positive `resample_period` draws a new displacement for the struck ion after every collision.
+    pub fn set_vibration(&mut self, temperature: f64, stiffness: f64, resample_period: f64) {
+        let resampling = if resample_period > 0.0 {
+            PhononResampling::Every(resample_period)
+        } else {
+            PhononResampling::OnScatter
+        };
+        self.cs.set_vibration(Some(ThermalVibration::new(
+            temperature,
+            stiffness,
+            resampling,
+        )));
+    }
+
+    pub fn clear_vibration(&mut self) {
+        self.cs.set_vibration(None);
+    }
+
+    // Chooses the scattering law for an ion species by name:
+    // "specular", "isotropic", "forward" (anisotropy), "restitution" (coefficient) or "thermal" (temperature).
+    pub fn set_scattering_law(
+        &mut self,
+        species: u32,
+        law: &str,
+        parameter: f64,
+    ) -> Result<(), JsError> {
+        let law: Box<dyn ScatteringLaw> = match law {
+            "specular" => Box::new(Specular),
+            "isotropic" => Box::new(IsotropicRandom),
+            "forward" => Box::new(ForwardPeaked::new(parameter)),
+            "restitution" => Box::new(Restitution::new(parameter)),
+            "thermal" => Box::new(Thermalising::new(parameter)),
+            _ => return Err(JsError::new(&format!("unknown scattering law '{}'", law))),
+        };
+        self.cs.set_scattering_law(species, Some(law));
+        Ok(())
+    }
+
+    pub fn clear_scattering_law(&mut self, species: u32) {
+        self.cs.set_scattering_law(species, None);
+    }
+
+    // Chooses the carrier-carrier interaction by name:
+    // "disabled", "hard_disk", "monte_carlo" (with `cross_section`) or "momentum_relaxing".
+    pub fn set_electron_scattering(
+        &mut self,
+        mode: &str,
+        cross_section: f64,
+    ) -> Result<(), JsError> {
+        let mode = match mode {
+            "disabled" => ElectronScattering::Disabled,
+            "hard_disk" => ElectronScattering::HardDisk,
+            "monte_carlo" => ElectronScattering::MonteCarlo { cross_section },
+            "momentum_relaxing" => ElectronScattering::MomentumRelaxing,
+            _ => {
+                return Err(JsError::new(&format!(
+                    "unknown electron scattering '{}'",
+                    mode
+                )))
+            }
+        };
+        self.cs.set_electron_scattering(mode);
+        Ok(())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn electron_collisions(&self) -> i32 {
+        self.cs.electron_collisions
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn momentum_relaxing_events(&self) -> i32 {
+        self.cs.momentum_relaxing_events
+    }
+
+    // Makes ion collisions inelastic: carriers above `quantum` kinetic energy lose it with `probability`.
+    pub fn set_optical_phonon(&mut self, quantum: f64, probability: f64) {
+        self.cs
+            .set_optical_phonon(Some(OpticalPhonon::new(quantum, probability)));
+    }
+
+    pub fn clear_optical_phonon(&mut self) {
+        self.cs.set_optical_phonon(None);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn phonons_emitted(&self) -> i32 {
+        self.cs.phonons_emitted
+    }
+
+    // Lets ions recoil around their sites, an infinite mass pins them again.
+    pub fn set_mobile_ions(&mut self, mass: f64, stiffness: f64) {
+        self.cs.set_mobile_ions(mass, stiffness);
+    }
+
+    pub fn lattice_energy(&self) -> f64 {
+        self.cs.lattice_energy()
+    }
+
+    pub fn drift_velocity(&self) -> f64 {
+        self.cs.drift_velocity()
+    }
+
+    // Registers a carrier species and returns its id for `add_carriers`.
+    pub fn add_species(&mut self, name: &str, mass: f64, charge: f64, radius: f64) -> usize {
+        self.cs
+            .add_species(Species::new(name, mass, charge, radius))
+    }
+
+    // Species with the symmetric mass tensor [[mass_xx, mass_xy], [mass_xy, mass_yy]].
+    pub fn add_anisotropic_species(
+        &mut self,
+        name: &str,
+        mass_xx: f64,
+        mass_xy: f64,
+        mass_yy: f64,
+        charge: f64,
+        radius: f64,
+    ) -> Result<usize, JsError> {
+        let tensor = Matrix2::new(mass_xx, mass_xy, mass_xy, mass_yy);
+        let species = Species::anisotropic(name, tensor, charge, radius)
+            .ok_or_else(|| JsError::new("mass tensor must be positive definite"))?;
+        Ok(self.cs.add_species(species))
+    }
+
+    // Massless carrier moving at `fermi_velocity`, velocities given to `add_carriers` become momenta m * v.
+    pub fn add_dirac_species(
+        &mut self,
+        name: &str,
+        fermi_velocity: f64,
+        charge: f64,
+        radius: f64,
+    ) -> usize {
+        self.cs
+            .add_species(Species::dirac(name, fermi_velocity, charge, radius))
+    }
+
+    pub fn add_carriers(&mut self, species: usize, count: i32, init_velocity: f64) {
+        self.cs.add_carriers(species, count, init_velocity);
+    }
+
+    // Carriers at random free positions with Maxwell-Boltzmann velocities at `temperature`,
+    // shifted by `drift` along the field. Fails when they do not fit.
+    pub fn place_maxwell(
+        &mut self,
+        species: usize,
+        count: usize,
+        temperature: f64,
+        drift: f64,
+    ) -> Result<usize, JsError> {
+        let velocities = if drift == 0.0 {
+            VelocityDistribution::Maxwell { temperature }
+        } else {
+            VelocityDistribution::DriftingMaxwell { temperature, drift }
+        };
+        Ok(self
+            .cs
+            .place_carriers(species, &InitStrategy::Random { count, velocities })?)
+    }
+
+    pub fn place_monoenergetic(
+        &mut self,
+        species: usize,
+        count: usize,
+        speed: f64,
+    ) -> Result<usize, JsError> {
+        let velocities = VelocityDistribution::Monoenergetic { speed };
+        Ok(self
+            .cs
+            .place_carriers(species, &InitStrategy::Random { count, velocities })?)
+    }
+
+    // Carriers on interstitial sites between the ions, with Maxwell-Boltzmann velocities.
+    pub fn place_on_lattice(
+        &mut self,
+        species: usize,
+        count: usize,
+        temperature: f64,
+    ) -> Result<usize, JsError> {
+        let velocities = VelocityDistribution::Maxwell { temperature };
+        Ok(self
+            .cs
+            .place_carriers(species, &InitStrategy::Lattice { count, velocities })?)
+    }
+
+    pub fn place_beam(
+        &mut self,
+        species: usize,
+        count: usize,
+        y: f64,
+        width: f64,
+        speed: f64,
+    ) -> Result<usize, JsError> {
+        let beam = InitStrategy::Beam {
+            count,
+            y,
+            width,
+            speed,
+        };
+        Ok(self.cs.place_carriers(species, &beam)?)
+    }
+
+    // Accepts an array of `{x, y, vx?, vy?, species?}` objects or `[x, y, vx?, vy?, species?]` arrays,
+    // entries without a species get `species`.
+    pub fn place_explicit(&mut self, species: usize, carriers: Array) -> Result<usize, JsError> {
+        let json = JSON::stringify(&carriers)
+            .map_err(|_| JsError::new("carriers must be a JSON serializable array"))?;
+        self.place_explicit_json(species, &String::from(json))
+    }
+
+    pub fn place_explicit_json(&mut self, species: usize, json: &str) -> Result<usize, JsError> {
+        let records = placement::parse_json(json)?;
+        Ok(self
+            .cs
+            .place_carriers(species, &InitStrategy::Explicit(records))?)
+    }
+
+    // Thermal noise at `temperature` balancing the drag, drawn every `step` ticks.
+    pub fn set_langevin(&mut self, temperature: f64, step: f64) {
+        self.cs.set_langevin(Some(Langevin::new(temperature, step)));
+    }
+
+    pub fn clear_langevin(&mut self) {
+        self.cs.set_langevin(None);
+    }
+
+    // Couples the carriers to a heat bath by name: "rescale", "berendsen", "andersen" or "nose_hoover".
+    pub fn set_thermostat(
+        &mut self,
+        kind: &str,
+        temperature: f64,
+        coupling_time: f64,
+    ) -> Result<(), JsError> {
+        let kind = match kind {
+            "rescale" => ThermostatKind::Rescale,
+            "berendsen" => ThermostatKind::Berendsen,
+            "andersen" => ThermostatKind::Andersen,
+            "nose_hoover" => ThermostatKind::NoseHoover,
+            _ => return Err(JsError::new(&format!("unknown thermostat '{}'", kind))),
+        };
+        self.cs
+            .set_thermostat(Some(Thermostat::new(kind, temperature, coupling_time)));
+        Ok(())
+    }
+
+    pub fn clear_thermostat(&mut self) {
+        self.cs.set_thermostat(None);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn carrier_temperature(&self) -> f64 {
+        self.cs.carrier_temperature()
+    }
+
+    // Carriers with kinetic energies from the Fermi-Dirac distribution instead of one speed.
+    pub fn add_fermi_carriers(
+        &mut self,
+        species: usize,
+        count: i32,
+        temperature: f64,
+        fermi_energy: f64,
+    ) {
+        self.cs
+            .add_fermi_carriers(species, count, FermiDirac::new(temperature, fermi_energy));
+    }
+
+    // Rejects scattering into momentum cells of side `cell_size` holding `degeneracy` carriers.
+    pub fn set_pauli_blocking(&mut self, cell_size: f64, degeneracy: u32) {
+        self.cs
+            .set_pauli_blocking(Some(PauliBlocking::new(cell_size, degeneracy)));
+    }
+
+    pub fn clear_pauli_blocking(&mut self) {
+        self.cs.set_pauli_blocking(None);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn pauli_blocked(&self) -> i32 {
+        self.cs.pauli_blocked
+    }
+
+    pub fn mean_kinetic_energy(&self, species: usize) -> f64 {
+        self.cs.mean_kinetic_energy(species)
+    }
+
+    pub fn add_trap(&mut self, x: f64, y: f64, radius: f64, mean_dwell: f64) {
+        self.cs
+            .add_trap(Trap::new(Vector2::new(x, y), radius, mean_dwell));
+    }
+
+    pub fn get_traps(&self) -> Array {
+        self.cs
+            .traps
+            .iter()
+            .map(|trap| JsValue::from(TrapJs::new(&trap.borrow())))
+            .collect()
+    }
+
+    // Charged impurity deflecting carriers with a Coulomb force within `cutoff`.
+    pub fn add_impurity(&mut self, x: f64, y: f64, charge: f64, cutoff: f64) {
+        self.cs
+            .add_impurity(Impurity::new(Vector2::new(x, y), charge, cutoff));
+    }
+
+    pub fn get_impurities(&self) -> Array {
+        self.cs
+            .impurities
+            .iter()
+            .map(|impurity| JsValue::from(ImpurityJs::new(&impurity.borrow())))
+            .collect()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn impurity_scatterings(&self) -> i32 {
+        self.cs.impurity_scatterings
+    }
+
+    pub fn trapped_count(&self, species: usize) -> usize {
+        self.cs.trapped_count(species)
+    }
+
+    pub fn effective_mobility(&self, species: usize) -> f64 {
+        self.cs.effective_mobility(species)
+    }
+
+    // Generates electron-hole pairs at `rate` per tick and lets them recombine on contact.
+    pub fn set_generation(
+        &mut self,
+        rate: f64,
+        recombination_probability: f64,
+        electron_species: usize,
+        hole_species: usize,
+        init_velocity: f64,
+    ) {
+        self.cs.set_generation(Some(GenerationRecombination::new(
+            rate,
+            recombination_probability,
+            electron_species,
+            hole_species,
+            init_velocity,
+        )));
+    }
+
+    pub fn clear_generation(&mut self) {
+        self.cs.set_generation(None);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn generated(&self) -> i32 {
+        self.cs.carrier_stats.generated
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn recombined(&self) -> i32 {
+        self.cs.carrier_stats.recombined
+    }
+
+    pub fn mean_lifetime(&self) -> f64 {
+        self.cs.carrier_stats.mean_lifetime()
+    }
+
+    // Lets carriers above `threshold` kinetic energy create an electron-hole pair when they hit an ion.
+    pub fn set_impact_ionisation(
+        &mut self,
+        threshold: f64,
+        gap: f64,
+        probability: f64,
+        electron_species: usize,
+        hole_species: usize,
+    ) {
+        self.cs.set_impact_ionisation(Some(ImpactIonisation::new(
+            threshold,
+            gap,
+            probability,
+            electron_species,
+            hole_species,
+        )));
+    }
+
+    pub fn clear_impact_ionisation(&mut self) {
+        self.cs.set_impact_ionisation(None);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn impact_ionisations(&self) -> i32 {
+        self.cs.carrier_stats.impact_ionisations
+    }
+
+    pub fn get_species(&self) -> Array {
+        self.cs
+            .species
+            .iter()
+            .map(|species| JsValue::from(SpeciesJs::new(species)))
+            .collect()
+    }
+
     pub fn get_ions(&self) -> Array {
         self.cs
             .ions
@@ -63,13 +574,70 @@ impl CrystalStructureJs {
             .collect()
     }
 
-    pub fn avg_ticks_between_bounces(&self) -> f64 {
-        let sum = self
-            .cs
+    // Carriers of a single species, e.g. to draw electrons and holes in different colors.
+    pub fn get_carriers(&self, species: usize) -> Array {
+        self.cs
             .electrons
             .iter()
-            .fold(0.0, |acc, el| acc + el.borrow().avg_ticks_between_bounces);
+            .filter(|electron| electron.borrow().species == species)
+            .map(|electron| JsValue::from(ElectronJs::new(&electron.borrow())))
+            .collect()
+    }
+
+    // Same layout as `BoltzmannSolver.distribution` for a grid with the given `v_max` and `cells`.
+    pub fn velocity_histogram(&self, species: usize, v_max: f64, cells: usize) -> Vec<f64> {
+        self.cs
+            .velocity_histogram(species, &VelocityGrid::new(v_max, cells))
+    }
+
+    // Replays the ion collisions from the current state in f32, f64 and double-double.
+    pub fn compare_precisions(
+        &self,
+        acc: f64,
+        ticks: usize,
+    ) -> Result<DivergenceReportJs, JsError> {
+        Ok(DivergenceReportJs::new(precision::compare_precisions(
+            &self.cs, acc, ticks,
+        )?))
+    }
+
+    // Pooled mean free time under the name the app has always used.
+    pub fn avg_ticks_between_bounces(&self) -> f64 {
+        self.cs.mean_free_time()
+    }
+
+    // Collisions with one kind of partner: "ion", "electron", "wall" or "impurity".
+    // Crossings of the periodic x borders are not collisions and are never counted.
+    pub fn collision_count(&self, kind: &str) -> Result<i32, JsError> {
+        Ok(self.cs.collision_stats().get(collision_kind(kind)?).count)
+    }
 
-        sum / self.cs.electrons.len() as f64
+    // Mean time between collisions with one kind of partner, pooled over the carriers.
+    pub fn partner_mean_free_time(&self, kind: &str) -> Result<f64, JsError> {
+        Ok(self.cs.collision_stats().get(collision_kind(kind)?).mean)
+    }
+
+    // Mean free time combined from the per-partner ones, 1/tau = sum of 1/tau_k.
+    pub fn combined_mean_free_time(&self) -> f64 {
+        self.cs.collision_stats().mean_free_time()
+    }
+}
+
+pub(crate) fn collision_kind(name: &str) -> Result<CollisionKind, JsError> {
+    CollisionKind::from_name(name)
+        .ok_or_else(|| JsError::new(&format!("unknown collision partner '{}'", name)))
+}
+
+impl CrystalStructureJs {
+    fn with_ions(
+        x_size: f64,
+        y_size: f64,
+        records: &[IonRecord],
+        init_velocity: f64,
+        num_electrons: i32,
+    ) -> Result<CrystalStructureJs, JsError> {
+        Ok(CrystalStructureJs {
+            cs: CrystalStructure::with_ions(x_size, y_size, records, init_velocity, num_electrons)?,
+        })
     }
 }
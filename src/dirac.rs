@@ -0,0 +1,147 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+use crate::cfg::EPSILON;
+
+// Collision predictions of Dirac carriers look this far ahead, a re-prediction is scheduled after.
+pub const HORIZON: f64 = 1.0;
+// Largest fraction of the smaller body a carrier may cross between two samples of the gap.
+const SAMPLE_FRACTION: f64 = 0.5;
+const BISECTIONS: usize = 60;
+
+// Kinematic state of a massless carrier with the linear dispersion E = v_F * |p|.
+// The field changes the momentum at the rate `force`, the speed stays at `fermi_velocity`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiracState {
+    pub fermi_velocity: f64,
+    pub momentum: Vector2<f64>,
+    pub force: Vector2<f64>,
+}
+
+impl DiracState {
+    pub fn velocity(&self) -> Vector2<f64> {
+        velocity(self.momentum, self.fermi_velocity)
+    }
+
+    pub fn energy(&self) -> f64 {
+        self.fermi_velocity * self.momentum.magnitude()
+    }
+
+    // Displacement after `time` and the momentum reached.
+    // With the momentum split into p_par along the force and a constant p_perp across it,
+    // x_par = v_F / F * (|p(t)| - |p(0)|) and x_perp = v_F * p_perp / F * (asinh(p_par(t) / |p_perp|) - asinh(p_par(0) / |p_perp|)).
+    pub fn displacement(&self, time: f64) -> (Vector2<f64>, Vector2<f64>) {
+        let momentum = self.momentum + self.force * time;
+        let force = self.force.magnitude();
+        if force == 0.0 {
+            return (self.velocity() * time, momentum);
+        }
+        let along = self.force / force;
+        let across = Vector2::new(-along.y, along.x);
+        let p_perp = self.momentum.dot(&across);
+        let (start, end) = (self.momentum.dot(&along), momentum.dot(&along));
+
+        let parallel = (momentum.magnitude() - self.momentum.magnitude()) / force;
+        let perpendicular = if p_perp == 0.0 {
+            0.0
+        } else {
+            p_perp / force * ((end / p_perp.abs()).asinh() - (start / p_perp.abs()).asinh())
+        };
+        (
+            (along * parallel + across * perpendicular) * self.fermi_velocity,
+            momentum,
+        )
+    }
+
+    // Keeps |p| and turns the momentum to the direction of `vel`, set by a collision.
+    pub fn align(&mut self, vel: Vector2<f64>) {
+        if vel.magnitude() > 0.0 {
+            self.momentum = vel.normalize() * self.momentum.magnitude();
+        }
+    }
+}
+
+pub fn velocity(momentum: Vector2<f64>, fermi_velocity: f64) -> Vector2<f64> {
+    if momentum.magnitude() == 0.0 {
+        return Vector2::new(0.0, 0.0);
+    }
+    momentum.normalize() * fermi_velocity
+}
+
+// Sampling step that cannot jump over a body of the given size at the given closing speed.
+pub fn sample_step(size: f64, closing_speed: f64) -> f64 {
+    SAMPLE_FRACTION * size / closing_speed.max(EPSILON)
+}
+
+// First time in (EPSILON, horizon] at which `gap` drops to zero from above.
+// The gap is sampled every `step` and the crossing refined by bisection; contacts shallower
+// than the sampling resolution may be missed. Returns infinity when there is none.
+pub fn first_contact(gap: impl Fn(f64) -> f64, step: f64, horizon: f64) -> f64 {
+    let mut armed = gap(EPSILON) > 0.0;
+    let mut low = EPSILON;
+    while low < horizon {
+        let high = (low + step).min(horizon);
+        let next = gap(high);
+        if armed && next <= 0.0 {
+            let (mut low, mut high) = (low, high);
+            for _ in 0..BISECTIONS {
+                let mid = (low + high) / 2.0;
+                if gap(mid) > 0.0 {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            return high;
+        }
+        armed |= next > 0.0;
+        low = high;
+    }
+    f64::INFINITY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displacement_matches_integration() {
+        let state = DiracState {
+            fermi_velocity: 2.0,
+            momentum: Vector2::new(-1.0, 0.5),
+            force: Vector2::new(0.3, 0.1),
+        };
+        let steps = 100_000;
+        let dt = 10.0 / steps as f64;
+        let mut pos = Vector2::new(0.0, 0.0);
+        for i in 0..steps {
+            let momentum = state.momentum + state.force * ((i as f64 + 0.5) * dt);
+            pos += velocity(momentum, 2.0) * dt;
+        }
+        let (displacement, momentum) = state.displacement(10.0);
+
+        assert!((displacement - pos).magnitude() < 1e-6);
+        assert!((momentum - Vector2::new(2.0, 1.5)).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn speed_fixed_at_fermi_velocity() {
+        let state = DiracState {
+            fermi_velocity: 3.0,
+            momentum: Vector2::new(0.0, 0.1),
+            force: Vector2::new(5.0, 0.0),
+        };
+        assert!((state.velocity().magnitude() - 3.0).abs() < 1e-12);
+        let (_, momentum) = state.displacement(2.0);
+        assert!((velocity(momentum, 3.0).magnitude() - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn first_contact_brackets_crossing() {
+        let time = first_contact(|t| (t - 0.3).powi(2) - 0.01, 0.05, 1.0);
+        assert!((time - 0.2).abs() < 1e-12);
+        assert_eq!(first_contact(|t| 1.0 + t, 0.05, 1.0), f64::INFINITY);
+        // Already overlapping: only a fresh approach counts.
+        assert_eq!(first_contact(|t| t - 0.5, 0.05, 1.0), f64::INFINITY);
+    }
+}
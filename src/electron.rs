@@ -1,89 +1,286 @@
-use crate::cfg::ELEC_ELEC_RADIUS;
 use crate::collidable::Collidable;
 
 use crate::collidables::Collidables;
-use crate::utils::calc_time_to_collision;
+use crate::collision_stats::{CollisionKind, CollisionStats};
+use crate::dirac::DiracState;
+use crate::impurity::{propagate, Transit};
+use crate::species::Species;
+use crate::utils::{calc_time_to_collision, elastic_collision, tensor_collision};
 
 extern crate nalgebra as na;
-use na::Vector2;
+use na::{Matrix2, Vector2};
 
 #[derive(Clone)]
 pub struct Electron {
     pub pos: Vector2<f64>,
     pub vel: Vector2<f64>,
     pub acc: Vector2<f64>,
+    pub species: usize,
+    pub mass: f64,
+    pub mass_tensor: Option<Matrix2<f64>>,
+    pub charge: f64,
+    pub radius: f64,
+    pub born_at: f64,
     pub time_to_bounce: f64,
     pub collidable: Collidables,
-    ticks_since_bounce: f64,
-    pub avg_ticks_between_bounces: f64,
-    bounce_count: i32,
+    // Set while the carrier orbits inside the cutoff of a charged impurity.
+    pub transit: Option<Transit>,
+    // Momentum and Fermi velocity of carriers with the linear dispersion.
+    pub dirac: Option<DiracState>,
+    // Velocity drawn for the end of the current Langevin step. While it is set, `acc` is the
+    // constant acceleration that reaches the drawn end position and the drag is already included.
+    pub langevin_vel: Option<Vector2<f64>>,
+    // Scattering events broken down by partner, periodic crossings are not counted.
+    pub collisions: CollisionStats,
+}
+
+// Plain copy of what collision prediction reads from a carrier, it can be sent to other threads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Kinematics {
+    pub pos: Vector2<f64>,
+    pub vel: Vector2<f64>,
+    pub acc: Vector2<f64>,
+    pub species: usize,
+    pub mass: f64,
+    pub mass_tensor: Option<Matrix2<f64>>,
+    pub charge: f64,
+    pub radius: f64,
 }
 
 impl Electron {
     pub fn new(pos: Vector2<f64>, vel: Vector2<f64>, acc: Vector2<f64>) -> Electron {
+        let mut electron = Electron::of_species(0, &Species::electron(), pos, vel);
+        electron.acc = acc;
+        electron
+    }
+
+    pub fn of_species(
+        species_id: usize,
+        species: &Species,
+        pos: Vector2<f64>,
+        vel: Vector2<f64>,
+    ) -> Electron {
+        let dirac = species.fermi_velocity.map(|fermi_velocity| DiracState {
+            fermi_velocity,
+            momentum: vel * species.mass,
+            force: Vector2::new(0.0, 0.0),
+        });
         Electron {
             pos,
-            vel,
-            acc,
+            vel: dirac.map_or(vel, |state| state.velocity()),
+            acc: Vector2::new(0.0, 0.0),
+            species: species_id,
+            mass: species.mass,
+            mass_tensor: species.mass_tensor,
+            charge: species.charge,
+            radius: species.radius,
+            born_at: 0.0,
+            time_to_bounce: 0.0,
+            collidable: Collidables::empty(),
+            transit: None,
+            dirac,
+            langevin_vel: None,
+            collisions: CollisionStats::default(),
+        }
+    }
+
+    pub fn kinematics(&self) -> Kinematics {
+        Kinematics {
+            pos: self.pos,
+            vel: self.vel,
+            acc: self.acc,
+            species: self.species,
+            mass: self.mass,
+            mass_tensor: self.mass_tensor,
+            charge: self.charge,
+            radius: self.radius,
+        }
+    }
+
+    // Carrier with the given motion and no transit, Dirac state or statistics.
+    pub fn from_kinematics(kinematics: &Kinematics) -> Electron {
+        Electron {
+            pos: kinematics.pos,
+            vel: kinematics.vel,
+            acc: kinematics.acc,
+            species: kinematics.species,
+            mass: kinematics.mass,
+            mass_tensor: kinematics.mass_tensor,
+            charge: kinematics.charge,
+            radius: kinematics.radius,
+            born_at: 0.0,
             time_to_bounce: 0.0,
             collidable: Collidables::empty(),
-            ticks_since_bounce: 0.0,
-            avg_ticks_between_bounces: 0.0,
-            bounce_count: 0,
+            transit: None,
+            dirac: None,
+            langevin_vel: None,
+            collisions: CollisionStats::default(),
+        }
+    }
+
+    // Sets the acceleration M^-1 * q * E for a field pointing along x,
+    // Dirac carriers get the force q * E on their momentum instead.
+    pub fn set_field(&mut self, field: f64) {
+        if let Some(state) = &mut self.dirac {
+            state.force = Vector2::new(self.charge * field, 0.0);
+            return;
         }
+        self.acc = match self.mass_tensor {
+            Some(_) => self.inverse_mass() * Vector2::new(self.charge * field, 0.0),
+            None => Vector2::new(self.charge * field / self.mass, 0.0),
+        };
     }
 
+    pub fn inverse_mass(&self) -> Matrix2<f64> {
+        match self.mass_tensor {
+            Some(tensor) => tensor.try_inverse().unwrap(),
+            None => Matrix2::identity() / self.mass,
+        }
+    }
+
+    // 1/2 v^T M v, which reduces to 1/2 m v^2 for a scalar mass, and v_F * |p| for Dirac carriers.
+    pub fn kinetic_energy(&self) -> f64 {
+        if let Some(state) = self.dirac {
+            return state.energy();
+        }
+        match self.mass_tensor {
+            Some(tensor) => 0.5 * self.vel.dot(&(tensor * self.vel)),
+            None => 0.5 * self.mass * self.vel.magnitude_squared(),
+        }
+    }
+
+    // Crystal momentum M * v, or the momentum of a Dirac carrier.
+    pub fn momentum(&self) -> Vector2<f64> {
+        match (self.dirac, self.mass_tensor) {
+            (Some(state), _) => state.momentum,
+            (None, Some(tensor)) => tensor * self.vel,
+            (None, None) => self.vel * self.mass,
+        }
+    }
+
+    // Rescales the speed to the given kinetic energy, keeping the direction.
+    pub fn set_kinetic_energy(&mut self, energy: f64) {
+        self.align_momentum();
+        if let Some(state) = &mut self.dirac {
+            let current = state.energy();
+            if current > 0.0 {
+                state.momentum *= energy.max(0.0) / current;
+            }
+            self.vel = state.velocity();
+            return;
+        }
+        let current = self.kinetic_energy();
+        if current > 0.0 {
+            self.vel *= (energy.max(0.0) / current).sqrt();
+        }
+    }
+
+    // Points the momentum of a Dirac carrier along the velocity a collision left it with.
+    // The magnitude of the momentum, and so the energy, is kept and the speed reset to v_F.
+    pub fn align_momentum(&mut self) {
+        if let Some(state) = &mut self.dirac {
+            state.align(self.vel);
+            self.vel = state.velocity();
+        }
+    }
+
+    // Position after `time` without further collisions, the drag is ignored as in the predictions.
+    pub fn position_at(&self, time: f64) -> Vector2<f64> {
+        match self.dirac {
+            Some(state) => self.pos + state.displacement(time).0,
+            None => self.pos + self.vel * time + self.acc * time.powi(2) / 2.0,
+        }
+    }
+
+    // Upper bound on the speed within the next `time`.
+    pub fn max_speed(&self, time: f64) -> f64 {
+        match self.dirac {
+            Some(state) => state.fermi_velocity,
+            None => self.vel.magnitude() + self.acc.magnitude() * time,
+        }
+    }
+
+    // Inside an impurity cutoff the Coulomb orbit is integrated instead, without the drag.
+    // Dirac carriers follow their closed-form path, also without the drag.
     pub fn update(&mut self, time: f64, supp: f64) {
+        if let Some(state) = &mut self.dirac {
+            let (displacement, momentum) = state.displacement(time);
+            state.momentum = momentum;
+            self.pos += displacement;
+            self.vel = state.velocity();
+            self.time_to_bounce -= time;
+            self.collisions.advance(time);
+            return;
+        }
+        if let Some(transit) = self.transit {
+            let (pos, vel) = propagate(
+                self.pos,
+                self.vel,
+                transit.centre,
+                transit.strength,
+                self.acc,
+                time,
+            );
+            self.pos = pos;
+            self.vel = vel;
+            self.time_to_bounce -= time;
+            self.collisions.advance(time);
+            return;
+        }
+        let supp = if self.langevin_vel.is_some() {
+            0.0
+        } else {
+            supp
+        };
         let acc = self.acc - self.vel * supp;
         let vel = self.vel;
         self.vel += acc * time;
         self.pos += vel * time + acc * time.powi(2) / 2.0;
         self.time_to_bounce -= time;
-        self.ticks_since_bounce += time;
+        self.collisions.advance(time);
     }
 
-    pub fn update_stats(&mut self) {
-        let bounces_time = self.avg_ticks_between_bounces * self.bounce_count as f64;
-        self.bounce_count += 1;
-        self.avg_ticks_between_bounces =
-            (bounces_time + self.ticks_since_bounce) / self.bounce_count as f64;
-        self.ticks_since_bounce = 0.0;
-    }
-}
-
-impl Collidable for Electron {
-    fn calc_time_to_collision(&self, other: &Electron) -> f64 {
+    // Time until the centres of the two carriers are `distance` apart.
+    pub fn calc_time_to_contact(&self, other: &Electron, distance: f64) -> f64 {
         calc_time_to_collision(
-            self.pos,
-            self.vel,
-            self.acc,
-            other.pos,
-            other.vel,
-            other.acc,
-            ELEC_ELEC_RADIUS,
+            self.pos, self.vel, self.acc, other.pos, other.vel, other.acc, distance,
         )
     }
 
-    fn bounce(&mut self, other: &mut Electron) {
-        let dist_vec = other.pos - self.pos;
-        if dist_vec.x == 0.0 {
-            std::mem::swap(&mut self.vel.y, &mut other.vel.y);
-            return;
+    // Puts the carrier on the exit point of its transit.
+    pub fn finish_transit(&mut self) {
+        if let Some(transit) = self.transit.take() {
+            self.pos = transit.exit_pos;
+            self.vel = transit.exit_vel;
         }
+    }
 
-        let k = dist_vec.y / dist_vec.x;
-
-        let v1_x = self.vel.x - other.vel.x;
-        let v1_y = self.vel.y - other.vel.y;
-
-        let v2_x = k * (k * v1_x - v1_y) / (k.powi(2) + 1.0);
-        let v2_y = (v1_y - k * v1_x) / (k.powi(2) + 1.0);
+    // Counts a scattering event with the given partner in the overall and per-partner statistics.
+    pub fn record_collision(&mut self, kind: CollisionKind) {
+        self.collisions.record(kind);
+    }
+}
 
-        let reflected1 = Vector2::new(v2_x, v2_y) + other.vel;
-        let reflected2 = self.vel + other.vel - reflected1;
+impl Collidable for Electron {
+    fn calc_time_to_collision(&self, other: &Electron) -> f64 {
+        self.calc_time_to_contact(other, self.radius + other.radius)
+    }
 
-        self.vel = reflected1;
-        other.vel = reflected2;
+    fn bounce(&mut self, other: &mut Electron) {
+        let normal = (other.pos - self.pos).normalize();
+        let (vel, other_vel) = if self.mass_tensor.is_some() || other.mass_tensor.is_some() {
+            tensor_collision(
+                self.vel,
+                self.inverse_mass(),
+                other.vel,
+                other.inverse_mass(),
+                normal,
+            )
+        } else {
+            elastic_collision(self.vel, self.mass, other.vel, other.mass, normal)
+        };
+        self.vel = vel;
+        other.vel = other_vel;
     }
 }
 
@@ -100,13 +297,13 @@ impl Ord for Electron {
         if self.pos.x < other.pos.x {
             std::cmp::Ordering::Less
         } else if self.pos.x > other.pos.x {
-            return std::cmp::Ordering::Greater;
+            std::cmp::Ordering::Greater
         } else if self.pos.y < other.pos.y {
-            return std::cmp::Ordering::Less;
+            std::cmp::Ordering::Less
         } else if self.pos.y > other.pos.y {
-            return std::cmp::Ordering::Greater;
+            std::cmp::Ordering::Greater
         } else {
-            return std::cmp::Ordering::Equal;
+            std::cmp::Ordering::Equal
         }
     }
 }
@@ -196,4 +393,75 @@ mod tests {
         assert_eq!(e1.vel, Vector2::new(0.0, 1.0));
         assert_eq!(e2.vel, Vector2::new(0.0, 2.0));
     }
+
+    #[test]
+    fn bounce_heavy_partner() {
+        let heavy = Species::new("heavy", 3.0, -1.0, 3.0);
+        let mut e1 =
+            Electron::of_species(1, &heavy, Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0));
+        let mut e2 = Electron::new(
+            Vector2::new(-6.0, 0.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+
+        e1.bounce(&mut e2);
+
+        assert_eq!(e1.vel, Vector2::new(1.0, 0.0));
+        assert_eq!(e2.vel, Vector2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn bounce_oblique_conserves_energy_and_momentum() {
+        let heavy = Species::new("heavy", 5.0, 1.0, 3.0);
+        let mut e1 =
+            Electron::of_species(1, &heavy, Vector2::new(0.0, 0.0), Vector2::new(0.5, -0.25));
+        let mut e2 = Electron::new(
+            Vector2::new(3.0, 5.196),
+            Vector2::new(-1.0, -2.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let momentum = e1.vel * 5.0 + e2.vel;
+        let energy = 5.0 * e1.vel.norm_squared() / 2.0 + e2.vel.norm_squared() / 2.0;
+
+        e1.bounce(&mut e2);
+
+        let momentum_after = e1.vel * 5.0 + e2.vel;
+        let energy_after = 5.0 * e1.vel.norm_squared() / 2.0 + e2.vel.norm_squared() / 2.0;
+        assert!((momentum_after - momentum).norm() < 1e-12);
+        assert!((energy_after - energy).abs() < 1e-12);
+    }
+
+    #[test]
+    fn set_field_scales_with_charge_to_mass() {
+        let mut electron = Electron::of_species(
+            1,
+            &Species::new("hole", 2.0, 1.0, 3.0),
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        electron.set_field(-0.5);
+        assert_eq!(electron.acc, Vector2::new(-0.25, 0.0));
+    }
+
+    #[test]
+    fn anisotropic_bounce_conserves_tensor_energy() {
+        let tensor = Matrix2::new(1.0, 0.5, 0.5, 3.0);
+        let species = Species::anisotropic("tilted", tensor, -1.0, 3.0).unwrap();
+        let mut e1 =
+            Electron::of_species(1, &species, Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.5));
+        let mut e2 = Electron::new(
+            Vector2::new(3.0, 5.196),
+            Vector2::new(-1.0, -2.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let momentum = tensor * e1.vel + e2.vel;
+        let energy = e1.kinetic_energy() + e2.kinetic_energy();
+
+        e1.bounce(&mut e2);
+
+        let momentum_after = tensor * e1.vel + e2.vel;
+        assert!((momentum_after - momentum).norm() < 1e-12);
+        assert!((e1.kinetic_energy() + e2.kinetic_energy() - energy).abs() < 1e-12);
+    }
 }
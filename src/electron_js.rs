@@ -6,7 +6,20 @@ use crate::electron::Electron;
 pub struct ElectronJs {
     pub x: f64,
     pub y: f64,
+    pub species: usize,
+    pub charge: f64,
+    pub radius: f64,
+    // Mean free time from the collision counters below.
     pub avg_ticks_between_bounces: f64,
+    // Collisions by partner and the mean time between them, periodic crossings excluded.
+    pub ion_collisions: i32,
+    pub ion_free_time: f64,
+    pub electron_collisions: i32,
+    pub electron_free_time: f64,
+    pub wall_collisions: i32,
+    pub wall_free_time: f64,
+    pub impurity_collisions: i32,
+    pub impurity_free_time: f64,
 }
 
 impl ElectronJs {
@@ -14,7 +27,18 @@ impl ElectronJs {
         ElectronJs {
             x: electron.pos.x,
             y: electron.pos.y,
-            avg_ticks_between_bounces: electron.avg_ticks_between_bounces,
+            species: electron.species,
+            charge: electron.charge,
+            radius: electron.radius,
+            avg_ticks_between_bounces: electron.collisions.mean_free_time(),
+            ion_collisions: electron.collisions.ion.count,
+            ion_free_time: electron.collisions.ion.mean,
+            electron_collisions: electron.collisions.electron.count,
+            electron_free_time: electron.collisions.electron.mean,
+            wall_collisions: electron.collisions.wall.count,
+            wall_free_time: electron.collisions.wall.mean,
+            impurity_collisions: electron.collisions.impurity.count,
+            impurity_free_time: electron.collisions.impurity.mean,
         }
     }
 }
@@ -0,0 +1,122 @@
+// Thermal generation of electron-hole pairs and their recombination on contact.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GenerationRecombination {
+    // Mean number of pairs created per tick, as a Poisson process.
+    pub generation_rate: f64,
+    // Chance that an electron-hole contact annihilates the pair instead of bouncing.
+    pub recombination_probability: f64,
+    pub electron_species: usize,
+    pub hole_species: usize,
+    // Speed of newly generated carriers.
+    pub init_velocity: f64,
+}
+
+impl GenerationRecombination {
+    pub fn new(
+        generation_rate: f64,
+        recombination_probability: f64,
+        electron_species: usize,
+        hole_species: usize,
+        init_velocity: f64,
+    ) -> Self {
+        GenerationRecombination {
+            generation_rate,
+            recombination_probability,
+            electron_species,
+            hole_species,
+            init_velocity,
+        }
+    }
+}
+
+// Creation of an electron-hole pair when a hot carrier hits an ion.
+// The carrier must carry at least `threshold` kinetic energy, the pair costs `gap`
+// and whatever is left is shared equally between the three carriers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImpactIonisation {
+    pub threshold: f64,
+    pub gap: f64,
+    pub probability: f64,
+    pub electron_species: usize,
+    pub hole_species: usize,
+}
+
+impl ImpactIonisation {
+    pub fn new(
+        threshold: f64,
+        gap: f64,
+        probability: f64,
+        electron_species: usize,
+        hole_species: usize,
+    ) -> Self {
+        ImpactIonisation {
+            threshold: threshold.max(gap),
+            gap,
+            probability,
+            electron_species,
+            hole_species,
+        }
+    }
+
+    // Kinetic energy left to each of the three carriers, `None` below threshold.
+    pub fn shared_energy(&self, energy: f64) -> Option<f64> {
+        if energy < self.threshold {
+            return None;
+        }
+        Some((energy - self.gap) / 3.0)
+    }
+}
+
+// Counters of carrier creation and removal events.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CarrierStats {
+    pub generated: i32,
+    pub recombined: i32,
+    pub captured: i32,
+    pub released: i32,
+    pub impact_ionisations: i32,
+    lifetime_sum: f64,
+    lifetime_count: i32,
+}
+
+impl CarrierStats {
+    pub fn record_lifetime(&mut self, lifetime: f64) {
+        self.lifetime_sum += lifetime;
+        self.lifetime_count += 1;
+    }
+
+    // Mean age at which carriers recombined.
+    pub fn mean_lifetime(&self) -> f64 {
+        if self.lifetime_count == 0 {
+            return 0.0;
+        }
+        self.lifetime_sum / self.lifetime_count as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mean_lifetime() {
+        let mut stats = CarrierStats::default();
+        assert_eq!(stats.mean_lifetime(), 0.0);
+        stats.record_lifetime(2.0);
+        stats.record_lifetime(4.0);
+        assert_eq!(stats.mean_lifetime(), 3.0);
+    }
+
+    #[test]
+    fn impact_ionisation_threshold() {
+        let ionisation = ImpactIonisation::new(4.0, 3.0, 1.0, 0, 1);
+        assert_eq!(ionisation.shared_energy(3.5), None);
+        assert_eq!(ionisation.shared_energy(6.0), Some(1.0));
+    }
+
+    #[test]
+    fn threshold_not_below_gap() {
+        let ionisation = ImpactIonisation::new(1.0, 3.0, 1.0, 0, 1);
+        assert_eq!(ionisation.threshold, 3.0);
+    }
+}
@@ -0,0 +1,308 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+use crate::electron::Electron;
+use crate::utils::calc_time_to_collision;
+
+// Fraction of the shortest local time scale covered by one integration step.
+const STEP_FRACTION: f64 = 0.01;
+// Upper bound on integration steps for one transit, guards against orbits that never leave.
+const MAX_STEPS: usize = 1_000_000;
+
+// A charged impurity that deflects carriers with a Coulomb force inside `cutoff`.
+// The potential energy of a carrier with charge q at distance r is q * charge / r
+// (Coulomb constant 1 in simulation units), outside the cutoff the impurity is invisible.
+// The cutoff circle is expected to lie inside the box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Impurity {
+    pub pos: Vector2<f64>,
+    pub charge: f64,
+    pub cutoff: f64,
+}
+
+// Passage of a carrier through the cutoff circle of an impurity.
+// The exit state is known when the carrier enters, the orbit in between is only integrated for display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transit {
+    pub centre: Vector2<f64>,
+    // q * Q / m, positive for repulsion.
+    pub strength: f64,
+    pub entry_vel: Vector2<f64>,
+    pub ends_at: f64,
+    pub exit_pos: Vector2<f64>,
+    pub exit_vel: Vector2<f64>,
+}
+
+impl Transit {
+    // Angle between the incoming and outgoing velocity.
+    pub fn deflection(&self) -> f64 {
+        let cos = self.entry_vel.dot(&self.exit_vel)
+            / (self.entry_vel.magnitude() * self.exit_vel.magnitude());
+        cos.clamp(-1.0, 1.0).acos()
+    }
+}
+
+impl Impurity {
+    pub fn new(pos: Vector2<f64>, charge: f64, cutoff: f64) -> Impurity {
+        Impurity {
+            pos,
+            charge,
+            cutoff,
+        }
+    }
+
+    // Time until the carrier reaches the cutoff circle, neutral carriers never interact.
+    pub fn calc_time_to_collision(&self, other: &Electron) -> f64 {
+        if self.strength(other) == 0.0 {
+            return f64::INFINITY;
+        }
+        calc_time_to_collision(
+            other.pos,
+            other.vel,
+            other.acc,
+            self.pos,
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            self.cutoff,
+        )
+    }
+
+    pub fn strength(&self, electron: &Electron) -> f64 {
+        electron.charge * self.charge / electron.mass
+    }
+
+    // Orbit of a carrier that just reached the cutoff circle, `None` when it is on its way out.
+    // Without a field the orbit is a conic section and solved in closed form,
+    // otherwise it is integrated numerically until the carrier leaves the cutoff.
+    pub fn transit(&self, electron: &Electron, now: f64) -> Option<Transit> {
+        let rel_pos = electron.pos - self.pos;
+        if rel_pos.dot(&electron.vel) >= 0.0 {
+            return None;
+        }
+        let strength = self.strength(electron);
+        let analytic = if electron.acc == Vector2::new(0.0, 0.0) {
+            kepler_transit(rel_pos, electron.vel, strength)
+        } else {
+            None
+        };
+        let (duration, exit_pos, exit_vel) = analytic.unwrap_or_else(|| {
+            numeric_transit(rel_pos, electron.vel, strength, electron.acc, self.cutoff)
+        });
+
+        Some(Transit {
+            centre: self.pos,
+            strength,
+            entry_vel: electron.vel,
+            ends_at: now + duration,
+            exit_pos: self.pos + exit_pos,
+            exit_vel,
+        })
+    }
+}
+
+// Distance of closest approach of the undeflected straight line.
+pub fn impact_parameter(rel_pos: Vector2<f64>, vel: Vector2<f64>) -> f64 {
+    (rel_pos.x * vel.y - rel_pos.y * vel.x).abs() / vel.magnitude()
+}
+
+// Rutherford deflection angle of an unscreened Coulomb centre.
+pub fn rutherford_deflection(strength: f64, speed: f64, impact_parameter: f64) -> f64 {
+    2.0 * (strength.abs() / (speed.powi(2) * impact_parameter)).atan()
+}
+
+// Closed-form passage through the cutoff at zero field, relative to the impurity.
+// The orbit is symmetric about the apsis line along the Runge-Lenz vector, so the exit state is
+// the mirrored and reversed entry state. The time from periapsis follows the Landau-Lifshitz
+// parametrisation of the hyperbola, or Kepler's equation for bound orbits.
+fn kepler_transit(
+    rel_pos: Vector2<f64>,
+    vel: Vector2<f64>,
+    strength: f64,
+) -> Option<(f64, Vector2<f64>, Vector2<f64>)> {
+    if strength == 0.0 {
+        return None;
+    }
+    let r = rel_pos.magnitude();
+    let h = rel_pos.x * vel.y - rel_pos.y * vel.x;
+    let energy = vel.magnitude_squared() / 2.0 + strength / r;
+    let k = strength.abs();
+    let a = k / (2.0 * energy.abs());
+    let e = (1.0 + 2.0 * energy * h.powi(2) / strength.powi(2))
+        .max(0.0)
+        .sqrt();
+    if !a.is_finite() || e == 0.0 {
+        return None;
+    }
+    let time_scale = (a.powi(3) / k).sqrt();
+
+    let half_time = if energy > 0.0 {
+        let shift = if strength > 0.0 { -1.0 } else { 1.0 };
+        let xi = ((r / a + shift) / e).max(1.0).acosh();
+        time_scale * (e * xi.sinh() - shift * xi)
+    } else {
+        let xi = ((1.0 - r / a) / e).clamp(-1.0, 1.0).acos();
+        time_scale * (xi - e * xi.sin())
+    };
+
+    let axis = (Vector2::new(vel.y * h, -vel.x * h) + rel_pos / r * strength).normalize();
+    let mirror = |v: Vector2<f64>| axis * 2.0 * v.dot(&axis) - v;
+    Some((2.0 * half_time, mirror(rel_pos), -mirror(vel)))
+}
+
+// Integrates the orbit until the carrier is back on the cutoff circle.
+// The crossing inside the last step is located by bisection.
+fn numeric_transit(
+    rel_pos: Vector2<f64>,
+    vel: Vector2<f64>,
+    strength: f64,
+    acc: Vector2<f64>,
+    cutoff: f64,
+) -> (f64, Vector2<f64>, Vector2<f64>) {
+    let origin = Vector2::new(0.0, 0.0);
+    let (mut pos, mut vel, mut time) = (rel_pos, vel, 0.0);
+    for _ in 0..MAX_STEPS {
+        let step = step_size(pos, vel, origin, strength);
+        let (next_pos, next_vel) = rk4_step(pos, vel, origin, strength, acc, step);
+        if next_pos.magnitude() >= cutoff && next_pos.dot(&next_vel) > 0.0 {
+            let (mut low, mut high) = (0.0, step);
+            for _ in 0..50 {
+                let mid = (low + high) / 2.0;
+                let (mid_pos, _) = rk4_step(pos, vel, origin, strength, acc, mid);
+                if mid_pos.magnitude() >= cutoff {
+                    high = mid;
+                } else {
+                    low = mid;
+                }
+            }
+            let (exit_pos, exit_vel) = rk4_step(pos, vel, origin, strength, acc, high);
+            return (time + high, exit_pos, exit_vel);
+        }
+        pos = next_pos;
+        vel = next_vel;
+        time += step;
+    }
+    (time, pos, vel)
+}
+
+// Advances a carrier in the field of the impurity at `centre` and the uniform acceleration `acc`.
+pub fn propagate(
+    mut pos: Vector2<f64>,
+    mut vel: Vector2<f64>,
+    centre: Vector2<f64>,
+    strength: f64,
+    acc: Vector2<f64>,
+    time: f64,
+) -> (Vector2<f64>, Vector2<f64>) {
+    let mut remaining = time;
+    while remaining > 0.0 {
+        let step = step_size(pos, vel, centre, strength).min(remaining);
+        let (next_pos, next_vel) = rk4_step(pos, vel, centre, strength, acc, step);
+        pos = next_pos;
+        vel = next_vel;
+        remaining -= step;
+    }
+    (pos, vel)
+}
+
+// Step resolving both the crossing time r / v and the free-fall time of the Coulomb force.
+fn step_size(pos: Vector2<f64>, vel: Vector2<f64>, centre: Vector2<f64>, strength: f64) -> f64 {
+    let r = (pos - centre).magnitude();
+    let crossing = r / vel.magnitude();
+    let fall = (r.powi(3) / strength.abs()).sqrt();
+    STEP_FRACTION * crossing.min(fall)
+}
+
+fn rk4_step(
+    pos: Vector2<f64>,
+    vel: Vector2<f64>,
+    centre: Vector2<f64>,
+    strength: f64,
+    acc: Vector2<f64>,
+    dt: f64,
+) -> (Vector2<f64>, Vector2<f64>) {
+    let force = |p: Vector2<f64>| {
+        let d = p - centre;
+        d * strength / d.magnitude().powi(3) + acc
+    };
+    let k1_v = force(pos);
+    let k1_x = vel;
+    let k2_v = force(pos + k1_x * (dt / 2.0));
+    let k2_x = vel + k1_v * (dt / 2.0);
+    let k3_v = force(pos + k2_x * (dt / 2.0));
+    let k3_x = vel + k2_v * (dt / 2.0);
+    let k4_v = force(pos + k3_x * dt);
+    let k4_x = vel + k3_v * dt;
+    (
+        pos + (k1_x + k2_x * 2.0 + k3_x * 2.0 + k4_x) * (dt / 6.0),
+        vel + (k1_v + k2_v * 2.0 + k3_v * 2.0 + k4_v) * (dt / 6.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entering(b: f64, speed: f64) -> Electron {
+        let x = -(400.0f64 - b.powi(2)).sqrt();
+        Electron::new(
+            Vector2::new(100.0 + x, 100.0 + b),
+            Vector2::new(speed, 0.0),
+            Vector2::new(0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn analytic_matches_numeric() {
+        for &charge in [1.0, -1.0, -20.0].iter() {
+            let impurity = Impurity::new(Vector2::new(100.0, 100.0), charge, 20.0);
+            let electron = entering(5.0, 1.0);
+            let rel_pos = electron.pos - impurity.pos;
+            let strength = impurity.strength(&electron);
+            let (time, pos, vel) = kepler_transit(rel_pos, electron.vel, strength).unwrap();
+            let (time_n, pos_n, vel_n) = numeric_transit(
+                rel_pos,
+                electron.vel,
+                strength,
+                Vector2::new(0.0, 0.0),
+                20.0,
+            );
+
+            assert!((time - time_n).abs() < 1e-4 * time);
+            assert!((pos - pos_n).magnitude() < 1e-4);
+            assert!((vel - vel_n).magnitude() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn repulsive_head_on_turns_back() {
+        let impurity = Impurity::new(Vector2::new(100.0, 100.0), -2.0, 20.0);
+        let electron = entering(0.0, 1.0);
+        let transit = impurity.transit(&electron, 0.0).unwrap();
+
+        assert!((transit.exit_pos - electron.pos).magnitude() < 1e-9);
+        assert!((transit.exit_vel + electron.vel).magnitude() < 1e-9);
+        assert!((transit.deflection() - std::f64::consts::PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn weak_deflection_follows_rutherford() {
+        let impurity = Impurity::new(Vector2::new(100.0, 100.0), -0.01, 1000.0);
+        let electron = Electron::new(
+            Vector2::new(100.0 - 999.0, 105.0),
+            Vector2::new(1.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let transit = impurity.transit(&electron, 0.0).unwrap();
+        let expected = rutherford_deflection(impurity.strength(&electron), 1.0, 5.0);
+
+        assert!((transit.deflection() - expected).abs() < 1e-2 * expected);
+    }
+
+    #[test]
+    fn leaving_carrier_is_ignored() {
+        let impurity = Impurity::new(Vector2::new(100.0, 100.0), 1.0, 20.0);
+        let mut electron = entering(5.0, 1.0);
+        electron.vel = -electron.vel;
+        assert_eq!(impurity.transit(&electron, 0.0), None);
+    }
+}
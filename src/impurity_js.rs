@@ -0,0 +1,22 @@
+use wasm_bindgen::prelude::*;
+
+use crate::impurity::Impurity;
+
+#[wasm_bindgen(js_name = Impurity)]
+pub struct ImpurityJs {
+    pub x: f64,
+    pub y: f64,
+    pub charge: f64,
+    pub cutoff: f64,
+}
+
+impl ImpurityJs {
+    pub fn new(impurity: &Impurity) -> ImpurityJs {
+        ImpurityJs {
+            x: impurity.pos.x,
+            y: impurity.pos.y,
+            charge: impurity.charge,
+            cutoff: impurity.cutoff,
+        }
+    }
+}
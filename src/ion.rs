@@ -1,36 +1,190 @@
 extern crate nalgebra as na;
-use crate::cfg::ION_ELEC_RADIUS;
+use crate::cfg::ION_RADIUS;
 use crate::collidable::Collidable;
+use crate::dirac::{first_contact, sample_step};
 use crate::electron::Electron;
-use crate::utils::calc_time_to_collision;
-use na::Vector2;
+use crate::scattering::ScatteringLaw;
+use crate::utils::{calc_time_to_collision, elastic_collision, tensor_collision};
+use na::{Matrix2, Vector2};
+
+// Contacts with mobile ions are looked for one tick ahead, the engine re-predicts every tick
+// while ions are mobile.
+pub const ORBIT_HORIZON: f64 = 1.0;
 
 #[derive(Clone, Copy)]
 pub struct Ion {
     pub pos: Vector2<f64>,
+    pub site: Vector2<f64>,
+    pub vel: Vector2<f64>,
+    pub acc: Vector2<f64>,
+    pub radius: f64,
+    pub species: u32,
+    // Infinite mass keeps the ion pinned, a finite one lets it recoil around its site.
+    pub mass: f64,
+    pub stiffness: f64,
 }
 
 impl Ion {
     pub fn new(pos: Vector2<f64>) -> Ion {
-        Ion { pos }
+        Ion::of_species(pos, ION_RADIUS, 0)
+    }
+
+    pub fn of_species(pos: Vector2<f64>, radius: f64, species: u32) -> Ion {
+        Ion {
+            pos,
+            site: pos,
+            vel: Vector2::new(0.0, 0.0),
+            acc: Vector2::new(0.0, 0.0),
+            radius,
+            species,
+            mass: f64::INFINITY,
+            stiffness: 0.0,
+        }
+    }
+
+    pub fn is_mobile(&self) -> bool {
+        self.mass.is_finite()
+    }
+
+    // Tethers the ion to its site with a harmonic spring of the given stiffness.
+    pub fn set_mobile(&mut self, mass: f64, stiffness: f64) {
+        self.mass = mass;
+        self.stiffness = stiffness;
+        self.vel = Vector2::new(0.0, 0.0);
+        self.update_acc();
+    }
+
+    // Advances the ion along its exact harmonic orbit around the site.
+    pub fn update(&mut self, time: f64) {
+        if !self.is_mobile() {
+            return;
+        }
+        let (pos, vel) = self.orbit(time);
+        self.pos = pos;
+        self.vel = vel;
+        self.update_acc();
+    }
+
+    // Position and velocity after `time` without collisions, pinned ions stay put.
+    fn orbit(&self, time: f64) -> (Vector2<f64>, Vector2<f64>) {
+        if !self.is_mobile() {
+            return (self.pos, self.vel);
+        }
+        if self.stiffness > 0.0 {
+            let omega = (self.stiffness / self.mass).sqrt();
+            let (sin, cos) = (omega * time).sin_cos();
+            let displacement = self.pos - self.site;
+            (
+                self.site + displacement * cos + self.vel * (sin / omega),
+                self.vel * cos - displacement * (omega * sin),
+            )
+        } else {
+            (self.pos + self.vel * time, self.vel)
+        }
+    }
+
+    pub fn position_at(&self, time: f64) -> Vector2<f64> {
+        self.orbit(time).0
+    }
+
+    // Largest speed on the orbit, reached when the ion passes its site.
+    pub fn max_speed(&self) -> f64 {
+        if !self.is_mobile() {
+            return 0.0;
+        }
+        (2.0 * self.energy() / self.mass).sqrt()
+    }
+
+    // Whether the carrier can touch the ion within `horizon` with both at their largest speed.
+    pub fn within_reach(&self, electron: &Electron, horizon: f64) -> bool {
+        let gap = (self.pos - electron.pos).magnitude() - self.radius - electron.radius;
+        gap <= (electron.max_speed(horizon) + self.max_speed()) * horizon
+    }
+
+    // A tethered orbit is not polynomial, so the first contact within `ORBIT_HORIZON` is
+    // bracketed on the exact gap between the carrier path and the orbit.
+    // Free ions move on straight lines and keep the exact polynomial prediction.
+    fn time_to_contact_on_orbit(&self, other: &Electron) -> f64 {
+        let closing_speed = other.max_speed(ORBIT_HORIZON) + self.max_speed();
+        let step = sample_step(other.radius.min(self.radius), closing_speed);
+        let gap = |t: f64| {
+            (other.position_at(t) - self.position_at(t)).magnitude() - self.radius - other.radius
+        };
+        first_contact(gap, step, ORBIT_HORIZON)
+    }
+
+    fn update_acc(&mut self) {
+        self.acc = if self.is_mobile() {
+            (self.site - self.pos) * self.stiffness / self.mass
+        } else {
+            Vector2::new(0.0, 0.0)
+        };
+    }
+
+    pub fn energy(&self) -> f64 {
+        if !self.is_mobile() {
+            return 0.0;
+        }
+        self.mass * self.vel.norm_squared() / 2.0
+            + self.stiffness * (self.pos - self.site).norm_squared() / 2.0
+    }
+
+    // Bounces a carrier off the ion according to `law` instead of specular reflection.
+    // Mobile ions apply the law to the relative velocity with the reduced mass and share
+    // the resulting momentum change, pinned ions leave the carrier velocity to the law alone.
+    pub fn scatter(&mut self, other: &mut Electron, law: &dyn ScatteringLaw) {
+        let normal = (other.pos - self.pos).normalize();
+        if !self.is_mobile() {
+            other.vel = law.scatter(other.vel, normal, other.mass);
+            return;
+        }
+        let total_mass = other.mass + self.mass;
+        let centre_vel = (other.vel * other.mass + self.vel * self.mass) / total_mass;
+        let reduced_mass = other.mass * self.mass / total_mass;
+        let rel_vel = law.scatter(other.vel - self.vel, normal, reduced_mass);
+        other.vel = centre_vel + rel_vel * (self.mass / total_mass);
+        self.vel = centre_vel - rel_vel * (other.mass / total_mass);
     }
 }
 
 impl Collidable for Ion {
     fn calc_time_to_collision(&self, other: &Electron) -> f64 {
+        if self.is_mobile() && self.stiffness > 0.0 {
+            return self.time_to_contact_on_orbit(other);
+        }
         calc_time_to_collision(
             other.pos,
             other.vel,
             other.acc,
             self.pos,
-            Vector2::new(0.0, 0.0),
-            Vector2::new(0.0, 0.0),
-            ION_ELEC_RADIUS,
+            self.vel,
+            self.acc,
+            self.radius + other.radius,
         )
     }
 
     fn bounce(&mut self, other: &mut Electron) {
         let normal = (other.pos - self.pos).normalize();
+        if other.mass_tensor.is_some() {
+            let inv_mass = if self.is_mobile() {
+                Matrix2::identity() / self.mass
+            } else {
+                Matrix2::zeros()
+            };
+            let (vel, ion_vel) =
+                tensor_collision(other.vel, other.inverse_mass(), self.vel, inv_mass, normal);
+            other.vel = vel;
+            self.vel = ion_vel;
+            return;
+        }
+        if self.is_mobile() {
+            let (vel, ion_vel) =
+                elastic_collision(other.vel, other.mass, self.vel, self.mass, normal);
+            other.vel = vel;
+            self.vel = ion_vel;
+            return;
+        }
+
         let incidence = other.vel.normalize() * -1.0;
         let dot = incidence.dot(&normal);
         let mut reflected = normal * 2.0 * dot - incidence;
@@ -80,6 +234,7 @@ impl PartialOrd for Ion {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scattering::{Restitution, Specular};
 
     #[test]
     fn calc_time_to_collision_left() {
@@ -140,4 +295,151 @@ mod tests {
         let time = ion.calc_time_to_collision(&electron);
         assert_eq!(time, 0.449_489_742_783_177_9);
     }
+
+    #[test]
+    fn calc_time_to_collision_own_radius() {
+        let ion = Ion::of_species(Vector2::new(100.0, 100.0), 5.0, 1);
+        let electron = Electron::new(
+            Vector2::new(86.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let time = ion.calc_time_to_collision(&electron);
+        assert_eq!(time, 3.0);
+    }
+
+    #[test]
+    fn calc_time_to_collision_moving_ion() {
+        let mut ion = Ion::new(Vector2::new(100.0, 100.0));
+        ion.set_mobile(10.0, 0.0);
+        ion.vel = Vector2::new(-2.0, 0.0);
+        let electron = Electron::new(
+            Vector2::new(86.0, 100.0),
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let time = ion.calc_time_to_collision(&electron);
+        assert_eq!(time, 0.5);
+    }
+
+    #[test]
+    fn bounce_recoil_equal_mass() {
+        let mut ion = Ion::new(Vector2::new(100.0, 100.0));
+        ion.set_mobile(1.0, 0.0);
+        let mut electron = Electron::new(
+            Vector2::new(87.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        ion.bounce(&mut electron);
+
+        assert_eq!(electron.vel, Vector2::new(0.0, 0.0));
+        assert_eq!(ion.vel, Vector2::new(2.0, 0.0));
+    }
+
+    #[test]
+    fn bounce_recoil_conserves_energy_and_momentum() {
+        let mut ion = Ion::new(Vector2::new(100.0, 100.0));
+        ion.set_mobile(20.0, 0.0);
+        let mut electron = Electron::new(
+            Vector2::new(90.0, 92.0),
+            Vector2::new(2.0, 1.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let momentum = electron.vel;
+        let energy = electron.vel.norm_squared() / 2.0;
+        ion.bounce(&mut electron);
+
+        let momentum_after = electron.vel + ion.vel * 20.0;
+        let energy_after = electron.vel.norm_squared() / 2.0 + ion.energy();
+        assert!((momentum_after - momentum).norm() < 1e-12);
+        assert!((energy_after - energy).abs() < 1e-12);
+        assert!(ion.vel.x > 0.0);
+    }
+
+    #[test]
+    fn scatter_specular_matches_bounce() {
+        let mut ion = Ion::new(Vector2::new(100.0, 100.0));
+        ion.set_mobile(20.0, 0.0);
+        let mut bounced = Electron::new(
+            Vector2::new(90.0, 92.0),
+            Vector2::new(2.0, 1.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let mut scattered = bounced.clone();
+        let mut other = ion;
+        ion.bounce(&mut bounced);
+        other.scatter(&mut scattered, &Specular);
+
+        assert!((bounced.vel - scattered.vel).norm() < 1e-12);
+        assert!((ion.vel - other.vel).norm() < 1e-12);
+    }
+
+    #[test]
+    fn scatter_restitution_conserves_momentum() {
+        let mut ion = Ion::new(Vector2::new(100.0, 100.0));
+        ion.set_mobile(20.0, 0.0);
+        let mut electron = Electron::new(
+            Vector2::new(90.0, 92.0),
+            Vector2::new(2.0, 1.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let energy = electron.vel.norm_squared() / 2.0;
+        ion.scatter(&mut electron, &Restitution::new(0.5));
+
+        let momentum_after = electron.vel + ion.vel * 20.0;
+        let energy_after = electron.vel.norm_squared() / 2.0 + ion.energy();
+        assert!((momentum_after - Vector2::new(2.0, 1.0)).norm() < 1e-12);
+        assert!(energy_after < energy);
+    }
+
+    #[test]
+    fn update_oscillates_around_site() {
+        let mut ion = Ion::new(Vector2::new(100.0, 100.0));
+        ion.set_mobile(1.0, 0.01);
+        ion.vel = Vector2::new(1.0, 0.0);
+        let period = 2.0 * std::f64::consts::PI / 0.1;
+        let steps = 10_000;
+        for _ in 0..steps {
+            ion.update(period / steps as f64);
+        }
+        assert!((ion.pos - ion.site).norm() < 1e-9);
+        assert!((ion.vel.x - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mobile_contact_follows_the_orbit() {
+        let mut ion = Ion::new(Vector2::new(100.0, 100.0));
+        ion.set_mobile(1.0, 4.0);
+        ion.pos = Vector2::new(100.0, 108.0);
+        ion.update_acc();
+        let electron = Electron::new(
+            Vector2::new(80.0, 100.0),
+            Vector2::new(10.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+
+        assert!(ion.within_reach(&electron, ORBIT_HORIZON));
+        let time = ion.calc_time_to_collision(&electron);
+        assert!(time < ORBIT_HORIZON);
+
+        ion.update(time);
+        let gap = (electron.position_at(time) - ion.pos).magnitude() - ion.radius - electron.radius;
+        assert!(gap.abs() < 1e-9);
+    }
+
+    #[test]
+    fn mobile_ion_out_of_reach() {
+        let mut ion = Ion::new(Vector2::new(100.0, 100.0));
+        ion.set_mobile(1.0, 0.01);
+        ion.vel = Vector2::new(1.0, 0.0);
+        let electron = Electron::new(
+            Vector2::new(40.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+
+        assert!(!ion.within_reach(&electron, ORBIT_HORIZON));
+        assert_eq!(ion.calc_time_to_collision(&electron), f64::INFINITY);
+    }
 }
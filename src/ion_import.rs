@@ -0,0 +1,375 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use nalgebra::Vector2;
+use serde_json::Value;
+
+use crate::cfg::ION_RADIUS;
+
+// A single ion site read from an external configuration.
+// Radius and species are optional and fall back to the defaults when missing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IonRecord {
+    pub pos: Vector2<f64>,
+    pub radius: Option<f64>,
+    pub species: Option<u32>,
+}
+
+impl IonRecord {
+    pub fn new(pos: Vector2<f64>) -> IonRecord {
+        IonRecord {
+            pos,
+            radius: None,
+            species: None,
+        }
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius.unwrap_or(ION_RADIUS)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IonImportError {
+    Io(String),
+    Parse { line: usize, message: String },
+    Entry { entry: usize, message: String },
+    InvalidRadius { index: usize, radius: f64 },
+    OutOfBox { index: usize, pos: Vector2<f64> },
+    Overlap { first: usize, second: usize },
+}
+
+impl fmt::Display for IonImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IonImportError::Io(message) => write!(f, "cannot read ion file: {}", message),
+            IonImportError::Parse { line, message } => {
+                write!(f, "invalid ion entry at line {}: {}", line, message)
+            }
+            IonImportError::Entry { entry, message } => {
+                write!(f, "invalid ion entry {}: {}", entry, message)
+            }
+            IonImportError::InvalidRadius { index, radius } => {
+                write!(f, "ion {} has invalid radius {}", index, radius)
+            }
+            IonImportError::OutOfBox { index, pos } => write!(
+                f,
+                "ion {} at ({}, {}) does not fit inside the box",
+                index, pos.x, pos.y
+            ),
+            IonImportError::Overlap { first, second } => {
+                write!(f, "ions {} and {} overlap", first, second)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IonImportError {}
+
+// Parses comma separated ion sites, one per line: `x,y[,radius[,type]]`.
+// An optional header row may name the columns in any order.
+// Empty lines and lines starting with `#` are skipped.
+pub fn parse_csv(text: &str) -> Result<Vec<IonRecord>, IonImportError> {
+    let mut columns: [Option<usize>; 4] = [Some(0), Some(1), Some(2), Some(3)];
+    let mut records = Vec::new();
+    let mut first_row = true;
+
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+
+        if first_row {
+            first_row = false;
+            if fields[0].parse::<f64>().is_err() {
+                columns = parse_header(&fields, line_no)?;
+                continue;
+            }
+        }
+
+        let field = |column: Option<usize>| column.and_then(|c| fields.get(c).copied());
+        let number = |name: &str, value: &str| {
+            value.parse::<f64>().map_err(|_| IonImportError::Parse {
+                line: line_no,
+                message: format!("{} is not a number: '{}'", name, value),
+            })
+        };
+
+        let x = match field(columns[0]) {
+            Some(value) => number("x", value)?,
+            None => return Err(missing(line_no, "x")),
+        };
+        let y = match field(columns[1]) {
+            Some(value) => number("y", value)?,
+            None => return Err(missing(line_no, "y")),
+        };
+        let radius = match field(columns[2]) {
+            Some(value) if !value.is_empty() => Some(number("radius", value)?),
+            _ => None,
+        };
+        let species = match field(columns[3]) {
+            Some(value) if !value.is_empty() => {
+                Some(value.parse::<u32>().map_err(|_| IonImportError::Parse {
+                    line: line_no,
+                    message: format!("type is not a non-negative integer: '{}'", value),
+                })?)
+            }
+            _ => None,
+        };
+
+        records.push(IonRecord {
+            pos: Vector2::new(x, y),
+            radius,
+            species,
+        });
+    }
+    Ok(records)
+}
+
+fn parse_header(fields: &[&str], line: usize) -> Result<[Option<usize>; 4], IonImportError> {
+    let mut columns = [None; 4];
+    for (i, name) in fields.iter().enumerate() {
+        let slot = match name.to_lowercase().as_str() {
+            "x" => 0,
+            "y" => 1,
+            "r" | "radius" => 2,
+            "type" | "species" => 3,
+            _ => {
+                return Err(IonImportError::Parse {
+                    line,
+                    message: format!("unknown column '{}'", name),
+                })
+            }
+        };
+        columns[slot] = Some(i);
+    }
+    if columns[0].is_none() || columns[1].is_none() {
+        return Err(IonImportError::Parse {
+            line,
+            message: "header must contain x and y columns".to_string(),
+        });
+    }
+    Ok(columns)
+}
+
+fn missing(line: usize, name: &str) -> IonImportError {
+    IonImportError::Parse {
+        line,
+        message: format!("missing {} value", name),
+    }
+}
+
+fn missing_entry(entry: usize, name: &str) -> IonImportError {
+    IonImportError::Entry {
+        entry,
+        message: format!("missing {} value", name),
+    }
+}
+
+// Parses a JSON array of ion sites.
+// Each entry is either an object `{"x", "y", "radius"?, "type"?}` or an array `[x, y, radius?, type?]`.
+// Syntax errors report the line of the text, errors in an entry report its 1-based index in the array.
+pub fn parse_json(text: &str) -> Result<Vec<IonRecord>, IonImportError> {
+    let value: Value = serde_json::from_str(text).map_err(|err| IonImportError::Parse {
+        line: err.line(),
+        message: err.to_string(),
+    })?;
+    let entries = value.as_array().ok_or_else(|| IonImportError::Parse {
+        line: 1,
+        message: "expected an array of ions".to_string(),
+    })?;
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| parse_json_entry(entry, i + 1))
+        .collect()
+}
+
+fn parse_json_entry(value: &Value, entry: usize) -> Result<IonRecord, IonImportError> {
+    let (x, y, radius, species) = match value {
+        Value::Object(map) => (
+            map.get("x"),
+            map.get("y"),
+            map.get("radius").or_else(|| map.get("r")),
+            map.get("type").or_else(|| map.get("species")),
+        ),
+        Value::Array(items) => (items.first(), items.get(1), items.get(2), items.get(3)),
+        _ => {
+            return Err(IonImportError::Entry {
+                entry,
+                message: "expected an object or an array".to_string(),
+            })
+        }
+    };
+
+    let number = |name: &str, value: Option<&Value>| match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_f64()
+            .map(Some)
+            .ok_or_else(|| IonImportError::Entry {
+                entry,
+                message: format!("{} is not a number: {}", name, value),
+            }),
+    };
+
+    let x = number("x", x)?.ok_or_else(|| missing_entry(entry, "x"))?;
+    let y = number("y", y)?.ok_or_else(|| missing_entry(entry, "y"))?;
+    let radius = number("radius", radius)?;
+    let species = match species {
+        None | Some(Value::Null) => None,
+        Some(value) => Some(
+            value
+                .as_u64()
+                .and_then(|s| u32::try_from(s).ok())
+                .ok_or_else(|| IonImportError::Entry {
+                    entry,
+                    message: format!("type is not a non-negative integer: {}", value),
+                })?,
+        ),
+    };
+
+    Ok(IonRecord {
+        pos: Vector2::new(x, y),
+        radius,
+        species,
+    })
+}
+
+// Reads ion sites from a `.json` file, any other extension is read as CSV.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_file(path: &std::path::Path) -> Result<Vec<IonRecord>, IonImportError> {
+    let text = std::fs::read_to_string(path).map_err(|err| IonImportError::Io(err.to_string()))?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("json") => parse_json(&text),
+        _ => parse_csv(&text),
+    }
+}
+
+// Checks that every ion lies fully inside the box and that no two ions overlap.
+pub fn validate(records: &[IonRecord], x_size: f64, y_size: f64) -> Result<(), IonImportError> {
+    for (index, record) in records.iter().enumerate() {
+        let radius = record.radius();
+        if !radius.is_finite() || radius <= 0.0 {
+            return Err(IonImportError::InvalidRadius { index, radius });
+        }
+        let pos = record.pos;
+        if !pos.x.is_finite()
+            || !pos.y.is_finite()
+            || pos.x - radius < 0.0
+            || pos.y - radius < 0.0
+            || pos.x + radius > x_size
+            || pos.y + radius > y_size
+        {
+            return Err(IonImportError::OutOfBox { index, pos });
+        }
+    }
+
+    let mut order: Vec<usize> = (0..records.len()).collect();
+    order.sort_by(|&a, &b| records[a].pos.x.partial_cmp(&records[b].pos.x).unwrap());
+    let max_radius = records.iter().map(|r| r.radius()).fold(0.0, f64::max);
+
+    for (i, &first) in order.iter().enumerate() {
+        let a = &records[first];
+        for &second in order[i + 1..].iter() {
+            let b = &records[second];
+            if b.pos.x - a.pos.x >= a.radius() + max_radius {
+                break;
+            }
+            if (b.pos - a.pos).magnitude() < a.radius() + b.radius() {
+                return Err(IonImportError::Overlap {
+                    first: first.min(second),
+                    second: first.max(second),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_csv_plain() {
+        let records = parse_csv("10,20\n30.5, 40, 5\n\n# comment\n50,60,7,2\n").unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], IonRecord::new(Vector2::new(10.0, 20.0)));
+        assert_eq!(records[1].radius, Some(5.0));
+        assert_eq!(records[1].species, None);
+        assert_eq!(records[2].radius, Some(7.0));
+        assert_eq!(records[2].species, Some(2));
+    }
+
+    #[test]
+    fn parse_csv_header() {
+        let records = parse_csv("type,y,x\n1,20,10\n").unwrap();
+        assert_eq!(records[0].pos, Vector2::new(10.0, 20.0));
+        assert_eq!(records[0].species, Some(1));
+        assert_eq!(records[0].radius, None);
+    }
+
+    #[test]
+    fn parse_csv_invalid_number() {
+        let err = parse_csv("x,y\n10,20\n10,abc\n").unwrap_err();
+        assert!(matches!(err, IonImportError::Parse { line: 3, .. }));
+    }
+
+    #[test]
+    fn parse_json_objects_and_arrays() {
+        let records =
+            parse_json(r#"[{"x": 10, "y": 20, "radius": 4}, [30, 40], [50, 60, null, 3]]"#)
+                .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].radius, Some(4.0));
+        assert_eq!(records[1], IonRecord::new(Vector2::new(30.0, 40.0)));
+        assert_eq!(records[2].species, Some(3));
+    }
+
+    #[test]
+    fn parse_json_missing_coordinate() {
+        let err = parse_json(r#"[[10, 20], {"x": 10}]"#).unwrap_err();
+        assert!(matches!(err, IonImportError::Entry { entry: 2, .. }));
+    }
+
+    #[test]
+    fn parse_json_syntax_error_reports_line() {
+        let err = parse_json("[\n  [10, 20],\n  [30 40]\n]").unwrap_err();
+        assert!(matches!(err, IonImportError::Parse { line: 3, .. }));
+    }
+
+    #[test]
+    fn validate_out_of_box() {
+        let records = vec![
+            IonRecord::new(Vector2::new(50.0, 50.0)),
+            IonRecord::new(Vector2::new(795.0, 50.0)),
+        ];
+        let err = validate(&records, 800.0, 600.0).unwrap_err();
+        assert!(matches!(err, IonImportError::OutOfBox { index: 1, .. }));
+    }
+
+    #[test]
+    fn validate_overlap() {
+        let mut records = vec![
+            IonRecord::new(Vector2::new(50.0, 50.0)),
+            IonRecord::new(Vector2::new(100.0, 50.0)),
+            IonRecord::new(Vector2::new(65.0, 50.0)),
+        ];
+        assert_eq!(
+            validate(&records, 800.0, 600.0),
+            Err(IonImportError::Overlap {
+                first: 0,
+                second: 2
+            })
+        );
+
+        records[2].pos.y = 80.0;
+        records[2].radius = Some(5.0);
+        assert_eq!(validate(&records, 800.0, 600.0), Ok(()));
+    }
+}
@@ -6,6 +6,8 @@ use crate::ion::Ion;
 pub struct IonJs {
     pub x: f64,
     pub y: f64,
+    pub radius: f64,
+    pub species: u32,
 }
 
 impl IonJs {
@@ -13,6 +15,8 @@ impl IonJs {
         IonJs {
             x: ion.pos.x,
             y: ion.pos.y,
+            radius: ion.radius,
+            species: ion.species,
         }
     }
 }
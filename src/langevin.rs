@@ -0,0 +1,123 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+use crate::utils::random_normal;
+
+// Thermal noise matching the `supp` drag through the fluctuation-dissipation theorem.
+// Each velocity component follows the Ornstein-Uhlenbeck process
+// dv = (a - gamma * v) dt + sqrt(2 * gamma * T / m) dW with gamma = supp and k_B = 1.
+// Transitions are drawn exactly once every `step` ticks and after every collision.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Langevin {
+    pub temperature: f64,
+    pub step: f64,
+}
+
+impl Langevin {
+    pub fn new(temperature: f64, step: f64) -> Self {
+        Langevin { temperature, step }
+    }
+
+    // Exact position and velocity after `time` for drag `gamma`, uniform acceleration `acc` and `mass`.
+    // Position and velocity noise are correlated, per component
+    // var v = T / m * (1 - e^(-2 gamma t)),
+    // var x = T / (m gamma^2) * (2 gamma t - 3 + 4 e^(-gamma t) - e^(-2 gamma t)),
+    // cov = T / (m gamma) * (1 - e^(-gamma t))^2.
+    pub fn transition(
+        &self,
+        pos: Vector2<f64>,
+        vel: Vector2<f64>,
+        acc: Vector2<f64>,
+        gamma: f64,
+        mass: f64,
+        time: f64,
+    ) -> (Vector2<f64>, Vector2<f64>) {
+        if gamma <= 0.0 {
+            return (
+                pos + vel * time + acc * time.powi(2) / 2.0,
+                vel + acc * time,
+            );
+        }
+        let decay = (-gamma * time).exp();
+        let relaxed = -(-gamma * time).exp_m1();
+        let terminal = acc / gamma;
+        let mean_vel = terminal + (vel - terminal) * decay;
+        let mean_pos = pos + terminal * time + (vel - terminal) * (relaxed / gamma);
+
+        let thermal = self.temperature.max(0.0) / mass;
+        let var_vel = thermal * relaxed * (1.0 + decay);
+        let var_pos = thermal / gamma.powi(2)
+            * (2.0 * gamma * time - 3.0 + 4.0 * decay - decay.powi(2)).max(0.0);
+        let cov = thermal / gamma * relaxed.powi(2);
+        if var_vel <= 0.0 {
+            return (mean_pos, mean_vel);
+        }
+        let sigma_vel = var_vel.sqrt();
+        let along = cov / sigma_vel;
+        let residual = (var_pos - along.powi(2)).max(0.0).sqrt();
+
+        let z_vel = Vector2::new(random_normal(), random_normal());
+        let z_pos = Vector2::new(random_normal(), random_normal());
+        (
+            mean_pos + z_vel * along + z_pos * residual,
+            mean_vel + z_vel * sigma_vel,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn transition_moments() {
+        seed_random(51);
+        let langevin = Langevin::new(2.0, 1.0);
+        let (gamma, mass, time) = (0.5, 4.0, 3.0);
+        let acc = Vector2::new(0.2, 0.0);
+        let n = 40_000;
+        let (pos, vel): (Vec<Vector2<f64>>, Vec<Vector2<f64>>) = (0..n)
+            .map(|_| {
+                langevin.transition(
+                    Vector2::new(0.0, 0.0),
+                    Vector2::new(1.0, 0.0),
+                    acc,
+                    gamma,
+                    mass,
+                    time,
+                )
+            })
+            .unzip();
+        let mean = |f: &dyn Fn(usize) -> f64| (0..n).map(f).sum::<f64>() / n as f64;
+
+        let decay = (-gamma * time).exp();
+        let mean_vel = 0.4 + (1.0 - 0.4) * decay;
+        let mean_pos = 0.4 * time + (1.0 - 0.4) * (1.0 - decay) / gamma;
+        let var_vel = 0.5 * (1.0 - decay.powi(2));
+        let var_pos =
+            0.5 / gamma.powi(2) * (2.0 * gamma * time - 3.0 + 4.0 * decay - decay.powi(2));
+        let cov = 0.5 / gamma * (1.0 - decay).powi(2);
+
+        assert!((mean(&|i| vel[i].x) - mean_vel).abs() < 0.01);
+        assert!((mean(&|i| pos[i].x) - mean_pos).abs() < 0.02);
+        assert!((mean(&|i| vel[i].y.powi(2)) - var_vel).abs() < 0.02 * var_vel);
+        assert!((mean(&|i| pos[i].y.powi(2)) - var_pos).abs() < 0.03 * var_pos);
+        assert!((mean(&|i| pos[i].y * vel[i].y) - cov).abs() < 0.03 * cov);
+    }
+
+    #[test]
+    fn no_drag_is_ballistic() {
+        let langevin = Langevin::new(1.0, 1.0);
+        let (pos, vel) = langevin.transition(
+            Vector2::new(1.0, 1.0),
+            Vector2::new(1.0, 0.0),
+            Vector2::new(0.0, 2.0),
+            0.0,
+            1.0,
+            2.0,
+        );
+        assert_eq!(pos, Vector2::new(3.0, 5.0));
+        assert_eq!(vel, Vector2::new(1.0, 4.0));
+    }
+}
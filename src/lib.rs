@@ -1,12 +1,38 @@
+pub mod boltzmann;
+mod boltzmann_js;
 mod border;
 mod cfg;
 mod collidable;
 mod collidables;
 mod collision;
-mod crystal_structure;
+pub mod collision_stats;
+pub mod crystal_structure;
 mod crystal_structure_js;
+pub mod dirac;
 mod electron;
 mod electron_js;
+pub mod generation;
+pub mod impurity;
+mod impurity_js;
 mod ion;
+pub mod ion_import;
 mod ion_js;
-mod utils;
+pub mod langevin;
+pub mod placement;
+pub mod precision;
+mod precision_js;
+pub mod relaxation;
+mod relaxation_js;
+pub mod scattering;
+pub mod soa;
+pub mod sommerfeld;
+pub mod species;
+mod species_js;
+pub mod sweep;
+pub mod thermal;
+pub mod thermostat;
+pub mod three_d;
+mod three_d_js;
+pub mod trap;
+mod trap_js;
+pub mod utils;
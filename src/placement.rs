@@ -0,0 +1,282 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use nalgebra::Vector2;
+use serde_json::Value;
+
+use crate::utils::{random, random_normal};
+
+// Velocities given to newly placed carriers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VelocityDistribution {
+    // One speed in a uniformly random direction.
+    Monoenergetic { speed: f64 },
+    // 2D Maxwell-Boltzmann distribution at `temperature` (k_B = 1).
+    Maxwell { temperature: f64 },
+    // Maxwellian shifted by `drift` along the field axis.
+    DriftingMaxwell { temperature: f64, drift: f64 },
+}
+
+impl VelocityDistribution {
+    pub fn sample(&self, mass: f64) -> Vector2<f64> {
+        let maxwell = |temperature: f64| {
+            let sigma = (temperature.max(0.0) / mass).sqrt();
+            Vector2::new(random_normal(), random_normal()) * sigma
+        };
+        match *self {
+            VelocityDistribution::Monoenergetic { speed } => {
+                let angle = random() * 2.0 * std::f64::consts::PI;
+                Vector2::new(angle.cos(), angle.sin()) * speed
+            }
+            VelocityDistribution::Maxwell { temperature } => maxwell(temperature),
+            VelocityDistribution::DriftingMaxwell { temperature, drift } => {
+                maxwell(temperature) + Vector2::new(drift, 0.0)
+            }
+        }
+    }
+}
+
+// How a batch of carriers is put into the structure.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InitStrategy {
+    // Uniformly random free positions.
+    Random {
+        count: usize,
+        velocities: VelocityDistribution,
+    },
+    // Interstitial sites in the middle of the squares between four lattice ions, chosen at random.
+    Lattice {
+        count: usize,
+        velocities: VelocityDistribution,
+    },
+    // Carriers moving along +x at `speed`, packed against the left border
+    // inside the strip of the given `width` around `y`.
+    Beam {
+        count: usize,
+        y: f64,
+        width: f64,
+        speed: f64,
+    },
+    // Carriers exactly where the records put them.
+    Explicit(Vec<CarrierRecord>),
+}
+
+// A single carrier read from an external configuration, species falls back to the one placed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CarrierRecord {
+    pub pos: Vector2<f64>,
+    pub vel: Vector2<f64>,
+    pub species: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PlacementError {
+    Io(String),
+    Parse { line: usize, message: String },
+    Entry { entry: usize, message: String },
+    UnknownSpecies { index: usize, species: usize },
+    OutOfBox { index: usize, pos: Vector2<f64> },
+    Overlap { index: usize },
+    NoLattice,
+    NoRoom { requested: usize, placed: usize },
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::Io(message) => write!(f, "cannot read carrier file: {}", message),
+            PlacementError::Parse { line, message } => {
+                write!(f, "invalid carrier entry at line {}: {}", line, message)
+            }
+            PlacementError::Entry { entry, message } => {
+                write!(f, "invalid carrier entry {}: {}", entry, message)
+            }
+            PlacementError::UnknownSpecies { index, species } => {
+                write!(f, "carrier {} has unknown species {}", index, species)
+            }
+            PlacementError::OutOfBox { index, pos } => write!(
+                f,
+                "carrier {} at ({}, {}) does not fit inside the box",
+                index, pos.x, pos.y
+            ),
+            PlacementError::Overlap { index } => {
+                write!(f, "carrier {} overlaps an ion or another carrier", index)
+            }
+            PlacementError::NoLattice => {
+                write!(f, "interstitial sites need a regular ion lattice")
+            }
+            PlacementError::NoRoom { requested, placed } => write!(
+                f,
+                "only {} of {} carriers fit, lower the density",
+                placed, requested
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
+
+// Parses a JSON array of carriers.
+// Each entry is either an object `{"x", "y", "vx"?, "vy"?, "species"?}` or an array `[x, y, vx?, vy?, species?]`.
+// Syntax errors report the line of the text, errors in an entry report its 1-based index in the array.
+pub fn parse_json(text: &str) -> Result<Vec<CarrierRecord>, PlacementError> {
+    let value: Value = serde_json::from_str(text).map_err(|err| PlacementError::Parse {
+        line: err.line(),
+        message: err.to_string(),
+    })?;
+    let entries = value.as_array().ok_or_else(|| PlacementError::Parse {
+        line: 1,
+        message: "expected an array of carriers".to_string(),
+    })?;
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| parse_json_entry(entry, i + 1))
+        .collect()
+}
+
+fn parse_json_entry(value: &Value, entry: usize) -> Result<CarrierRecord, PlacementError> {
+    let (x, y, vx, vy, species) = match value {
+        Value::Object(map) => (
+            map.get("x"),
+            map.get("y"),
+            map.get("vx"),
+            map.get("vy"),
+            map.get("species"),
+        ),
+        Value::Array(items) => (
+            items.first(),
+            items.get(1),
+            items.get(2),
+            items.get(3),
+            items.get(4),
+        ),
+        _ => {
+            return Err(PlacementError::Entry {
+                entry,
+                message: "expected an object or an array".to_string(),
+            })
+        }
+    };
+
+    let number = |name: &str, value: Option<&Value>| match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_f64()
+            .map(Some)
+            .ok_or_else(|| PlacementError::Entry {
+                entry,
+                message: format!("{} is not a number: {}", name, value),
+            }),
+    };
+    let missing = |name: &str| PlacementError::Entry {
+        entry,
+        message: format!("missing {} value", name),
+    };
+
+    let x = number("x", x)?.ok_or_else(|| missing("x"))?;
+    let y = number("y", y)?.ok_or_else(|| missing("y"))?;
+    let vx = number("vx", vx)?.unwrap_or(0.0);
+    let vy = number("vy", vy)?.unwrap_or(0.0);
+    let species = match species {
+        None | Some(Value::Null) => None,
+        Some(value) => Some(
+            value
+                .as_u64()
+                .and_then(|s| usize::try_from(s).ok())
+                .ok_or_else(|| PlacementError::Entry {
+                    entry,
+                    message: format!("species is not a non-negative integer: {}", value),
+                })?,
+        ),
+    };
+
+    Ok(CarrierRecord {
+        pos: Vector2::new(x, y),
+        vel: Vector2::new(vx, vy),
+        species,
+    })
+}
+
+// Reads carriers from a JSON file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_file(path: &std::path::Path) -> Result<Vec<CarrierRecord>, PlacementError> {
+    let text = std::fs::read_to_string(path).map_err(|err| PlacementError::Io(err.to_string()))?;
+    parse_json(&text)
+}
+
+// Candidate centres on a square grid with the given spacing and a random offset,
+// keeping `margin` from the edges of the box. Carriers on different nodes never overlap
+// as long as the spacing is at least their clearance.
+pub fn grid_sites(x_size: f64, y_size: f64, spacing: f64, margin: f64) -> Vec<Vector2<f64>> {
+    let (offset_x, offset_y) = (random() * spacing, random() * spacing);
+    let mut sites = Vec::new();
+    let mut x = margin + offset_x;
+    while x <= x_size - margin {
+        let mut y = margin + offset_y;
+        while y <= y_size - margin {
+            sites.push(Vector2::new(x, y));
+            y += spacing;
+        }
+        x += spacing;
+    }
+    sites
+}
+
+// Fisher-Yates shuffle driven by the simulation's random numbers.
+pub fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = ((random() * (i + 1) as f64) as usize).min(i);
+        items.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn parse_json_objects_and_arrays() {
+        let records = parse_json(
+            r#"[{"x": 10, "y": 20, "vx": 1.5, "species": 1}, [30, 40], [50, 60, 0, -2]]"#,
+        )
+        .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].vel, Vector2::new(1.5, 0.0));
+        assert_eq!(records[0].species, Some(1));
+        assert_eq!(records[1].vel, Vector2::new(0.0, 0.0));
+        assert_eq!(records[2].vel, Vector2::new(0.0, -2.0));
+        assert!(matches!(
+            parse_json(r#"[[1, 2], {"y": 3}]"#),
+            Err(PlacementError::Entry { entry: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn drifting_maxwell_moments() {
+        seed_random(31);
+        let distribution = VelocityDistribution::DriftingMaxwell {
+            temperature: 2.0,
+            drift: 0.5,
+        };
+        let n = 20_000;
+        let samples: Vec<Vector2<f64>> = (0..n).map(|_| distribution.sample(4.0)).collect();
+        let mean = samples.iter().sum::<Vector2<f64>>() / n as f64;
+        let var_y = samples.iter().map(|v| v.y.powi(2)).sum::<f64>() / n as f64;
+
+        assert!((mean - Vector2::new(0.5, 0.0)).magnitude() < 0.02);
+        assert!((var_y - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn grid_sites_keep_spacing() {
+        seed_random(32);
+        let sites = grid_sites(100.0, 60.0, 12.0, 3.0);
+        assert!(sites
+            .iter()
+            .all(|site| site.x >= 3.0 && site.x <= 97.0 && site.y >= 3.0 && site.y <= 57.0));
+        assert!(sites.len() >= 7 * 4);
+    }
+}
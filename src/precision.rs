@@ -0,0 +1,725 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use nalgebra::Vector2;
+use roots::find_roots_quartic;
+
+use crate::cfg::EPSILON;
+use crate::crystal_structure::CrystalStructure;
+use crate::scattering::ElectronScattering;
+use crate::species::field_from_acc;
+
+// Newton steps that polish a quartic root found in f64 to the working precision.
+const POLISH_STEPS: usize = 4;
+
+// Floating point type the reduced `Billiard` runs in.
+// `Electron`, `Ion`, `Border` and the engine stay on f64, only this kernel is generic: it replays
+// the ion collisions of plain structures in f32 and double-double to see how far long f64
+// trajectories can be trusted.
+pub trait Real:
+    Copy
+    + Debug
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn sqrt(self) -> Self;
+
+    fn zero() -> Self {
+        Self::from_f64(0.0)
+    }
+
+    fn abs(self) -> Self {
+        if self < Self::zero() {
+            -self
+        } else {
+            self
+        }
+    }
+}
+
+impl Real for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn sqrt(self) -> Self {
+        f32::sqrt(self)
+    }
+}
+
+impl Real for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+}
+
+// Unevaluated sum hi + lo of two f64 with |lo| <= ulp(hi) / 2, about 106 bits of mantissa.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+impl DoubleDouble {
+    pub fn new(hi: f64, lo: f64) -> Self {
+        let (hi, lo) = quick_two_sum(hi, lo);
+        DoubleDouble { hi, lo }
+    }
+}
+
+// Exact a + b = s + e.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let v = s - a;
+    (s, (a - (s - v)) + (b - v))
+}
+
+// Exact a + b = s + e for |a| >= |b|.
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+// Exact a * b = p + e using the fused multiply-add.
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let (s, e) = two_sum(self.hi, other.hi);
+        let (t, f) = two_sum(self.lo, other.lo);
+        let (s, e) = quick_two_sum(s, e + t);
+        DoubleDouble::new(s, e + f)
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let (p, e) = two_prod(self.hi, other.hi);
+        DoubleDouble::new(p, e + (self.hi * other.lo + self.lo * other.hi))
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    // Long division with three f64 quotient digits.
+    fn div(self, other: Self) -> Self {
+        let q1 = self.hi / other.hi;
+        let r = self - other * DoubleDouble::from_f64(q1);
+        let q2 = r.hi / other.hi;
+        let r = r - other * DoubleDouble::from_f64(q2);
+        let q3 = r.hi / other.hi;
+        DoubleDouble::new(q1, q2) + DoubleDouble::from_f64(q3)
+    }
+}
+
+impl PartialOrd for DoubleDouble {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.hi.partial_cmp(&other.hi) {
+            Some(Ordering::Equal) => self.lo.partial_cmp(&other.lo),
+            ordering => ordering,
+        }
+    }
+}
+
+impl Real for DoubleDouble {
+    fn from_f64(value: f64) -> Self {
+        DoubleDouble { hi: value, lo: 0.0 }
+    }
+
+    fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    // One Newton step from the f64 square root doubles the number of correct bits.
+    fn sqrt(self) -> Self {
+        if self.hi <= 0.0 {
+            return DoubleDouble::zero();
+        }
+        let root = DoubleDouble::from_f64(self.hi.sqrt());
+        root + (self - root * root) / (root + root)
+    }
+}
+
+// Plain 2D vector over any `Real`, nalgebra needs more of the scalar than double-double offers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vec2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T: Real> Vec2<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Vec2 { x, y }
+    }
+
+    pub fn zero() -> Self {
+        Vec2::new(T::zero(), T::zero())
+    }
+
+    pub fn from_f64(vector: Vector2<f64>) -> Self {
+        Vec2::new(T::from_f64(vector.x), T::from_f64(vector.y))
+    }
+
+    pub fn to_f64(self) -> Vector2<f64> {
+        Vector2::new(self.x.to_f64(), self.y.to_f64())
+    }
+
+    pub fn dot(self, other: Self) -> T {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn scale(self, factor: T) -> Self {
+        Vec2::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl<T: Real> Add for Vec2<T> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl<T: Real> Sub for Vec2<T> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+// Smallest root above `EPSILON` of a * t^2 + b * t + c, using the cancellation-free form.
+fn first_quadratic_root<T: Real>(a: T, b: T, c: T) -> Option<T> {
+    let epsilon = T::from_f64(EPSILON);
+    if a == T::zero() {
+        if b == T::zero() {
+            return None;
+        }
+        let t = -c / b;
+        return if t > epsilon { Some(t) } else { None };
+    }
+    let discriminant = b * b - T::from_f64(4.0) * a * c;
+    if discriminant < T::zero() {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let q = if b < T::zero() { b - root } else { b + root } * T::from_f64(-0.5);
+    let mut roots = vec![q / a];
+    if q != T::zero() {
+        roots.push(c / q);
+    }
+    roots
+        .into_iter()
+        .filter(|&t| t > epsilon)
+        .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+}
+
+// Time until a body at `dist` ahead, closing in with `vel` and `acc`, is reached;
+// the precision-generic form of `utils::calc_time_to_border_collision`.
+pub fn border_time<T: Real>(dist: T, vel: T, acc: T) -> Option<T> {
+    first_quadratic_root(acc * T::from_f64(0.5), vel, -dist)
+}
+
+// Time until two disks with relative position, velocity and acceleration touch at `r_sum`;
+// the precision-generic form of `utils::calc_time_to_collision`.
+// The quartic is solved in f64 and the root is polished by Newton steps in `T`.
+pub fn contact_time<T: Real>(
+    d_pos: Vec2<T>,
+    d_vel: Vec2<T>,
+    d_acc: Vec2<T>,
+    r_sum: T,
+) -> Option<T> {
+    let two = T::from_f64(2.0);
+    let e = d_pos.dot(d_pos) - r_sum * r_sum;
+    if d_acc.dot(d_acc) == T::zero() {
+        return first_quadratic_root(d_vel.dot(d_vel), two * d_vel.dot(d_pos), e);
+    }
+    let a = d_acc.dot(d_acc) * T::from_f64(0.25);
+    let b = d_vel.dot(d_acc);
+    let c = d_vel.dot(d_vel) + d_pos.dot(d_acc);
+    let d = two * d_pos.dot(d_vel);
+    let mut roots = find_roots_quartic(a.to_f64(), b.to_f64(), c.to_f64(), d.to_f64(), e.to_f64())
+        .as_ref()
+        .to_vec();
+    roots.retain(|&x| x > EPSILON);
+    let mut t = T::from_f64(roots.into_iter().fold(f64::INFINITY, f64::min));
+    if t.to_f64().is_infinite() {
+        return None;
+    }
+    for _ in 0..POLISH_STEPS {
+        let value = (((a * t + b) * t + c) * t + d) * t + e;
+        let slope = ((T::from_f64(4.0) * a * t + T::from_f64(3.0) * b) * t + two * c) * t + d;
+        if slope == T::zero() {
+            break;
+        }
+        t = t - value / slope;
+    }
+    Some(t)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Carrier<T> {
+    pub pos: Vec2<T>,
+    pub vel: Vec2<T>,
+    pub radius: T,
+    pub mass: T,
+    pub charge: T,
+}
+
+impl<T: Real> Carrier<T> {
+    // Acceleration q * E / m in the field along x.
+    fn acceleration(&self, field: f64) -> Vec2<T> {
+        Vec2::new(self.charge * T::from_f64(field) / self.mass, T::zero())
+    }
+}
+
+// What a carrier of the kernel hits next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Hit {
+    Ion(usize),
+    Wall,
+    Periodic,
+}
+
+// Reduced ion-only billiard in precision `T`, not a generic copy of the engine.
+// Carriers accelerate along x with their own charge and scalar mass, reflect specularly off
+// ions and the y borders and wrap around the periodic x border where `Border` does, without drag.
+// Ions are frozen where they are and carriers do not see each other, so it only follows the
+// engine run without drag on the structures `compare_precisions` accepts.
+pub struct Billiard<T> {
+    pub x_size: T,
+    pub y_size: T,
+    pub ions: Vec<Vec2<T>>,
+    pub ion_radii: Vec<T>,
+    pub carriers: Vec<Carrier<T>>,
+}
+
+impl<T: Real> Billiard<T> {
+    // Rounds the ions and carriers of a structure to `T`.
+    pub fn from_structure(cs: &CrystalStructure) -> Self {
+        Billiard {
+            x_size: T::from_f64(cs.x_size),
+            y_size: T::from_f64(cs.y_size),
+            ions: cs
+                .ions
+                .iter()
+                .map(|ion| Vec2::from_f64(ion.borrow().pos))
+                .collect(),
+            ion_radii: cs
+                .ions
+                .iter()
+                .map(|ion| T::from_f64(ion.borrow().radius))
+                .collect(),
+            carriers: cs
+                .electrons
+                .iter()
+                .map(|electron| {
+                    let electron = electron.borrow();
+                    Carrier {
+                        pos: Vec2::from_f64(electron.pos),
+                        vel: Vec2::from_f64(electron.vel),
+                        radius: T::from_f64(electron.radius),
+                        mass: T::from_f64(electron.mass),
+                        charge: T::from_f64(electron.charge),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    // Advances every carrier by one tick in the field of the engine's acceleration `acc`.
+    pub fn update(&mut self, acc: f64) {
+        let field = field_from_acc(acc);
+        for i in 0..self.carriers.len() {
+            let mut carrier = self.carriers[i];
+            let acc = carrier.acceleration(field);
+            let mut time = T::from_f64(1.0);
+            while let Some((hit, t)) = self.next_hit(&carrier, acc, time) {
+                move_carrier(&mut carrier, acc, t);
+                time = time - t;
+                self.resolve(&mut carrier, hit);
+            }
+            move_carrier(&mut carrier, acc, time);
+            self.carriers[i] = carrier;
+        }
+    }
+
+    fn next_hit(&self, carrier: &Carrier<T>, acc: Vec2<T>, within: T) -> Option<(Hit, T)> {
+        let mut best: Option<(Hit, T)> = None;
+        let mut consider = |hit: Hit, time: Option<T>| {
+            if let Some(time) = time {
+                if time <= within && best.is_none_or(|(_, best)| time < best) {
+                    best = Some((hit, time));
+                }
+            }
+        };
+        let (pos, vel) = (carrier.pos, carrier.vel);
+        // Same gaps as the periodic `Border`s, a carrier wraps once it is a radius past the edge.
+        consider(
+            Hit::Periodic,
+            border_time(self.x_size + carrier.radius - pos.x, vel.x, acc.x),
+        );
+        consider(
+            Hit::Periodic,
+            border_time(pos.x + carrier.radius, -vel.x, -acc.x),
+        );
+        consider(
+            Hit::Wall,
+            border_time(pos.y - carrier.radius, -vel.y, -acc.y),
+        );
+        consider(
+            Hit::Wall,
+            border_time(self.y_size - carrier.radius - pos.y, vel.y, acc.y),
+        );
+
+        let travel = (vel.dot(vel).sqrt() + acc.x.abs()) * within;
+        for (i, (ion, &radius)) in self.ions.iter().zip(self.ion_radii.iter()).enumerate() {
+            let clearance = radius + carrier.radius;
+            let reach = travel + clearance;
+            let d_pos = pos - *ion;
+            if d_pos.x.abs() > reach || d_pos.y.abs() > reach {
+                continue;
+            }
+            consider(Hit::Ion(i), contact_time(d_pos, vel, acc, clearance));
+        }
+        best
+    }
+
+    fn resolve(&self, carrier: &mut Carrier<T>, hit: Hit) {
+        match hit {
+            Hit::Ion(i) => {
+                let normal = carrier.pos - self.ions[i];
+                let approach = carrier.vel.dot(normal) / normal.dot(normal);
+                carrier.vel = carrier.vel - normal.scale(T::from_f64(2.0) * approach);
+            }
+            Hit::Wall => carrier.vel.y = -carrier.vel.y,
+            Hit::Periodic => {
+                if carrier.vel.x > T::zero() {
+                    carrier.pos.x = carrier.pos.x - self.x_size;
+                } else {
+                    carrier.pos.x = carrier.pos.x + self.x_size;
+                }
+            }
+        }
+    }
+
+    pub fn positions(&self) -> Vec<Vector2<f64>> {
+        self.carriers
+            .iter()
+            .map(|carrier| carrier.pos.to_f64())
+            .collect()
+    }
+
+    // Kinetic energy of the carriers, accumulated in `T`.
+    pub fn kinetic_energy(&self) -> f64 {
+        self.carriers
+            .iter()
+            .fold(T::zero(), |sum, carrier| {
+                sum + carrier.vel.dot(carrier.vel) * carrier.mass * T::from_f64(0.5)
+            })
+            .to_f64()
+    }
+}
+
+fn move_carrier<T: Real>(carrier: &mut Carrier<T>, acc: Vec2<T>, time: T) {
+    carrier.pos = carrier.pos + carrier.vel.scale(time) + acc.scale(time * time * T::from_f64(0.5));
+    carrier.vel = carrier.vel + acc.scale(time);
+}
+
+// How far the f32 and f64 trajectories drift from the double-double reference.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DivergenceReport {
+    // Largest carrier distance from its reference twin after each tick, periodic in x.
+    pub f32_error: Vec<f64>,
+    pub f64_error: Vec<f64>,
+    // Relative deviation of the final kinetic energy from the reference.
+    pub f32_energy_error: f64,
+    pub f64_energy_error: f64,
+}
+
+impl DivergenceReport {
+    // First tick after which the error exceeds `threshold`, e.g. a fraction of the ion radius.
+    pub fn divergence_tick(errors: &[f64], threshold: f64) -> Option<usize> {
+        errors
+            .iter()
+            .position(|&error| error > threshold)
+            .map(|tick| tick + 1)
+    }
+}
+
+// Part of a structure the reduced `Billiard` does not model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrecisionError {
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for PrecisionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrecisionError::Unsupported(what) => {
+                write!(f, "the precision billiard does not model {}", what)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PrecisionError {}
+
+// First feature of `cs` that would make the engine leave the reduced billiard model.
+fn unsupported(cs: &CrystalStructure) -> Option<&'static str> {
+    let features = [
+        (!cs.traps.is_empty(), "traps"),
+        (!cs.impurities.is_empty(), "charged impurities"),
+        (cs.vibration.is_some(), "ion vibrations"),
+        (
+            cs.ions.iter().any(|ion| ion.borrow().is_mobile()),
+            "mobile ions",
+        ),
+        (
+            cs.generation.is_some() || cs.impact_ionisation.is_some(),
+            "generation and recombination",
+        ),
+        (cs.optical_phonon.is_some(), "optical phonons"),
+        (!cs.scattering_laws.is_empty(), "scattering laws"),
+        (cs.pauli_blocking.is_some(), "Pauli blocking"),
+        (cs.thermostat.is_some(), "thermostats"),
+        (cs.langevin.is_some(), "Langevin noise"),
+        (
+            cs.electron_scattering != ElectronScattering::Disabled && cs.electrons.len() > 1,
+            "carrier-carrier scattering",
+        ),
+        (
+            cs.electrons.iter().any(|electron| {
+                let electron = electron.borrow();
+                electron.dirac.is_some()
+                    || electron.mass_tensor.is_some()
+                    || electron.transit.is_some()
+            }),
+            "Dirac, anisotropic or orbiting carriers",
+        ),
+    ];
+    features
+        .iter()
+        .find(|(present, _)| *present)
+        .map(|&(_, what)| what)
+}
+
+// Replays the ion collisions of a structure for `ticks` ticks in f32, f64 and double-double,
+// with the reduced `Billiard` of each precision.
+// Structures with anything beyond plain carriers among pinned ions are rejected, the metrics
+// would describe a different model from the one the engine runs.
+pub fn compare_precisions(
+    cs: &CrystalStructure,
+    acc: f64,
+    ticks: usize,
+) -> Result<DivergenceReport, PrecisionError> {
+    if let Some(what) = unsupported(cs) {
+        return Err(PrecisionError::Unsupported(what));
+    }
+    let mut single = Billiard::<f32>::from_structure(cs);
+    let mut double = Billiard::<f64>::from_structure(cs);
+    let mut reference = Billiard::<DoubleDouble>::from_structure(cs);
+    let error = |positions: Vec<Vector2<f64>>, reference: &[Vector2<f64>]| {
+        positions
+            .iter()
+            .zip(reference.iter())
+            .map(|(pos, reference)| {
+                let dx = (pos.x - reference.x).abs();
+                Vector2::new(dx.min(cs.x_size - dx), pos.y - reference.y).magnitude()
+            })
+            .fold(0.0, f64::max)
+    };
+
+    let mut report = DivergenceReport {
+        f32_error: Vec::with_capacity(ticks),
+        f64_error: Vec::with_capacity(ticks),
+        f32_energy_error: 0.0,
+        f64_energy_error: 0.0,
+    };
+    for _ in 0..ticks {
+        single.update(acc);
+        double.update(acc);
+        reference.update(acc);
+        let positions = reference.positions();
+        report.f32_error.push(error(single.positions(), &positions));
+        report.f64_error.push(error(double.positions(), &positions));
+    }
+    let energy = reference.kinetic_energy();
+    if energy > 0.0 {
+        report.f32_energy_error = (single.kinetic_energy() - energy).abs() / energy;
+        report.f64_energy_error = (double.kinetic_energy() - energy).abs() / energy;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::electron::Electron;
+    use crate::ion_import::IonRecord;
+    use crate::species::Species;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn double_double_arithmetic() {
+        let one = DoubleDouble::from_f64(1.0);
+        let tiny = DoubleDouble::from_f64(1e-20);
+        assert_eq!((one + tiny - one).to_f64(), 1e-20);
+
+        let two = DoubleDouble::from_f64(2.0);
+        let root = two.sqrt();
+        assert!((root * root - two).to_f64().abs() < 1e-30);
+
+        let third = one / DoubleDouble::from_f64(3.0);
+        assert!((third * DoubleDouble::from_f64(3.0) - one).to_f64().abs() < 1e-31);
+    }
+
+    #[test]
+    fn contact_time_in_every_precision() {
+        fn head_on<T: Real>(acc: f64) -> T {
+            contact_time(
+                Vec2::<T>::from_f64(Vector2::new(-20.0, 0.5)),
+                Vec2::from_f64(Vector2::new(10.0, 0.0)),
+                Vec2::from_f64(Vector2::new(acc, 0.0)),
+                T::from_f64(13.0),
+            )
+            .unwrap()
+        }
+        let exact = 20.0 - (13.0f64.powi(2) - 0.25).sqrt();
+        assert!((head_on::<f64>(0.0) * 10.0 - exact).abs() < 1e-12);
+        assert!((head_on::<f32>(0.0).to_f64() * 10.0 - exact).abs() < 1e-5);
+
+        // With acceleration the polished double-double root sits on the contact circle.
+        let t = head_on::<DoubleDouble>(0.3);
+        let x = DoubleDouble::from_f64(-20.0)
+            + DoubleDouble::from_f64(10.0) * t
+            + DoubleDouble::from_f64(0.15) * t * t;
+        let gap = x * x + DoubleDouble::from_f64(0.25) - DoubleDouble::from_f64(169.0);
+        assert!(gap.to_f64().abs() < 1e-25);
+        assert!((t.to_f64() - head_on::<f64>(0.3)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn single_precision_diverges_first() {
+        seed_random(91);
+        let mut cs = CrystalStructure::new(400.0, 300.0, 50.0, 10.0, 10);
+        cs.set_electron_scattering(ElectronScattering::Disabled);
+        let report = compare_precisions(&cs, 0.0, 200).unwrap();
+
+        assert!(report.f32_error[0] < 1e-2);
+        assert!(report.f64_error[0] < 1e-9);
+        let f32_tick = DivergenceReport::divergence_tick(&report.f32_error, 1.0);
+        let f64_tick = DivergenceReport::divergence_tick(&report.f64_error, 1.0);
+        assert!(f32_tick.is_some());
+        assert!(f64_tick.is_none_or(|tick| tick > f32_tick.unwrap()));
+        assert!(report.f64_energy_error < 1e-9);
+    }
+
+    #[test]
+    fn billiard_follows_the_engine_with_own_radii_and_masses() {
+        let mut ion = IonRecord::new(Vector2::new(100.0, 100.0));
+        ion.radius = Some(4.0);
+        let mut cs = CrystalStructure::with_ions(300.0, 200.0, &[ion], 0.0, 0).unwrap();
+        let holes = cs.add_species(Species::hole(4.0));
+        cs.add_carrier(Electron::of_species(
+            holes,
+            &cs.species[holes],
+            Vector2::new(80.0, 102.0),
+            Vector2::new(3.0, 0.0),
+        ));
+        let mut billiard = Billiard::<f64>::from_structure(&cs);
+        assert_eq!(billiard.ion_radii, vec![4.0]);
+        assert_eq!(billiard.carriers[0].mass, 4.0);
+
+        for _ in 0..10 {
+            cs.update(0.2, 0.0);
+            billiard.update(0.2);
+        }
+        let electron = cs.electrons[0].borrow();
+        assert_eq!(cs.collision_stats().ion.count, 1);
+        assert!((billiard.positions()[0] - electron.pos).magnitude() < 1e-9);
+        assert!((billiard.kinetic_energy() - electron.kinetic_energy()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn billiard_wraps_where_the_periodic_border_does() {
+        let mut cs = CrystalStructure::with_ions(300.0, 200.0, &[], 0.0, 0).unwrap();
+        cs.add_carrier(Electron::new(
+            Vector2::new(296.0, 100.0),
+            Vector2::new(1.5, 0.7),
+            Vector2::new(0.0, 0.0),
+        ));
+        let mut billiard = Billiard::<f64>::from_structure(&cs);
+
+        for _ in 0..8 {
+            cs.update(0.1, 0.0);
+            billiard.update(0.1);
+            let electron = cs.electrons[0].borrow();
+            assert!((billiard.positions()[0] - electron.pos).magnitude() < 1e-9);
+        }
+        assert!(cs.electrons[0].borrow().pos.x < 20.0);
+    }
+
+    #[test]
+    fn structures_beyond_the_billiard_are_rejected() {
+        let cs = CrystalStructure::new(400.0, 300.0, 50.0, 10.0, 10);
+        assert_eq!(
+            compare_precisions(&cs, 0.0, 10),
+            Err(PrecisionError::Unsupported("carrier-carrier scattering"))
+        );
+
+        let mut cs = CrystalStructure::new(400.0, 300.0, 50.0, 10.0, 1);
+        cs.set_mobile_ions(1.0, 0.1);
+        assert_eq!(
+            compare_precisions(&cs, 0.0, 10),
+            Err(PrecisionError::Unsupported("mobile ions"))
+        );
+    }
+}
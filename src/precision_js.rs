@@ -0,0 +1,43 @@
+use wasm_bindgen::prelude::*;
+
+use crate::precision::DivergenceReport;
+
+#[wasm_bindgen(js_name = DivergenceReport)]
+pub struct DivergenceReportJs {
+    report: DivergenceReport,
+}
+
+#[wasm_bindgen(js_class = DivergenceReport)]
+impl DivergenceReportJs {
+    pub fn f32_error(&self) -> Vec<f64> {
+        self.report.f32_error.clone()
+    }
+
+    pub fn f64_error(&self) -> Vec<f64> {
+        self.report.f64_error.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn f32_energy_error(&self) -> f64 {
+        self.report.f32_energy_error
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn f64_energy_error(&self) -> f64 {
+        self.report.f64_energy_error
+    }
+
+    pub fn f32_divergence_tick(&self, threshold: f64) -> Option<usize> {
+        DivergenceReport::divergence_tick(&self.report.f32_error, threshold)
+    }
+
+    pub fn f64_divergence_tick(&self, threshold: f64) -> Option<usize> {
+        DivergenceReport::divergence_tick(&self.report.f64_error, threshold)
+    }
+}
+
+impl DivergenceReportJs {
+    pub fn new(report: DivergenceReport) -> DivergenceReportJs {
+        DivergenceReportJs { report }
+    }
+}
@@ -0,0 +1,492 @@
+extern crate nalgebra as na;
+use na::Vector2;
+use std::fmt;
+
+use crate::collidables::Collidables;
+use crate::collision_stats::{CollisionKind, CollisionStats};
+use crate::crystal_structure::{CrystalStructure, Flux};
+use crate::electron::Electron;
+use crate::placement::VelocityDistribution;
+use crate::species::{field_from_acc, Species};
+use crate::utils::random;
+
+// Geometry-free Drude model in the relaxation time approximation.
+// Carriers are points that accelerate freely in the field and scatter at Poisson distributed
+// times with mean `tau`, each scattering redraws the velocity from the `equilibrium` distribution.
+// The x borders are periodic and the y borders reflect specularly without counting as scatterings,
+// so drift, mobility and current are measured exactly as in the hard-disk `CrystalStructure`.
+// Scatterings are recorded as ion collisions, they stand in for the lattice of the hard-disk model
+// and the mean free time is measured through the same `CollisionStats`.
+// `tau` must be positive, carriers would never finish a tick otherwise.
+pub struct RelaxationTime {
+    pub x_size: f64,
+    pub y_size: f64,
+    pub tau: f64,
+    pub equilibrium: VelocityDistribution,
+    pub acc: f64,
+    pub electrons: Vec<Electron>,
+    pub species: Vec<Species>,
+    pub flux: Vec<Flux>,
+    pub time: f64,
+    pub scatterings: i32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RelaxationError {
+    InvalidTau(f64),
+    NoScatterings,
+    // State of the structure the idealised model cannot carry over.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for RelaxationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelaxationError::InvalidTau(tau) => {
+                write!(f, "relaxation time must be positive, got {}", tau)
+            }
+            RelaxationError::NoScatterings => write!(
+                f,
+                "the structure has no measured mean free time, run it before matching"
+            ),
+            RelaxationError::Unsupported(what) => {
+                write!(f, "the relaxation time model cannot take over {}", what)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RelaxationError {}
+
+impl RelaxationTime {
+    pub fn new(
+        x_size: f64,
+        y_size: f64,
+        tau: f64,
+        equilibrium: VelocityDistribution,
+        num_electrons: i32,
+    ) -> Result<RelaxationTime, RelaxationError> {
+        if tau.is_nan() || tau <= 0.0 {
+            return Err(RelaxationError::InvalidTau(tau));
+        }
+        let mut model = RelaxationTime::empty(x_size, y_size, tau, equilibrium);
+        let electron = model.add_species(Species::electron());
+        model.add_carriers(electron, num_electrons);
+        Ok(model)
+    }
+
+    // Idealised counterpart of a hard-disk structure: same box, species, carriers and field,
+    // with the mean free time pooled over every collision in the structure so far.
+    // Carriers that never scattered carry no free time and are left out of the pool.
+    // Carriers keep their Dirac state and mass tensor and start with fresh statistics. The model
+    // has no Langevin noise and no impurities, so structures using either are rejected.
+    pub fn matching(
+        cs: &CrystalStructure,
+        equilibrium: VelocityDistribution,
+    ) -> Result<RelaxationTime, RelaxationError> {
+        if cs.langevin.is_some() {
+            return Err(RelaxationError::Unsupported("Langevin noise"));
+        }
+        if cs
+            .electrons
+            .iter()
+            .any(|electron| electron.borrow().transit.is_some())
+        {
+            return Err(RelaxationError::Unsupported(
+                "carriers orbiting an impurity",
+            ));
+        }
+        let tau = cs.mean_free_time();
+        if tau <= 0.0 {
+            return Err(RelaxationError::NoScatterings);
+        }
+        let mut model = RelaxationTime::empty(cs.x_size, cs.y_size, tau, equilibrium);
+        model.acc = cs.acc;
+        model.species = cs.species.clone();
+        model.flux = vec![Flux::default(); cs.species.len()];
+        let field = field_from_acc(cs.acc);
+        for carrier in cs.electrons.iter() {
+            let mut electron = carrier.borrow().clone();
+            electron.collidable = Collidables::empty();
+            electron.collisions = CollisionStats::default();
+            electron.born_at = 0.0;
+            electron.set_field(field);
+            electron.time_to_bounce = model.free_flight();
+            model.electrons.push(electron);
+        }
+        Ok(model)
+    }
+
+    fn empty(
+        x_size: f64,
+        y_size: f64,
+        tau: f64,
+        equilibrium: VelocityDistribution,
+    ) -> RelaxationTime {
+        RelaxationTime {
+            x_size,
+            y_size,
+            tau,
+            equilibrium,
+            acc: 0.0,
+            electrons: Vec::new(),
+            species: Vec::new(),
+            flux: Vec::new(),
+            time: 0.0,
+            scatterings: 0,
+        }
+    }
+
+    pub fn add_species(&mut self, species: Species) -> usize {
+        self.species.push(species);
+        self.flux.push(Flux::default());
+        self.species.len() - 1
+    }
+
+    // Places carriers uniformly in the box with velocities drawn from the equilibrium distribution.
+    pub fn add_carriers(&mut self, species_id: usize, count: i32) {
+        let field = field_from_acc(self.acc);
+        for _ in 0..count {
+            let species = &self.species[species_id];
+            let pos = Vector2::new(random() * self.x_size, random() * self.y_size);
+            let vel = self.equilibrium.sample(species.mass);
+            let mut electron = Electron::of_species(species_id, species, pos, vel);
+            electron.set_field(field);
+            electron.born_at = self.time;
+            electron.time_to_bounce = self.free_flight();
+            self.electrons.push(electron);
+        }
+    }
+
+    // Exponentially distributed time until the next scattering.
+    fn free_flight(&self) -> f64 {
+        -self.tau * (1.0 - random()).ln()
+    }
+
+    pub fn update(&mut self, acc: f64, supp: f64) {
+        if self.acc != acc {
+            self.acc = acc;
+            let field = field_from_acc(acc);
+            self.electrons
+                .iter_mut()
+                .for_each(|electron| electron.set_field(field));
+        }
+
+        for i in 0..self.electrons.len() {
+            let mut time: f64 = 1.0;
+            while self.electrons[i].time_to_bounce <= time {
+                let time_to_bounce = self.electrons[i].time_to_bounce.max(0.0);
+                self.advance(i, time_to_bounce, supp);
+                time -= time_to_bounce;
+                self.scatter(i);
+            }
+            self.advance(i, time, supp);
+        }
+        self.time += 1.0;
+    }
+
+    fn advance(&mut self, i: usize, time: f64, supp: f64) {
+        let electron = &mut self.electrons[i];
+        electron.update(time, supp);
+
+        let flux = &mut self.flux[electron.species];
+        while electron.pos.x >= self.x_size {
+            electron.pos.x -= self.x_size;
+            flux.left += 1;
+        }
+        while electron.pos.x < 0.0 {
+            electron.pos.x += self.x_size;
+            flux.right += 1;
+        }
+        while electron.pos.y < 0.0 || electron.pos.y > self.y_size {
+            electron.pos.y = if electron.pos.y < 0.0 {
+                -electron.pos.y
+            } else {
+                2.0 * self.y_size - electron.pos.y
+            };
+            electron.vel.y = -electron.vel.y;
+            if let Some(state) = &mut electron.dirac {
+                state.momentum.y = -state.momentum.y;
+            }
+        }
+    }
+
+    fn scatter(&mut self, i: usize) {
+        let time_to_bounce = self.free_flight();
+        let electron = &mut self.electrons[i];
+        electron.vel = self.equilibrium.sample(electron.mass);
+        if let Some(state) = &mut electron.dirac {
+            state.momentum = electron.vel * electron.mass;
+            electron.vel = state.velocity();
+        }
+        electron.record_collision(CollisionKind::Ion);
+        electron.time_to_bounce = time_to_bounce;
+        self.scatterings += 1;
+    }
+
+    // Mean electron velocity along the field axis.
+    pub fn drift_velocity(&self) -> f64 {
+        if self.electrons.is_empty() {
+            return 0.0;
+        }
+        let sum = self
+            .electrons
+            .iter()
+            .fold(0.0, |acc, electron| acc + electron.vel.x);
+        sum / self.electrons.len() as f64
+    }
+
+    // Number of electrons per unit area.
+    pub fn electron_density(&self) -> f64 {
+        self.electrons.len() as f64 / (self.x_size * self.y_size)
+    }
+
+    // Mean velocity along the field axis of the carriers of one species.
+    pub fn species_drift_velocity(&self, species: usize) -> f64 {
+        let (sum, count) = self
+            .electrons
+            .iter()
+            .filter(|electron| electron.species == species)
+            .fold((0.0, 0), |(sum, count), electron| {
+                (sum + electron.vel.x, count + 1)
+            });
+        if count == 0 {
+            return 0.0;
+        }
+        sum / count as f64
+    }
+
+    // Mean kinetic energy of the carriers of one species.
+    pub fn mean_kinetic_energy(&self, species: usize) -> f64 {
+        let (sum, count) = self
+            .electrons
+            .iter()
+            .filter(|electron| electron.species == species)
+            .fold((0.0, 0), |(sum, count), electron| {
+                (sum + electron.kinetic_energy(), count + 1)
+            });
+        if count == 0 {
+            return 0.0;
+        }
+        sum / count as f64
+    }
+
+    // Drift velocity per unit field, positive for both electrons and holes.
+    pub fn mobility(&self, species: usize) -> f64 {
+        let field = field_from_acc(self.acc);
+        if field == 0.0 {
+            return 0.0;
+        }
+        self.species_drift_velocity(species) * self.species[species].charge.signum() / field
+    }
+
+    // Textbook Drude mobility |q| * tau / m the measured one converges to without drag.
+    pub fn drude_mobility(&self, species: usize) -> f64 {
+        let species = &self.species[species];
+        species.charge.abs() * self.tau / species.mass
+    }
+
+    // Charge carried per unit time in the +x direction by one species, counted at the periodic borders.
+    pub fn species_current(&self, species: usize) -> f64 {
+        if self.time == 0.0 {
+            return 0.0;
+        }
+        let flux = self.flux[species];
+        self.species[species].charge * (flux.left - flux.right) as f64 / self.time
+    }
+
+    // Sum of the contributions of every carrier species.
+    pub fn net_current(&self) -> f64 {
+        (0..self.species.len())
+            .map(|species| self.species_current(species))
+            .sum()
+    }
+
+    // Scatterings of all carriers pooled, the same counters `CrystalStructure` keeps.
+    pub fn collision_stats(&self) -> CollisionStats {
+        self.electrons
+            .iter()
+            .fold(CollisionStats::default(), |stats, electron| {
+                stats.merge(electron.collisions)
+            })
+    }
+
+    // Measured mean time between scatterings, pooled over every scattering like the structure's.
+    pub fn mean_free_time(&self) -> f64 {
+        self.collision_stats().mean_free_time()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::langevin::Langevin;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn drift_reaches_drude_value() {
+        seed_random(61);
+        let mut model = RelaxationTime::new(
+            400.0,
+            300.0,
+            5.0,
+            VelocityDistribution::Maxwell { temperature: 1.0 },
+            4000,
+        )
+        .unwrap();
+        let mut drift = 0.0;
+        for tick in 0..350 {
+            model.update(0.02, 0.0);
+            if tick >= 50 {
+                drift += model.drift_velocity() / 300.0;
+            }
+        }
+        // v = q * E * tau / m with q * E / m = acc.
+        assert!((drift - 0.1).abs() < 0.01);
+        assert_eq!(model.drude_mobility(0), 5.0);
+        assert!(model.species_current(0) < 0.0);
+    }
+
+    #[test]
+    fn free_flights_are_exponential() {
+        seed_random(62);
+        let model = RelaxationTime::new(
+            100.0,
+            100.0,
+            3.0,
+            VelocityDistribution::Monoenergetic { speed: 1.0 },
+            0,
+        )
+        .unwrap();
+        let n = 40_000;
+        let flights: Vec<f64> = (0..n).map(|_| model.free_flight()).collect();
+        let mean = flights.iter().sum::<f64>() / n as f64;
+        let beyond = flights.iter().filter(|&&t| t > 3.0).count() as f64 / n as f64;
+
+        assert!((mean - 3.0).abs() < 0.05);
+        assert!((beyond - (-1.0f64).exp()).abs() < 0.01);
+    }
+
+    #[test]
+    fn carriers_stay_in_box() {
+        seed_random(63);
+        let mut model = RelaxationTime::new(
+            50.0,
+            40.0,
+            20.0,
+            VelocityDistribution::Monoenergetic { speed: 3.0 },
+            100,
+        )
+        .unwrap();
+        // Long enough that the unfinished last flight of each carrier barely biases the pooled mean.
+        for _ in 0..1000 {
+            model.update(0.1, 0.0);
+        }
+        assert!(model
+            .electrons
+            .iter()
+            .all(|electron| (0.0..50.0).contains(&electron.pos.x)
+                && (0.0..=40.0).contains(&electron.pos.y)));
+        assert!((model.mean_free_time() - 20.0).abs() < 4.0);
+    }
+
+    #[test]
+    fn rejects_non_positive_tau() {
+        let equilibrium = VelocityDistribution::Monoenergetic { speed: 1.0 };
+        assert_eq!(
+            RelaxationTime::new(100.0, 100.0, 0.0, equilibrium, 10).err(),
+            Some(RelaxationError::InvalidTau(0.0))
+        );
+        assert!(RelaxationTime::new(100.0, 100.0, -2.0, equilibrium, 10).is_err());
+        assert!(RelaxationTime::new(100.0, 100.0, f64::NAN, equilibrium, 10).is_err());
+    }
+
+    #[test]
+    fn matching_pools_the_structure_collisions() {
+        seed_random(64);
+        let mut cs = CrystalStructure::new(300.0, 200.0, 60.0, 1.0, 10);
+        let equilibrium = VelocityDistribution::Maxwell { temperature: 1.0 };
+        assert_eq!(
+            RelaxationTime::matching(&cs, equilibrium).err(),
+            Some(RelaxationError::NoScatterings)
+        );
+
+        for _ in 0..30 {
+            cs.update(0.01, 0.0);
+        }
+        let model = RelaxationTime::matching(&cs, equilibrium).unwrap();
+        assert!(model.tau > 0.0);
+        assert_eq!(model.tau, cs.collision_stats().mean_free_time());
+        assert_eq!(model.electrons.len(), cs.electrons.len());
+    }
+
+    #[test]
+    fn scatterings_are_pooled_like_the_structure_collisions() {
+        seed_random(65);
+        let mut model = RelaxationTime::new(
+            100.0,
+            100.0,
+            2.0,
+            VelocityDistribution::Monoenergetic { speed: 1.0 },
+            500,
+        )
+        .unwrap();
+        for _ in 0..200 {
+            model.update(0.0, 0.0);
+        }
+        let stats = model.collision_stats();
+        assert_eq!(stats.ion.count, model.scatterings);
+        assert_eq!(stats.total(), model.scatterings);
+        assert_eq!(model.mean_free_time(), stats.mean_free_time());
+        assert!((model.mean_free_time() - 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn matching_keeps_dirac_and_tensor_carriers() {
+        seed_random(66);
+        let mut cs = CrystalStructure::new(300.0, 200.0, 60.0, 1.0, 0);
+        let mut graphene = Species::electron();
+        graphene.fermi_velocity = Some(2.0);
+        let dirac = cs.add_species(graphene);
+        let mut anisotropic = Species::electron();
+        anisotropic.mass_tensor = Some(na::Matrix2::new(1.0, 0.0, 0.0, 4.0));
+        let tensor = cs.add_species(anisotropic);
+        cs.add_carriers(dirac, 5, 1.0);
+        cs.add_carriers(tensor, 5, 1.0);
+        for _ in 0..40 {
+            cs.update(0.01, 0.0);
+        }
+
+        let model =
+            RelaxationTime::matching(&cs, VelocityDistribution::Maxwell { temperature: 1.0 })
+                .unwrap();
+        for (carrier, electron) in cs.electrons.iter().zip(model.electrons.iter()) {
+            let carrier = carrier.borrow();
+            assert_eq!(electron.vel, carrier.vel);
+            assert_eq!(
+                electron.dirac.map(|state| state.momentum),
+                carrier.dirac.map(|state| state.momentum)
+            );
+            assert_eq!(electron.mass_tensor, carrier.mass_tensor);
+            assert_eq!(electron.collisions.total(), 0);
+        }
+        assert!(model
+            .electrons
+            .iter()
+            .any(|electron| electron.dirac.is_some()));
+    }
+
+    #[test]
+    fn matching_rejects_langevin_noise() {
+        seed_random(67);
+        let mut cs = CrystalStructure::new(300.0, 200.0, 60.0, 1.0, 10);
+        for _ in 0..30 {
+            cs.update(0.01, 0.01);
+        }
+        cs.set_langevin(Some(Langevin::new(1.0, 1.0)));
+        assert_eq!(
+            RelaxationTime::matching(&cs, VelocityDistribution::Maxwell { temperature: 1.0 }).err(),
+            Some(RelaxationError::Unsupported("Langevin noise"))
+        );
+    }
+}
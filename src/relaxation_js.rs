@@ -0,0 +1,146 @@
+use js_sys::Array;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    crystal_structure_js::{collision_kind, CrystalStructureJs},
+    electron_js::ElectronJs,
+    placement::VelocityDistribution,
+    relaxation::RelaxationTime,
+    species::Species,
+};
+
+#[wasm_bindgen(js_name = RelaxationTime)]
+pub struct RelaxationTimeJs {
+    model: RelaxationTime,
+}
+
+#[wasm_bindgen(js_class = RelaxationTime)]
+impl RelaxationTimeJs {
+    // Velocities are redrawn from the Maxwellian at `temperature` after every scattering.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        x_size: f64,
+        y_size: f64,
+        tau: f64,
+        temperature: f64,
+        num_electrons: i32,
+    ) -> Result<RelaxationTimeJs, JsError> {
+        Ok(RelaxationTimeJs {
+            model: RelaxationTime::new(
+                x_size,
+                y_size,
+                tau,
+                VelocityDistribution::Maxwell { temperature },
+                num_electrons,
+            )?,
+        })
+    }
+
+    // Velocities are redrawn with a fixed speed, like the initial electrons of a crystal structure.
+    pub fn monoenergetic(
+        x_size: f64,
+        y_size: f64,
+        tau: f64,
+        speed: f64,
+        num_electrons: i32,
+    ) -> Result<RelaxationTimeJs, JsError> {
+        Ok(RelaxationTimeJs {
+            model: RelaxationTime::new(
+                x_size,
+                y_size,
+                tau,
+                VelocityDistribution::Monoenergetic { speed },
+                num_electrons,
+            )?,
+        })
+    }
+
+    // Same box, carriers and field as the structure, with its measured mean free time.
+    // Fails until the structure has recorded a collision.
+    pub fn matching(
+        cs: &CrystalStructureJs,
+        temperature: f64,
+    ) -> Result<RelaxationTimeJs, JsError> {
+        Ok(RelaxationTimeJs {
+            model: RelaxationTime::matching(&cs.cs, VelocityDistribution::Maxwell { temperature })?,
+        })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tau(&self) -> f64 {
+        self.model.tau
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn scatterings(&self) -> i32 {
+        self.model.scatterings
+    }
+
+    pub fn add_species(&mut self, name: &str, mass: f64, charge: f64) -> usize {
+        self.model
+            .add_species(Species::new(name, mass, charge, 0.0))
+    }
+
+    pub fn add_carriers(&mut self, species: usize, count: i32) {
+        self.model.add_carriers(species, count);
+    }
+
+    pub fn update(&mut self, acc: f64, supp: f64) {
+        self.model.update(acc, supp);
+    }
+
+    pub fn drift_velocity(&self) -> f64 {
+        self.model.drift_velocity()
+    }
+
+    pub fn species_drift_velocity(&self, species: usize) -> f64 {
+        self.model.species_drift_velocity(species)
+    }
+
+    pub fn mobility(&self, species: usize) -> f64 {
+        self.model.mobility(species)
+    }
+
+    pub fn drude_mobility(&self, species: usize) -> f64 {
+        self.model.drude_mobility(species)
+    }
+
+    pub fn species_current(&self, species: usize) -> f64 {
+        self.model.species_current(species)
+    }
+
+    pub fn net_current(&self) -> f64 {
+        self.model.net_current()
+    }
+
+    pub fn mean_kinetic_energy(&self, species: usize) -> f64 {
+        self.model.mean_kinetic_energy(species)
+    }
+
+    // Pooled mean free time under the name the app has always used.
+    pub fn avg_ticks_between_bounces(&self) -> f64 {
+        self.model.mean_free_time()
+    }
+
+    // Same counters as `CrystalStructure.collision_count`, every scattering counts as "ion".
+    pub fn collision_count(&self, kind: &str) -> Result<i32, JsError> {
+        Ok(self
+            .model
+            .collision_stats()
+            .get(collision_kind(kind)?)
+            .count)
+    }
+
+    // Measured mean free time, pooled like `CrystalStructure.combined_mean_free_time`.
+    pub fn combined_mean_free_time(&self) -> f64 {
+        self.model.mean_free_time()
+    }
+
+    pub fn get_electrons(&self) -> Array {
+        self.model
+            .electrons
+            .iter()
+            .map(|electron| JsValue::from(ElectronJs::new(electron)))
+            .collect()
+    }
+}
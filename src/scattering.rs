@@ -0,0 +1,279 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+use crate::cfg::INIT_ITERATIONS;
+use crate::electron::Electron;
+use crate::utils::{random, random_normal};
+
+use std::f64::consts::PI;
+
+// Outgoing velocity of a carrier leaving an ion.
+// `vel` is the incoming velocity relative to the ion, `normal` the unit vector from the ion
+// centre to the carrier and `mass` the (reduced) mass of the pair.
+// The returned velocity must not point into the ion.
+pub trait ScatteringLaw {
+    fn scatter(&self, vel: Vector2<f64>, normal: Vector2<f64>, mass: f64) -> Vector2<f64>;
+}
+
+// Mirror reflection about the ion normal, the behaviour of `Ion::bounce`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Specular;
+
+impl ScatteringLaw for Specular {
+    fn scatter(&self, vel: Vector2<f64>, normal: Vector2<f64>, _mass: f64) -> Vector2<f64> {
+        vel - normal * 2.0 * vel.dot(&normal)
+    }
+}
+
+// Keeps the speed and draws a new direction uniformly from the outward half-plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IsotropicRandom;
+
+impl ScatteringLaw for IsotropicRandom {
+    fn scatter(&self, vel: Vector2<f64>, normal: Vector2<f64>, _mass: f64) -> Vector2<f64> {
+        outward_direction(normal) * vel.magnitude()
+    }
+}
+
+// Keeps the speed and deflects the direction by an angle from a wrapped Cauchy distribution
+// around the incoming direction. `anisotropy` is the mean cosine of the deflection:
+// 0 gives a uniform angle, values close to 1 favour grazing forward scattering.
+// Deflections into the ion are redrawn, falling back to specular reflection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ForwardPeaked {
+    pub anisotropy: f64,
+}
+
+impl ForwardPeaked {
+    pub fn new(anisotropy: f64) -> Self {
+        ForwardPeaked { anisotropy }
+    }
+
+    pub fn sample_deflection(&self) -> f64 {
+        let g = self.anisotropy;
+        2.0 * (((1.0 - g) / (1.0 + g)) * (PI * (random() - 0.5)).tan()).atan()
+    }
+}
+
+impl ScatteringLaw for ForwardPeaked {
+    fn scatter(&self, vel: Vector2<f64>, normal: Vector2<f64>, mass: f64) -> Vector2<f64> {
+        for _ in 0..INIT_ITERATIONS {
+            let (sin, cos) = self.sample_deflection().sin_cos();
+            let out = Vector2::new(vel.x * cos - vel.y * sin, vel.x * sin + vel.y * cos);
+            if out.dot(&normal) > 0.0 {
+                return out;
+            }
+        }
+        Specular.scatter(vel, normal, mass)
+    }
+}
+
+// Reverses the normal velocity component scaled by `coefficient`, the tangential one is kept.
+// A coefficient of 1 is specular, smaller values lose energy to the lattice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Restitution {
+    pub coefficient: f64,
+}
+
+impl Restitution {
+    pub fn new(coefficient: f64) -> Self {
+        Restitution { coefficient }
+    }
+}
+
+impl ScatteringLaw for Restitution {
+    fn scatter(&self, vel: Vector2<f64>, normal: Vector2<f64>, _mass: f64) -> Vector2<f64> {
+        vel - normal * (1.0 + self.coefficient) * vel.dot(&normal)
+    }
+}
+
+// Forgets the incoming velocity and leaves with a speed drawn from the 2D Maxwell distribution
+// at the lattice `temperature`, in a random outward direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thermalising {
+    pub temperature: f64,
+}
+
+impl Thermalising {
+    pub fn new(temperature: f64) -> Self {
+        Thermalising { temperature }
+    }
+}
+
+impl ScatteringLaw for Thermalising {
+    fn scatter(&self, _vel: Vector2<f64>, normal: Vector2<f64>, mass: f64) -> Vector2<f64> {
+        let sigma = (self.temperature.max(0.0) / mass).sqrt();
+        let speed = Vector2::new(random_normal(), random_normal()).magnitude() * sigma;
+        outward_direction(normal) * speed
+    }
+}
+
+// How carriers interact when they meet.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ElectronScattering {
+    // Carriers pass through each other, only electron-hole pairs that may recombine still meet.
+    Disabled,
+    // Elastic hard-disk collision at contact.
+    #[default]
+    HardDisk,
+    // Collision at a centre distance of `cross_section` (a length in 2D) that rotates the
+    // relative velocity by a random angle, conserving momentum and energy.
+    MonteCarlo {
+        cross_section: f64,
+    },
+    // Artificial contact event that redraws both directions at fixed speeds.
+    // Energy of each carrier is kept but momentum is not, so it relaxes the current.
+    MomentumRelaxing,
+}
+
+impl ElectronScattering {
+    // Centre distance at which two carriers interact.
+    pub fn contact_distance(&self, first: &Electron, second: &Electron) -> f64 {
+        match self {
+            ElectronScattering::MonteCarlo { cross_section } => *cross_section,
+            _ => first.radius + second.radius,
+        }
+    }
+
+    pub fn conserves_momentum(&self) -> bool {
+        *self != ElectronScattering::MomentumRelaxing
+    }
+}
+
+// Rotates the relative velocity of two carriers to a random direction in which they separate,
+// keeping the centre of mass velocity and the relative speed.
+pub fn random_angle_collision(first: &mut Electron, second: &mut Electron) {
+    let normal = (first.pos - second.pos).normalize();
+    let total_mass = first.mass + second.mass;
+    let centre_vel = (first.vel * first.mass + second.vel * second.mass) / total_mass;
+    let rel_vel = outward_direction(normal) * (first.vel - second.vel).magnitude();
+    first.vel = centre_vel + rel_vel * (second.mass / total_mass);
+    second.vel = centre_vel - rel_vel * (first.mass / total_mass);
+}
+
+// Sends both carriers off in random separating directions without changing their speeds.
+pub fn momentum_relaxing_collision(first: &mut Electron, second: &mut Electron) {
+    let normal = (first.pos - second.pos).normalize();
+    first.vel = outward_direction(normal) * first.vel.magnitude();
+    second.vel = outward_direction(-normal) * second.vel.magnitude();
+}
+
+// Unit vector at a uniform angle within a quarter turn of `normal`.
+fn outward_direction(normal: Vector2<f64>) -> Vector2<f64> {
+    let angle = normal.y.atan2(normal.x) + (random() - 0.5) * PI;
+    Vector2::new(angle.cos(), angle.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_random;
+
+    fn incoming() -> (Vector2<f64>, Vector2<f64>) {
+        let normal = Vector2::new(-1.0, 1.0).normalize();
+        (Vector2::new(3.0, -1.0), normal)
+    }
+
+    #[test]
+    fn specular_reverses_normal_component() {
+        let vel = Specular.scatter(Vector2::new(2.0, 1.0), Vector2::new(-1.0, 0.0), 1.0);
+        assert_eq!(vel, Vector2::new(-2.0, 1.0));
+    }
+
+    #[test]
+    fn restitution_loses_normal_energy() {
+        let vel =
+            Restitution::new(0.5).scatter(Vector2::new(2.0, 1.0), Vector2::new(-1.0, 0.0), 1.0);
+        assert_eq!(vel, Vector2::new(-1.0, 1.0));
+    }
+
+    #[test]
+    fn random_laws_leave_the_ion() {
+        seed_random(2);
+        let (vel, normal) = incoming();
+        let laws: Vec<Box<dyn ScatteringLaw>> = vec![
+            Box::new(IsotropicRandom),
+            Box::new(ForwardPeaked::new(0.9)),
+            Box::new(Thermalising::new(1.0)),
+        ];
+        for law in laws.iter() {
+            for _ in 0..100 {
+                assert!(law.scatter(vel, normal, 1.0).dot(&normal) >= 0.0);
+            }
+        }
+        for _ in 0..100 {
+            let out = IsotropicRandom.scatter(vel, normal, 1.0);
+            assert!((out.magnitude() - vel.magnitude()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn forward_peaked_mean_cosine() {
+        seed_random(6);
+        let law = ForwardPeaked::new(0.6);
+        let n = 20_000;
+        let mean = (0..n).map(|_| law.sample_deflection().cos()).sum::<f64>() / n as f64;
+        assert!((mean - 0.6).abs() < 0.02);
+    }
+
+    #[test]
+    fn thermalising_equipartition() {
+        seed_random(9);
+        let law = Thermalising::new(2.0);
+        let n = 20_000;
+        let energy = (0..n)
+            .map(|_| {
+                0.5 * 4.0
+                    * law
+                        .scatter(Vector2::new(1.0, 0.0), Vector2::new(1.0, 0.0), 4.0)
+                        .magnitude_squared()
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!((energy - 2.0).abs() < 0.1);
+    }
+
+    fn pair() -> (Electron, Electron) {
+        let first = Electron::new(
+            Vector2::new(100.0, 100.0),
+            Vector2::new(2.0, 1.0),
+            Vector2::new(0.0, 0.0),
+        );
+        let mut second = Electron::new(
+            Vector2::new(105.0, 103.0),
+            Vector2::new(-1.0, 0.5),
+            Vector2::new(0.0, 0.0),
+        );
+        second.mass = 3.0;
+        (first, second)
+    }
+
+    #[test]
+    fn random_angle_conserves_momentum_and_energy() {
+        seed_random(12);
+        let (mut first, mut second) = pair();
+        let momentum = first.vel * first.mass + second.vel * second.mass;
+        let energy =
+            first.mass * first.vel.norm_squared() + second.mass * second.vel.norm_squared();
+        random_angle_collision(&mut first, &mut second);
+
+        let momentum_after = first.vel * first.mass + second.vel * second.mass;
+        let energy_after =
+            first.mass * first.vel.norm_squared() + second.mass * second.vel.norm_squared();
+        assert!((momentum_after - momentum).norm() < 1e-12);
+        assert!((energy_after - energy).abs() < 1e-12);
+        assert!((first.vel - second.vel).dot(&(first.pos - second.pos)) > 0.0);
+    }
+
+    #[test]
+    fn momentum_relaxing_keeps_speeds() {
+        seed_random(13);
+        let (mut first, mut second) = pair();
+        let speeds = (first.vel.magnitude(), second.vel.magnitude());
+        momentum_relaxing_collision(&mut first, &mut second);
+
+        assert!((first.vel.magnitude() - speeds.0).abs() < 1e-12);
+        assert!((second.vel.magnitude() - speeds.1).abs() < 1e-12);
+        assert!((first.vel - second.vel).dot(&(first.pos - second.pos)) > 0.0);
+    }
+}
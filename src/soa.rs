@@ -0,0 +1,565 @@
+use nalgebra::Vector2;
+use wide::f64x4;
+
+use crate::border::BorderType;
+use crate::cfg::EPSILON;
+use crate::collidable::Collidable;
+use crate::crystal_structure::CrystalStructure;
+use crate::electron::Electron;
+use crate::ion::{Ion, ORBIT_HORIZON};
+use crate::utils::calc_time_to_collision;
+
+// Width of the SIMD batches. `wide` lowers f64x4 to SSE/AVX natively. On wasm it uses two v128
+// lanes only when built with `simd128` (see the README), otherwise it falls back to scalar code.
+const LANES: usize = 4;
+
+// Relative slack of the ion lower bound, keeps rounding from pruning an ion that is hit first.
+const BOUND_SLACK: f64 = 1e-9;
+
+// Partner of a predicted collision, indices into the wall and ion lists.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    Wall(usize),
+    Ion(usize),
+    None,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Prediction {
+    pub time: f64,
+    pub target: Target,
+}
+
+// Carriers stored column by column so that the kernels load four of them at once.
+// Columns are padded to a multiple of `LANES`, padding carriers stand still far outside.
+pub struct ParticleStore {
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+    pub vx: Vec<f64>,
+    pub vy: Vec<f64>,
+    pub ax: Vec<f64>,
+    pub ay: Vec<f64>,
+    pub radius: Vec<f64>,
+    len: usize,
+}
+
+// Ions in the same column layout, padded with NaN positions that fail every comparison.
+pub struct IonList {
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+    pub vx: Vec<f64>,
+    pub vy: Vec<f64>,
+    pub ax: Vec<f64>,
+    pub ay: Vec<f64>,
+    pub radius: Vec<f64>,
+    // Copies of the mobile ions, their orbits are predicted by the scalar `Ion` code.
+    pub orbits: Vec<Option<Ion>>,
+    len: usize,
+}
+
+// Border lines `a x + b y = c` in the order of `CrystalStructure::borders`.
+// Vertical ones are the periodic x borders, `width` is the period.
+pub struct WallList {
+    pub walls: Vec<(f64, f64, f64, BorderType)>,
+    pub width: f64,
+}
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(LANES) * LANES
+}
+
+fn load(column: &[f64], start: usize) -> f64x4 {
+    f64x4::new([
+        column[start],
+        column[start + 1],
+        column[start + 2],
+        column[start + 3],
+    ])
+}
+
+impl ParticleStore {
+    pub fn new() -> ParticleStore {
+        ParticleStore {
+            x: Vec::new(),
+            y: Vec::new(),
+            vx: Vec::new(),
+            vy: Vec::new(),
+            ax: Vec::new(),
+            ay: Vec::new(),
+            radius: Vec::new(),
+            len: 0,
+        }
+    }
+
+    // Copies the carriers of the structure. Dirac carriers and orbiting ones are not supported,
+    // the kernels assume parabolic paths.
+    pub fn from_structure(cs: &CrystalStructure) -> ParticleStore {
+        let mut store = ParticleStore::new();
+        cs.electrons
+            .iter()
+            .for_each(|electron| store.push(&electron.borrow()));
+        store
+    }
+
+    pub fn push(&mut self, electron: &Electron) {
+        self.x.truncate(self.len);
+        self.y.truncate(self.len);
+        self.vx.truncate(self.len);
+        self.vy.truncate(self.len);
+        self.ax.truncate(self.len);
+        self.ay.truncate(self.len);
+        self.radius.truncate(self.len);
+
+        self.x.push(electron.pos.x);
+        self.y.push(electron.pos.y);
+        self.vx.push(electron.vel.x);
+        self.vy.push(electron.vel.y);
+        self.ax.push(electron.acc.x);
+        self.ay.push(electron.acc.y);
+        self.radius.push(electron.radius);
+        self.len += 1;
+
+        let padded = padded(self.len);
+        self.x.resize(padded, f64::MAX);
+        self.y.resize(padded, f64::MAX);
+        self.vx.resize(padded, 0.0);
+        self.vy.resize(padded, 0.0);
+        self.ax.resize(padded, 0.0);
+        self.ay.resize(padded, 0.0);
+        self.radius.resize(padded, 0.0);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Earliest wall or ion collision of every carrier.
+    // Candidates and ties are resolved like `CrystalStructure::update_collidables`: walls before
+    // ions, the first of equal times wins.
+    pub fn predict(&self, ions: &IonList, walls: &WallList) -> Vec<Prediction> {
+        let mut predictions = self.predict_walls(walls);
+        self.predict_ions(ions, &mut predictions);
+        predictions
+    }
+
+    // Wall kernel, four carriers per batch against one wall at a time.
+    pub fn predict_walls(&self, walls: &WallList) -> Vec<Prediction> {
+        let mut predictions = Vec::with_capacity(self.x.len());
+        let zero = f64x4::splat(0.0);
+        for start in (0..self.x.len()).step_by(LANES) {
+            let (x, y) = (load(&self.x, start), load(&self.y, start));
+            let (vx, vy) = (load(&self.vx, start), load(&self.vy, start));
+            let (ax, ay) = (load(&self.ax, start), load(&self.ay, start));
+            let radius = load(&self.radius, start);
+
+            let mut best = f64x4::splat(f64::INFINITY);
+            let mut best_wall = [usize::MAX; LANES];
+            for (i, &(a, b, c, border_type)) in walls.walls.iter().enumerate() {
+                // Same direction filter as `CrystalStructure::filter_border`.
+                let inner = matches!(border_type, BorderType::Inner);
+                let (mut towards, outward) = (f64x4::splat(0.0), inner && c > 0.0);
+                let inward = !inner && c == 0.0;
+                if a == 1.0 && outward {
+                    towards |= vx.simd_gt(zero);
+                }
+                if b == 1.0 && outward {
+                    towards |= vy.simd_gt(zero);
+                }
+                if a == 1.0 && inward {
+                    towards |= vx.simd_lt(zero);
+                }
+                if b == 1.0 && inward {
+                    towards |= vy.simd_lt(zero);
+                }
+                if !towards.any() {
+                    continue;
+                }
+
+                // Same gap as `Border::gap`.
+                let dist = if a == 1.0 && b == 0.0 {
+                    if inner {
+                        f64x4::splat(walls.width) + radius - x
+                    } else {
+                        x + radius - f64x4::splat(c)
+                    }
+                } else {
+                    let pos = x * a + y * b;
+                    if inner {
+                        f64x4::splat(c) - radius - pos
+                    } else {
+                        pos - radius - f64x4::splat(c)
+                    }
+                };
+                let sign = if inner { 1.0 } else { -1.0 };
+                let vel = (vx * a + vy * b) * sign;
+                let acc = (ax * a + ay * b) * sign;
+
+                let time = towards.select(first_positive_root(dist, vel, acc), best);
+                let earlier = time.simd_lt(best).to_bitmask();
+                best = best.min(time);
+                for (lane, wall) in best_wall.iter_mut().enumerate() {
+                    if earlier & (1 << lane) != 0 {
+                        *wall = i;
+                    }
+                }
+            }
+
+            best.to_array()
+                .iter()
+                .zip(best_wall.iter())
+                .for_each(|(&time, &wall)| {
+                    predictions.push(Prediction {
+                        time,
+                        target: if wall == usize::MAX {
+                            Target::None
+                        } else {
+                            Target::Wall(wall)
+                        },
+                    })
+                });
+        }
+        predictions.truncate(self.len);
+        predictions
+    }
+
+    // Ion kernel, one carrier against four ions per batch.
+    // Relative paths without acceleration are straight and the contact quadratic is solved for the
+    // whole batch. Curved ones get a lower bound on the contact time and the exact quartic is only
+    // solved for ions that may still beat the current prediction. Mobile ions within reach go
+    // through the scalar orbit prediction.
+    pub fn predict_ions(&self, ions: &IonList, predictions: &mut [Prediction]) {
+        let zero = f64x4::splat(0.0);
+        for (i, prediction) in predictions.iter_mut().enumerate().take(self.len) {
+            let mut electron = Electron::new(
+                Vector2::new(self.x[i], self.y[i]),
+                Vector2::new(self.vx[i], self.vy[i]),
+                Vector2::new(self.ax[i], self.ay[i]),
+            );
+            let radius = self.radius[i];
+            electron.radius = radius;
+            let speed = electron.vel.magnitude();
+            let (x, y) = (f64x4::splat(electron.pos.x), f64x4::splat(electron.pos.y));
+            let (vx, vy) = (f64x4::splat(electron.vel.x), f64x4::splat(electron.vel.y));
+            let (ax, ay) = (f64x4::splat(electron.acc.x), f64x4::splat(electron.acc.y));
+            let diagonal = electron.vel.x != 0.0 && electron.vel.y != 0.0;
+            let (right, up) = (electron.vel.x > 0.0, electron.vel.y > 0.0);
+
+            for start in (0..ions.x.len()).step_by(LANES) {
+                let (ix, iy) = (load(&ions.x, start), load(&ions.y, start));
+                let reach = load(&ions.radius, start) + f64x4::splat(radius);
+
+                // Same quadrant filter as `CrystalStructure::filter_ion`, carriers moving along an
+                // axis are rare and use the scalar one.
+                let candidate = if diagonal {
+                    let ahead_x = if right {
+                        (x - reach).simd_le(ix)
+                    } else {
+                        (x + reach).simd_ge(ix)
+                    };
+                    let ahead_y = if up {
+                        (y - reach).simd_le(iy)
+                    } else {
+                        (y + reach).simd_ge(iy)
+                    };
+                    (ahead_x & ahead_y).to_bitmask()
+                } else if speed == 0.0 {
+                    0
+                } else {
+                    (0..LANES).fold(0, |mask, lane| {
+                        let j = start + lane;
+                        let hit =
+                            j < ions.len && CrystalStructure::filter_ion(&electron, &ions.ion(j));
+                        mask | ((hit as u32) << lane)
+                    })
+                };
+                let mobile = ions.mobile_mask(start);
+                for lane in 0..LANES {
+                    let orbit = match &ions.orbits[start + lane] {
+                        Some(orbit) if mobile & (1 << lane) != 0 => orbit,
+                        _ => continue,
+                    };
+                    if !orbit.within_reach(&electron, ORBIT_HORIZON) {
+                        continue;
+                    }
+                    let time = orbit.calc_time_to_collision(&electron);
+                    if time < prediction.time {
+                        *prediction = Prediction {
+                            time,
+                            target: Target::Ion(start + lane),
+                        };
+                    }
+                }
+                let candidate = candidate & !mobile;
+                if candidate == 0 {
+                    continue;
+                }
+
+                let (dx, dy) = (ix - x, iy - y);
+                let dvx = load(&ions.vx, start) - vx;
+                let dvy = load(&ions.vy, start) - vy;
+                let dax = load(&ions.ax, start) - ax;
+                let day = load(&ions.ay, start) - ay;
+                let straight = (dax.simd_eq(zero) & day.simd_eq(zero)).to_bitmask();
+
+                let times = if candidate & straight != 0 {
+                    straight_contact(dx, dy, dvx, dvy, reach).to_array()
+                } else {
+                    [f64::INFINITY; LANES]
+                };
+                let curved = candidate & !straight & 0b1111;
+                let reachable = if curved != 0 {
+                    // The gap |d| - R closes at most at |dv| + |da| t.
+                    let gap = (dx * dx + dy * dy).sqrt() - reach;
+                    let closing = (dvx * dvx + dvy * dvy).sqrt();
+                    let pull = (dax * dax + day * day).sqrt();
+                    let bound =
+                        gap * 2.0 / (closing + (closing * closing + pull * gap * 2.0).sqrt());
+                    let bound = gap.simd_gt(zero).select(bound, zero);
+                    let limit = f64x4::splat(prediction.time * (1.0 + BOUND_SLACK) + EPSILON);
+                    curved & bound.simd_lt(limit).to_bitmask()
+                } else {
+                    0
+                };
+
+                for (lane, &time) in times.iter().enumerate() {
+                    let j = start + lane;
+                    let time = if candidate & straight & (1 << lane) != 0 {
+                        time
+                    } else if reachable & (1 << lane) != 0 {
+                        calc_time_to_collision(
+                            electron.pos,
+                            electron.vel,
+                            electron.acc,
+                            Vector2::new(ions.x[j], ions.y[j]),
+                            Vector2::new(ions.vx[j], ions.vy[j]),
+                            Vector2::new(ions.ax[j], ions.ay[j]),
+                            ions.radius[j] + radius,
+                        )
+                    } else {
+                        continue;
+                    };
+                    if time < prediction.time {
+                        *prediction = Prediction {
+                            time,
+                            target: Target::Ion(j),
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for ParticleStore {
+    fn default() -> Self {
+        ParticleStore::new()
+    }
+}
+
+impl IonList {
+    pub fn from_structure(cs: &CrystalStructure) -> IonList {
+        let len = cs.ions.len();
+        let mut list = IonList {
+            x: Vec::with_capacity(padded(len)),
+            y: Vec::with_capacity(padded(len)),
+            vx: Vec::with_capacity(padded(len)),
+            vy: Vec::with_capacity(padded(len)),
+            ax: Vec::with_capacity(padded(len)),
+            ay: Vec::with_capacity(padded(len)),
+            radius: Vec::with_capacity(padded(len)),
+            orbits: Vec::with_capacity(padded(len)),
+            len,
+        };
+        cs.ions.iter().for_each(|ion| {
+            let ion = ion.borrow();
+            list.x.push(ion.pos.x);
+            list.y.push(ion.pos.y);
+            list.vx.push(ion.vel.x);
+            list.vy.push(ion.vel.y);
+            list.ax.push(ion.acc.x);
+            list.ay.push(ion.acc.y);
+            list.radius.push(ion.radius);
+            list.orbits.push(Some(*ion).filter(Ion::is_mobile));
+        });
+        list.x.resize(padded(len), f64::NAN);
+        list.y.resize(padded(len), f64::NAN);
+        list.vx.resize(padded(len), 0.0);
+        list.vy.resize(padded(len), 0.0);
+        list.ax.resize(padded(len), 0.0);
+        list.ay.resize(padded(len), 0.0);
+        list.radius.resize(padded(len), 0.0);
+        list.orbits.resize(padded(len), None);
+        list
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn mobile_mask(&self, start: usize) -> u32 {
+        (0..LANES).fold(0, |mask, lane| {
+            mask | ((self.orbits[start + lane].is_some() as u32) << lane)
+        })
+    }
+
+    // Rebuilds an ion for the scalar filter, which only reads its position and radius.
+    fn ion(&self, j: usize) -> Ion {
+        let mut ion = Ion::new(Vector2::new(self.x[j], self.y[j]));
+        ion.radius = self.radius[j];
+        ion
+    }
+}
+
+impl WallList {
+    pub fn from_structure(cs: &CrystalStructure) -> WallList {
+        WallList {
+            walls: cs
+                .borders
+                .iter()
+                .map(|border| {
+                    let border = border.borrow();
+                    (border.a, border.b, border.c, border.border_type)
+                })
+                .collect(),
+            width: cs.x_size,
+        }
+    }
+}
+
+// Smallest root above `EPSILON` of acc/2 t^2 + vel t - dist, lane by lane as in
+// `calc_time_to_border_collision`. Lanes without one are infinite.
+fn first_positive_root(dist: f64x4, vel: f64x4, acc: f64x4) -> f64x4 {
+    let zero = f64x4::splat(0.0);
+    let eps = f64x4::splat(EPSILON);
+    let infinity = f64x4::splat(f64::INFINITY);
+    let keep = |t: f64x4| t.simd_gt(eps).select(t, infinity);
+
+    let linear = keep(dist / vel);
+
+    let half = acc * 0.5;
+    let root = (vel * vel + half * dist * 4.0).sqrt();
+    // Cancellation free pair of roots: q / half and -dist / q.
+    let q = vel.simd_lt(zero).select(root - vel, -(vel + root)) * 0.5;
+    let (first, second) = (keep(q / half), keep(-dist / q));
+    let quadratic = first.min(second);
+    // A negative discriminant gives NaN roots, those compare false and stay infinite.
+    acc.simd_eq(zero).select(linear, quadratic)
+}
+
+// First contact above `EPSILON` of bodies at offset d closing at the constant relative velocity
+// dv, as the quadratic branch of `calc_time_to_collision`. Lanes without one are infinite.
+fn straight_contact(dx: f64x4, dy: f64x4, dvx: f64x4, dvy: f64x4, reach: f64x4) -> f64x4 {
+    let zero = f64x4::splat(0.0);
+    let eps = f64x4::splat(EPSILON);
+    let infinity = f64x4::splat(f64::INFINITY);
+    let keep = |t: f64x4| t.simd_gt(eps).select(t, infinity);
+
+    // |d + dv t|^2 = R^2 as a t^2 + 2 h t + c = 0.
+    let a = dvx * dvx + dvy * dvy;
+    let h = dvx * dx + dvy * dy;
+    let c = dx * dx + dy * dy - reach * reach;
+    let root = (h * h - a * c).sqrt();
+    let q = h.simd_lt(zero).select(root - h, -(h + root));
+    keep(q / a).min(keep(c / q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collidables::Collidables;
+    use crate::scattering::ElectronScattering;
+    use crate::utils::seed_random;
+    use std::rc::Rc;
+
+    fn structure() -> CrystalStructure {
+        seed_random(29);
+        let mut cs = CrystalStructure::new(800.0, 600.0, 100.0, 1.0, 0);
+        cs.electron_scattering = ElectronScattering::Disabled;
+        cs.add_carriers(0, 150, 1.0);
+        cs
+    }
+
+    fn serial(cs: &CrystalStructure) -> Vec<(f64, Target)> {
+        cs.electrons
+            .iter()
+            .map(|electron| {
+                let electron = electron.borrow();
+                let target = match &electron.collidable {
+                    Collidables::Border(border) => {
+                        let border = border.upgrade().unwrap();
+                        let found = cs.borders.iter().position(|b| Rc::ptr_eq(b, &border));
+                        Target::Wall(found.unwrap())
+                    }
+                    Collidables::Ion(ion) => {
+                        let ion = ion.upgrade().unwrap();
+                        Target::Ion(cs.ions.iter().position(|i| Rc::ptr_eq(i, &ion)).unwrap())
+                    }
+                    _ => Target::None,
+                };
+                (electron.time_to_bounce, target)
+            })
+            .collect()
+    }
+
+    fn assert_matches(cs: &CrystalStructure) {
+        let store = ParticleStore::from_structure(cs);
+        let predictions =
+            store.predict(&IonList::from_structure(cs), &WallList::from_structure(cs));
+        assert_eq!(predictions.len(), cs.electrons.len());
+        for (prediction, (time, target)) in predictions.iter().zip(serial(cs)) {
+            assert_eq!(prediction.target, target);
+            assert!((prediction.time - time).abs() <= 1e-9 * time.max(1.0));
+        }
+    }
+
+    #[test]
+    fn matches_serial_prediction_without_field() {
+        assert_matches(&structure());
+    }
+
+    #[test]
+    fn matches_serial_prediction_in_field() {
+        let mut cs = structure();
+        cs.update(0.2, 0.0);
+        cs.update_collidables();
+        assert_matches(&cs);
+    }
+
+    #[test]
+    fn straight_contacts() {
+        let times = straight_contact(
+            f64x4::new([20.0, 20.0, 20.0, 5.0]),
+            f64x4::new([0.0, 20.0, 0.0, 0.0]),
+            f64x4::new([-2.0, -1.0, 2.0, 0.0]),
+            f64x4::new([0.0, 0.0, 0.0, 0.0]),
+            f64x4::splat(10.0),
+        )
+        .to_array();
+        assert_eq!(times[0], 5.0);
+        assert_eq!(times[1], f64::INFINITY);
+        assert_eq!(times[2], f64::INFINITY);
+        assert_eq!(times[3], f64::INFINITY);
+    }
+
+    #[test]
+    fn wall_roots() {
+        let times = first_positive_root(
+            f64x4::new([10.0, 10.0, 10.0, 1.0]),
+            f64x4::new([2.0, 0.0, -1.0, -1.0]),
+            f64x4::new([0.0, 5.0, 0.5, -0.1]),
+        )
+        .to_array();
+        assert_eq!(times[0], 5.0);
+        assert!((times[1] - 2.0).abs() < 1e-12);
+        assert!((times[2] - (1.0 + 11.0_f64.sqrt()) * 2.0).abs() < 1e-12);
+        assert_eq!(times[3], f64::INFINITY);
+    }
+}
@@ -0,0 +1,161 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+use std::collections::HashMap;
+
+use crate::utils::random;
+
+// Fermi-Dirac occupation of a 2D carrier gas, k_B = 1 in simulation units.
+// The 2D density of states is constant, so carrier energies are distributed like the occupation itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FermiDirac {
+    pub temperature: f64,
+    pub fermi_energy: f64,
+}
+
+impl FermiDirac {
+    pub fn new(temperature: f64, fermi_energy: f64) -> Self {
+        FermiDirac {
+            temperature,
+            fermi_energy,
+        }
+    }
+
+    pub fn occupation(&self, energy: f64) -> f64 {
+        if self.temperature <= 0.0 {
+            return if energy < self.fermi_energy { 1.0 } else { 0.0 };
+        }
+        1.0 / (((energy - self.fermi_energy) / self.temperature).exp() + 1.0)
+    }
+
+    // Inverts N(E) = T * (ln(1 + e^(mu / T)) - ln(1 + e^((mu - E) / T))), the number of states
+    // below E, which gives E = mu - T * ln((1 + e^(mu / T))^(1 - u) - 1) for a uniform u.
+    // At zero temperature the Fermi disk is filled uniformly in energy.
+    pub fn sample_energy(&self) -> f64 {
+        let u = random();
+        if self.temperature <= 0.0 {
+            return u * self.fermi_energy.max(0.0);
+        }
+        let t = self.temperature;
+        let filled = (1.0 - u) * softplus(self.fermi_energy / t);
+        (self.fermi_energy - t * ln_exp_m1(filled)).max(0.0)
+    }
+}
+
+// ln(1 + e^x) without overflow.
+fn softplus(x: f64) -> f64 {
+    if x > 30.0 {
+        x + (-x).exp().ln_1p()
+    } else {
+        x.exp().ln_1p()
+    }
+}
+
+// ln(e^x - 1) for x > 0 without overflow.
+fn ln_exp_m1(x: f64) -> f64 {
+    if x > 30.0 {
+        x + (-(-x).exp()).ln_1p()
+    } else {
+        x.exp_m1().ln()
+    }
+}
+
+// Pauli exclusion on a square grid of momentum cells of side `cell_size`,
+// each holding at most `degeneracy` carriers (2 for spin).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PauliBlocking {
+    pub cell_size: f64,
+    pub degeneracy: u32,
+}
+
+impl PauliBlocking {
+    pub fn new(cell_size: f64, degeneracy: u32) -> Self {
+        PauliBlocking {
+            cell_size,
+            degeneracy,
+        }
+    }
+
+    pub fn grid(&self, momenta: impl Iterator<Item = Vector2<f64>>) -> OccupancyGrid {
+        let mut grid = OccupancyGrid {
+            blocking: *self,
+            counts: HashMap::new(),
+        };
+        momenta.for_each(|momentum| grid.occupy(momentum));
+        grid
+    }
+}
+
+// Number of carriers in each occupied momentum cell.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OccupancyGrid {
+    blocking: PauliBlocking,
+    counts: HashMap<(i64, i64), u32>,
+}
+
+impl OccupancyGrid {
+    fn cell(&self, momentum: Vector2<f64>) -> (i64, i64) {
+        let size = self.blocking.cell_size;
+        (
+            (momentum.x / size).floor() as i64,
+            (momentum.y / size).floor() as i64,
+        )
+    }
+
+    pub fn occupy(&mut self, momentum: Vector2<f64>) {
+        *self.counts.entry(self.cell(momentum)).or_insert(0) += 1;
+    }
+
+    pub fn occupancy(&self, momentum: Vector2<f64>) -> u32 {
+        *self.counts.get(&self.cell(momentum)).unwrap_or(&0)
+    }
+
+    pub fn is_full(&self, momentum: Vector2<f64>) -> bool {
+        self.occupancy(momentum) >= self.blocking.degeneracy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn zero_temperature_fills_fermi_disk() {
+        seed_random(21);
+        let distribution = FermiDirac::new(0.0, 4.0);
+        let n = 20_000;
+        let energies: Vec<f64> = (0..n).map(|_| distribution.sample_energy()).collect();
+        let mean = energies.iter().sum::<f64>() / n as f64;
+
+        assert!(energies.iter().all(|&energy| energy <= 4.0));
+        assert!((mean - 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn sampled_tail_follows_occupation() {
+        seed_random(22);
+        let distribution = FermiDirac::new(1.0, 3.0);
+        let n = 40_000;
+        let above = (0..n)
+            .filter(|_| distribution.sample_energy() > 3.0)
+            .count() as f64
+            / n as f64;
+        // States above mu hold T * ln 2 of the T * ln(1 + e^3) carriers.
+        let expected = 2.0f64.ln() / softplus(3.0);
+
+        assert!((above - expected).abs() < 0.01);
+        assert_eq!(distribution.occupation(3.0), 0.5);
+    }
+
+    #[test]
+    fn cell_blocks_at_degeneracy() {
+        let blocking = PauliBlocking::new(0.5, 2);
+        let mut grid = blocking.grid(vec![Vector2::new(0.1, 0.2)].into_iter());
+
+        assert!(!grid.is_full(Vector2::new(0.4, 0.4)));
+        grid.occupy(Vector2::new(0.3, 0.0));
+        assert!(grid.is_full(Vector2::new(0.4, 0.4)));
+        assert!(!grid.is_full(Vector2::new(-0.1, 0.4)));
+    }
+}
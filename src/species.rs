@@ -0,0 +1,121 @@
+extern crate nalgebra as na;
+use na::{Matrix2, Vector2};
+
+use crate::cfg::{ELECTRON_CHARGE, ELECTRON_MASS, ELECTRON_RADIUS};
+
+// A kind of charge carrier moving through the lattice.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Species {
+    pub name: String,
+    pub mass: f64,
+    pub charge: f64,
+    pub radius: f64,
+    // Effective mass tensor of anisotropic carriers, `None` for a scalar mass.
+    // Scattering laws, Monte-Carlo carrier collisions and impurity orbits still use the scalar `mass`.
+    pub mass_tensor: Option<Matrix2<f64>>,
+    // Fermi velocity of massless Dirac carriers, `None` for the parabolic dispersion.
+    pub fermi_velocity: Option<f64>,
+}
+
+impl Species {
+    pub fn new(name: &str, mass: f64, charge: f64, radius: f64) -> Species {
+        Species {
+            name: name.to_string(),
+            mass,
+            charge,
+            radius,
+            mass_tensor: None,
+            fermi_velocity: None,
+        }
+    }
+
+    // Carrier with a symmetric positive definite mass tensor, `None` for any other matrix.
+    // Its scalar mass is the density-of-states mass sqrt(det M).
+    pub fn anisotropic(
+        name: &str,
+        mass_tensor: Matrix2<f64>,
+        charge: f64,
+        radius: f64,
+    ) -> Option<Species> {
+        let symmetric = mass_tensor[(0, 1)] == mass_tensor[(1, 0)];
+        let determinant = mass_tensor.determinant();
+        if !symmetric || mass_tensor[(0, 0)] <= 0.0 || determinant <= 0.0 {
+            return None;
+        }
+        let mut species = Species::new(name, determinant.sqrt(), charge, radius);
+        species.mass_tensor = Some(mass_tensor);
+        Some(species)
+    }
+
+    // Graphene-like carrier moving at `fermi_velocity` in the direction of its momentum.
+    // The scalar mass only turns initial velocities into momenta and weighs carrier collisions.
+    pub fn dirac(name: &str, fermi_velocity: f64, charge: f64, radius: f64) -> Species {
+        let mut species = Species::new(name, ELECTRON_MASS, charge, radius);
+        species.fermi_velocity = Some(fermi_velocity);
+        species
+    }
+
+    pub fn electron() -> Species {
+        Species::new("electron", ELECTRON_MASS, ELECTRON_CHARGE, ELECTRON_RADIUS)
+    }
+
+    // Positively charged carrier with the given effective mass.
+    pub fn hole(mass: f64) -> Species {
+        Species::new("hole", mass, -ELECTRON_CHARGE, ELECTRON_RADIUS)
+    }
+
+    // Acceleration M^-1 * q * E in a field pointing along x.
+    // Dirac carriers do not accelerate, the field turns their momentum instead.
+    pub fn acceleration(&self, field: f64) -> Vector2<f64> {
+        if self.fermi_velocity.is_some() {
+            return Vector2::new(0.0, 0.0);
+        }
+        match self.mass_tensor {
+            Some(tensor) => tensor.try_inverse().unwrap() * Vector2::new(self.charge * field, 0.0),
+            None => Vector2::new(self.charge * field / self.mass, 0.0),
+        }
+    }
+}
+
+// The simulation is driven by the acceleration of a reference electron,
+// this converts it into the field strength acting on every species.
+pub fn field_from_acc(acc: f64) -> f64 {
+    acc * ELECTRON_MASS / ELECTRON_CHARGE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn electron_follows_reference_acc() {
+        let electron = Species::electron();
+        assert_eq!(
+            electron.acceleration(field_from_acc(0.5)),
+            Vector2::new(0.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn acceleration_scales_with_charge_to_mass() {
+        let heavy = Species::new("heavy", 4.0, 2.0, 5.0);
+        assert_eq!(
+            heavy.acceleration(field_from_acc(0.5)),
+            Vector2::new(-0.25, 0.0)
+        );
+    }
+
+    #[test]
+    fn anisotropic_acceleration_not_along_field() {
+        let tensor = Matrix2::new(2.0, 1.0, 1.0, 2.0);
+        let species = Species::anisotropic("tilted", tensor, -1.0, 3.0).unwrap();
+        let acc = species.acceleration(-3.0);
+
+        assert!((acc - Vector2::new(2.0, -1.0)).norm() < 1e-12);
+        assert!((species.mass - 3.0f64.sqrt()).abs() < 1e-12);
+        assert_eq!(
+            Species::anisotropic("bad", Matrix2::new(1.0, 2.0, 2.0, 1.0), -1.0, 3.0),
+            None
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use wasm_bindgen::prelude::*;
+
+use crate::species::Species;
+
+#[wasm_bindgen(js_name = Species)]
+pub struct SpeciesJs {
+    name: String,
+    pub mass: f64,
+    pub charge: f64,
+    pub radius: f64,
+}
+
+#[wasm_bindgen(js_class = Species)]
+impl SpeciesJs {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl SpeciesJs {
+    pub fn new(species: &Species) -> SpeciesJs {
+        SpeciesJs {
+            name: species.name.clone(),
+            mass: species.mass,
+            charge: species.charge,
+            radius: species.radius,
+        }
+    }
+}
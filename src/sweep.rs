@@ -0,0 +1,251 @@
+use crate::crystal_structure::CrystalStructure;
+use crate::generation::ImpactIonisation;
+use crate::species::Species;
+use crate::thermal::{OpticalPhonon, PhononResampling, ThermalVibration};
+
+// Parameters shared by every point of a parameter sweep.
+// Each point runs a fresh structure for `warmup_ticks` before averaging over `measure_ticks`.
+#[derive(Clone, Copy, Debug)]
+pub struct SweepSettings {
+    pub x_size: f64,
+    pub y_size: f64,
+    pub ion_distance: f64,
+    pub init_velocity: f64,
+    pub num_electrons: i32,
+    pub acc: f64,
+    pub supp: f64,
+    pub warmup_ticks: usize,
+    pub measure_ticks: usize,
+}
+
+impl SweepSettings {
+    fn build(&self) -> CrystalStructure {
+        CrystalStructure::new(
+            self.x_size,
+            self.y_size,
+            self.ion_distance,
+            self.init_velocity,
+            self.num_electrons,
+        )
+    }
+
+    // Runs the structure and returns the time averaged drift velocity.
+    fn measure_drift(&self, cs: &mut CrystalStructure) -> f64 {
+        for _ in 0..self.warmup_ticks {
+            cs.update(self.acc, self.supp);
+        }
+        let mut drift = 0.0;
+        for _ in 0..self.measure_ticks {
+            cs.update(self.acc, self.supp);
+            drift += cs.drift_velocity();
+        }
+        drift / self.measure_ticks.max(1) as f64
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ResistivityPoint {
+    pub temperature: f64,
+    pub drift_velocity: f64,
+    // NaN when the measured drift does not follow the field.
+    pub resistivity: f64,
+}
+
+// rho = E / (n * e * v_drift), NaN unless the drift is positive and the result finite.
+// At high temperature the measured drift can vanish or turn negative in the noise.
+fn resistivity(acc: f64, density: f64, drift_velocity: f64) -> f64 {
+    let resistivity = acc / (density * drift_velocity);
+    if drift_velocity > 0.0 && resistivity.is_finite() {
+        resistivity
+    } else {
+        f64::NAN
+    }
+}
+
+// Measures the resistivity rho = E / (n * e * v_drift) for each lattice temperature.
+// Simulation units are used throughout: unit charge and mass, so the field strength equals `acc`.
+pub fn temperature_sweep(
+    settings: &SweepSettings,
+    stiffness: f64,
+    resampling: PhononResampling,
+    temperatures: &[f64],
+) -> Vec<ResistivityPoint> {
+    temperatures
+        .iter()
+        .map(|&temperature| {
+            let mut cs = settings.build();
+            cs.set_vibration(Some(ThermalVibration::new(
+                temperature,
+                stiffness,
+                resampling,
+            )));
+            let drift_velocity = settings.measure_drift(&mut cs);
+            ResistivityPoint {
+                temperature,
+                drift_velocity,
+                resistivity: resistivity(settings.acc, cs.electron_density(), drift_velocity),
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct VelocityFieldPoint {
+    pub acc: f64,
+    pub drift_velocity: f64,
+    pub mobility: f64,
+}
+
+// Measures the velocity-field curve, overriding `settings.acc` with each acceleration.
+// With optical phonon emission the drift velocity saturates at high fields.
+pub fn field_sweep(
+    settings: &SweepSettings,
+    optical_phonon: Option<OpticalPhonon>,
+    accelerations: &[f64],
+) -> Vec<VelocityFieldPoint> {
+    accelerations
+        .iter()
+        .map(|&acc| {
+            let settings = SweepSettings { acc, ..*settings };
+            let mut cs = settings.build();
+            cs.set_optical_phonon(optical_phonon);
+            let drift_velocity = settings.measure_drift(&mut cs);
+            VelocityFieldPoint {
+                acc,
+                drift_velocity,
+                mobility: drift_velocity / acc,
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MultiplicationPoint {
+    pub acc: f64,
+    pub ionisations: i32,
+    pub multiplication: f64,
+}
+
+// Runs a fresh structure with impact ionisation for each field strength, overriding `settings.acc`.
+// The multiplication factor is the number of electrons at the end of the run per initial electron.
+pub fn avalanche_sweep(
+    settings: &SweepSettings,
+    threshold: f64,
+    gap: f64,
+    probability: f64,
+    hole_mass: f64,
+    accelerations: &[f64],
+) -> Vec<MultiplicationPoint> {
+    accelerations
+        .iter()
+        .map(|&acc| {
+            let mut cs = settings.build();
+            let initial = cs.electrons.len();
+            let holes = cs.add_species(Species::hole(hole_mass));
+            cs.set_impact_ionisation(Some(ImpactIonisation::new(
+                threshold,
+                gap,
+                probability,
+                0,
+                holes,
+            )));
+            for _ in 0..settings.warmup_ticks + settings.measure_ticks {
+                cs.update(acc, settings.supp);
+            }
+            let electrons = cs
+                .electrons
+                .iter()
+                .filter(|electron| electron.borrow().species == 0)
+                .count();
+            MultiplicationPoint {
+                acc,
+                ionisations: cs.carrier_stats.impact_ionisations,
+                multiplication: electrons as f64 / initial.max(1) as f64,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn temperature_sweep_reports_every_point() {
+        seed_random(11);
+        let settings = SweepSettings {
+            x_size: 300.0,
+            y_size: 200.0,
+            ion_distance: 60.0,
+            init_velocity: 0.2,
+            num_electrons: 10,
+            acc: 0.05,
+            supp: 0.0,
+            warmup_ticks: 50,
+            measure_ticks: 200,
+        };
+        let points = temperature_sweep(&settings, 0.2, PhononResampling::OnScatter, &[0.0, 10.0]);
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].temperature, 10.0);
+        points.iter().for_each(|point| {
+            assert!(point.drift_velocity.is_finite());
+            assert!(point.resistivity.is_finite());
+        });
+        // Vibrating ions scatter more, so the hot lattice is the more resistive one.
+        assert!(points[1].resistivity > points[0].resistivity);
+    }
+
+    #[test]
+    fn resistivity_is_nan_without_forward_drift() {
+        assert_eq!(resistivity(0.05, 0.5, 0.1), 1.0);
+        assert!(resistivity(0.05, 0.5, 0.0).is_nan());
+        assert!(resistivity(0.05, 0.5, -0.1).is_nan());
+        assert!(resistivity(0.05, 0.0, 0.1).is_nan());
+    }
+
+    #[test]
+    fn optical_phonons_saturate_the_drift() {
+        seed_random(4);
+        let settings = SweepSettings {
+            x_size: 300.0,
+            y_size: 200.0,
+            ion_distance: 60.0,
+            init_velocity: 1.0,
+            num_electrons: 10,
+            acc: 0.0,
+            supp: 0.0,
+            warmup_ticks: 50,
+            measure_ticks: 100,
+        };
+        let phonon = OpticalPhonon::new(0.5, 1.0);
+        let points = field_sweep(&settings, Some(phonon), &[0.05, 0.2, 0.8]);
+
+        assert_eq!(points[0].acc, 0.05);
+        assert!(points[0].mobility > points[1].mobility);
+        assert!(points[1].mobility > points[2].mobility);
+    }
+
+    #[test]
+    fn avalanche_grows_with_field() {
+        seed_random(3);
+        let settings = SweepSettings {
+            x_size: 300.0,
+            y_size: 200.0,
+            ion_distance: 60.0,
+            init_velocity: 1.0,
+            num_electrons: 10,
+            acc: 0.0,
+            supp: 0.0,
+            warmup_ticks: 0,
+            measure_ticks: 100,
+        };
+        let points = avalanche_sweep(&settings, 8.0, 2.0, 1.0, 1.0, &[0.0, 0.1]);
+
+        assert_eq!(points[0].ionisations, 0);
+        assert_eq!(points[0].multiplication, 1.0);
+        assert!(points[1].ionisations > 0);
+        assert!(points[1].multiplication > 1.0);
+    }
+}
@@ -0,0 +1,114 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+use crate::utils::random_normal;
+
+// When a new frozen-phonon configuration is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PhononResampling {
+    // Every ion is displaced anew once per period (in ticks).
+    Every(f64),
+    // Only the struck ion is displaced anew after each electron-ion collision.
+    OnScatter,
+}
+
+// Einstein model of lattice vibrations.
+// Each ion oscillates independently around its site with spring constant `stiffness` = M * omega^2,
+// so in equilibrium every displacement component is Gaussian with variance k_B * T / stiffness
+// (k_B = 1 in simulation units).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThermalVibration {
+    pub temperature: f64,
+    pub stiffness: f64,
+    pub resampling: PhononResampling,
+}
+
+impl ThermalVibration {
+    pub fn new(temperature: f64, stiffness: f64, resampling: PhononResampling) -> Self {
+        ThermalVibration {
+            temperature,
+            stiffness,
+            resampling,
+        }
+    }
+
+    // Root mean square displacement along one axis.
+    pub fn sigma(&self) -> f64 {
+        (self.temperature.max(0.0) / self.stiffness).sqrt()
+    }
+
+    pub fn sample_displacement(&self) -> Vector2<f64> {
+        let sigma = self.sigma();
+        Vector2::new(random_normal() * sigma, random_normal() * sigma)
+    }
+
+    pub fn resample_period(&self) -> Option<f64> {
+        match self.resampling {
+            PhononResampling::Every(period) => Some(period),
+            PhononResampling::OnScatter => None,
+        }
+    }
+}
+
+// Inelastic scattering by emission of an optical phonon of fixed energy `quantum` (hbar * omega).
+// A carrier hitting an ion with more kinetic energy than the quantum loses it with the given probability.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OpticalPhonon {
+    pub quantum: f64,
+    pub probability: f64,
+}
+
+impl OpticalPhonon {
+    pub fn new(quantum: f64, probability: f64) -> Self {
+        OpticalPhonon {
+            quantum,
+            probability,
+        }
+    }
+
+    // Kinetic energy left after emitting one phonon, `None` when the carrier is too slow.
+    pub fn energy_after(&self, energy: f64) -> Option<f64> {
+        if energy > self.quantum {
+            Some(energy - self.quantum)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn sigma_follows_equipartition() {
+        let vibration = ThermalVibration::new(8.0, 2.0, PhononResampling::OnScatter);
+        assert_eq!(vibration.sigma(), 2.0);
+    }
+
+    #[test]
+    fn sample_displacement_variance() {
+        seed_random(7);
+        let vibration = ThermalVibration::new(4.0, 1.0, PhononResampling::Every(10.0));
+        let n = 20_000;
+        let var = (0..n)
+            .map(|_| vibration.sample_displacement())
+            .fold(0.0, |acc, d| acc + d.x.powi(2) + d.y.powi(2))
+            / (2 * n) as f64;
+        assert!((var - 4.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn optical_phonon_needs_quantum() {
+        let phonon = OpticalPhonon::new(2.0, 1.0);
+        assert_eq!(phonon.energy_after(1.5), None);
+        assert_eq!(phonon.energy_after(5.0), Some(3.0));
+    }
+
+    #[test]
+    fn zero_temperature_is_static() {
+        let vibration = ThermalVibration::new(0.0, 1.0, PhononResampling::OnScatter);
+        assert_eq!(vibration.sample_displacement(), Vector2::new(0.0, 0.0));
+    }
+}
@@ -0,0 +1,237 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+use std::cell::RefMut;
+use std::collections::HashMap;
+
+use crate::electron::Electron;
+use crate::utils::{random, random_normal};
+
+// How the thermal motion is coupled to the heat bath.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThermostatKind {
+    // Scales the thermal velocities to the target temperature once every coupling time.
+    Rescale,
+    // Relaxes the temperature exponentially with the coupling time.
+    Berendsen,
+    // Redraws the thermal velocity of each carrier from the Maxwellian, on average once per coupling time.
+    Andersen,
+    // Friction coefficient driven by the temperature error, oscillating with the coupling time.
+    NoseHoover,
+}
+
+// Holds the carrier temperature at `temperature` (k_B = 1).
+// Only the velocities relative to the mean drift of each species are touched,
+// so a field still drives a current while its Joule heat goes to the bath.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thermostat {
+    pub kind: ThermostatKind,
+    pub temperature: f64,
+    pub coupling_time: f64,
+    since_rescale: f64,
+    friction: f64,
+}
+
+impl Thermostat {
+    pub fn new(kind: ThermostatKind, temperature: f64, coupling_time: f64) -> Self {
+        Thermostat {
+            kind,
+            temperature,
+            coupling_time,
+            since_rescale: 0.0,
+            friction: 0.0,
+        }
+    }
+
+    // Current Nose-Hoover friction coefficient, zero for the other kinds.
+    pub fn friction(&self) -> f64 {
+        self.friction
+    }
+
+    // Couples the carriers to the bath over `time`.
+    pub fn apply(&mut self, carriers: &mut [RefMut<Electron>], time: f64) {
+        let drifts = drift_velocities(carriers);
+        let current = temperature(carriers);
+        let scale = match self.kind {
+            ThermostatKind::Rescale => {
+                self.since_rescale += time;
+                if self.since_rescale < self.coupling_time {
+                    return;
+                }
+                self.since_rescale = 0.0;
+                (self.temperature / current).sqrt()
+            }
+            ThermostatKind::Berendsen => {
+                let ratio = 1.0 + time / self.coupling_time * (self.temperature / current - 1.0);
+                ratio.max(0.0).sqrt()
+            }
+            ThermostatKind::Andersen => {
+                let probability = 1.0 - (-time / self.coupling_time).exp();
+                for carrier in carriers.iter_mut() {
+                    if random() < probability {
+                        let thermal = thermal_velocity(carrier, self.temperature.max(0.0));
+                        carrier.vel = drifts[&carrier.species] + thermal;
+                    }
+                }
+                return;
+            }
+            ThermostatKind::NoseHoover => {
+                self.friction +=
+                    time / self.coupling_time.powi(2) * (current / self.temperature - 1.0);
+                (-self.friction * time).exp()
+            }
+        };
+        if !scale.is_finite() {
+            return;
+        }
+        for carrier in carriers.iter_mut() {
+            let drift = drifts[&carrier.species];
+            carrier.vel = drift + (carrier.vel - drift) * scale;
+        }
+    }
+}
+
+// Velocity drawn from the Maxwellian at `temperature`. Its covariance is T * M^-1, so
+// anisotropic carriers come out at the temperature `temperature` measures through the tensor.
+fn thermal_velocity(carrier: &Electron, temperature: f64) -> Vector2<f64> {
+    let normal = Vector2::new(random_normal(), random_normal());
+    match carrier.mass_tensor {
+        Some(_) => match (carrier.inverse_mass() * temperature).cholesky() {
+            Some(cholesky) => cholesky.l() * normal,
+            None => Vector2::new(0.0, 0.0),
+        },
+        None => normal * (temperature / carrier.mass).sqrt(),
+    }
+}
+
+fn drift_velocities(carriers: &[RefMut<Electron>]) -> HashMap<usize, Vector2<f64>> {
+    let mut sums: HashMap<usize, (Vector2<f64>, usize)> = HashMap::new();
+    for carrier in carriers.iter() {
+        let entry = sums
+            .entry(carrier.species)
+            .or_insert((Vector2::new(0.0, 0.0), 0));
+        entry.0 += carrier.vel;
+        entry.1 += 1;
+    }
+    sums.into_iter()
+        .map(|(species, (sum, count))| (species, sum / count as f64))
+        .collect()
+}
+
+// Mean kinetic energy of the motion relative to the drift of each species,
+// which equals k_B * T for the two degrees of freedom in 2D.
+pub fn temperature(carriers: &[RefMut<Electron>]) -> f64 {
+    if carriers.is_empty() {
+        return 0.0;
+    }
+    let drifts = drift_velocities(carriers);
+    let energy: f64 = carriers
+        .iter()
+        .map(|carrier| {
+            let thermal = carrier.vel - drifts[&carrier.species];
+            match carrier.mass_tensor {
+                Some(tensor) => 0.5 * thermal.dot(&(tensor * thermal)),
+                None => 0.5 * carrier.mass * thermal.magnitude_squared(),
+            }
+        })
+        .sum();
+    energy / carriers.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_random;
+    use std::cell::RefCell;
+
+    fn gas(count: usize, speed: f64, drift: f64) -> Vec<RefCell<Electron>> {
+        (0..count)
+            .map(|i| {
+                let angle = i as f64 * 2.0 * std::f64::consts::PI / count as f64;
+                RefCell::new(Electron::new(
+                    Vector2::new(0.0, 0.0),
+                    Vector2::new(angle.cos() * speed + drift, angle.sin() * speed),
+                    Vector2::new(0.0, 0.0),
+                ))
+            })
+            .collect()
+    }
+
+    fn borrow(gas: &[RefCell<Electron>]) -> Vec<RefMut<'_, Electron>> {
+        gas.iter().map(|carrier| carrier.borrow_mut()).collect()
+    }
+
+    #[test]
+    fn temperature_excludes_drift() {
+        let gas = gas(8, 2.0, 5.0);
+        assert!((temperature(&borrow(&gas)) - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn berendsen_relaxes_and_keeps_drift() {
+        let gas = gas(8, 2.0, 5.0);
+        let mut thermostat = Thermostat::new(ThermostatKind::Berendsen, 0.5, 10.0);
+        for _ in 0..100 {
+            thermostat.apply(&mut borrow(&gas), 1.0);
+        }
+        let carriers = borrow(&gas);
+        let drift = carriers.iter().map(|c| c.vel).sum::<Vector2<f64>>() / 8.0;
+
+        assert!((temperature(&carriers) - 0.5).abs() < 1e-3);
+        assert!((drift - Vector2::new(5.0, 0.0)).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn rescale_waits_for_coupling_time() {
+        let gas = gas(8, 2.0, 0.0);
+        let mut thermostat = Thermostat::new(ThermostatKind::Rescale, 1.0, 3.0);
+        thermostat.apply(&mut borrow(&gas), 1.0);
+        thermostat.apply(&mut borrow(&gas), 1.0);
+        assert!((temperature(&borrow(&gas)) - 2.0).abs() < 1e-12);
+        thermostat.apply(&mut borrow(&gas), 1.0);
+        assert!((temperature(&borrow(&gas)) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn andersen_thermalises() {
+        seed_random(41);
+        let gas = gas(2000, 0.1, 0.0);
+        let mut thermostat = Thermostat::new(ThermostatKind::Andersen, 1.5, 1.0);
+        for _ in 0..20 {
+            thermostat.apply(&mut borrow(&gas), 1.0);
+        }
+        assert!((temperature(&borrow(&gas)) - 1.5).abs() < 0.1);
+    }
+
+    #[test]
+    fn andersen_thermalises_anisotropic_carriers() {
+        seed_random(42);
+        let gas = gas(2000, 0.1, 0.0);
+        gas.iter().for_each(|carrier| {
+            carrier.borrow_mut().mass_tensor = Some(na::Matrix2::new(0.5, 0.0, 0.0, 8.0));
+        });
+        let mut thermostat = Thermostat::new(ThermostatKind::Andersen, 1.5, 1.0);
+        for _ in 0..20 {
+            thermostat.apply(&mut borrow(&gas), 1.0);
+        }
+        let carriers = borrow(&gas);
+        let spread = |axis: usize| {
+            carriers.iter().map(|c| c.vel[axis].powi(2)).sum::<f64>() / carriers.len() as f64
+        };
+
+        assert!((temperature(&carriers) - 1.5).abs() < 0.1);
+        // Equipartition per axis: m_xx <v_x^2> = m_yy <v_y^2> = T.
+        assert!((0.5 * spread(0) - 1.5).abs() < 0.15);
+        assert!((8.0 * spread(1) - 1.5).abs() < 0.15);
+    }
+
+    #[test]
+    fn nose_hoover_friction_follows_temperature_error() {
+        let gas = gas(8, 2.0, 0.0);
+        let mut thermostat = Thermostat::new(ThermostatKind::NoseHoover, 1.0, 2.0);
+        thermostat.apply(&mut borrow(&gas), 0.1);
+
+        assert!(thermostat.friction() > 0.0);
+        assert!(temperature(&borrow(&gas)) < 2.0);
+    }
+}
@@ -0,0 +1,748 @@
+extern crate nalgebra as na;
+use na::Vector3;
+
+use crate::cfg::{INIT_ITERATIONS, ION_RADIUS};
+use crate::collision_stats::{CollisionKind, CollisionStats};
+use crate::crystal_structure::Flux;
+use crate::species::{field_from_acc, Species};
+use crate::utils::{
+    calc_time_to_border_collision, calc_time_to_collision, elastic_collision, random,
+};
+
+// Collisions are only searched for within this many ticks, a `Horizon` event re-predicts after it.
+pub const HORIZON: f64 = 1.0;
+
+// Bravais lattice of the 3D ion crystal, described by its conventional cubic cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lattice {
+    SimpleCubic,
+    BodyCentred,
+    FaceCentred,
+}
+
+impl Lattice {
+    // Ion positions inside one conventional cell, in units of the lattice constant.
+    pub fn basis(&self) -> Vec<Vector3<f64>> {
+        let corner = Vector3::new(0.0, 0.0, 0.0);
+        match self {
+            Lattice::SimpleCubic => vec![corner],
+            Lattice::BodyCentred => vec![corner, Vector3::new(0.5, 0.5, 0.5)],
+            Lattice::FaceCentred => vec![
+                corner,
+                Vector3::new(0.5, 0.5, 0.0),
+                Vector3::new(0.5, 0.0, 0.5),
+                Vector3::new(0.0, 0.5, 0.5),
+            ],
+        }
+    }
+
+    // Ions per unit volume for the lattice constant `a`.
+    pub fn density(&self, a: f64) -> f64 {
+        self.basis().len() as f64 / a.powi(3)
+    }
+}
+
+// Carrier of the 3D structure, only scalar masses are supported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Electron3 {
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+    pub acc: Vector3<f64>,
+    pub species: usize,
+    pub mass: f64,
+    pub charge: f64,
+    pub radius: f64,
+    pub collisions: CollisionStats,
+}
+
+impl Electron3 {
+    pub fn of_species(
+        species_id: usize,
+        species: &Species,
+        pos: Vector3<f64>,
+        vel: Vector3<f64>,
+    ) -> Electron3 {
+        Electron3 {
+            pos,
+            vel,
+            acc: Vector3::new(0.0, 0.0, 0.0),
+            species: species_id,
+            mass: species.mass,
+            charge: species.charge,
+            radius: species.radius,
+            collisions: CollisionStats::default(),
+        }
+    }
+
+    // Sets the acceleration q * E / m for a field pointing along x.
+    pub fn set_field(&mut self, field: f64) {
+        self.acc = Vector3::new(self.charge * field / self.mass, 0.0, 0.0);
+    }
+
+    pub fn kinetic_energy(&self) -> f64 {
+        0.5 * self.mass * self.vel.magnitude_squared()
+    }
+
+    pub fn update(&mut self, time: f64, supp: f64) {
+        let acc = self.acc - self.vel * supp;
+        let vel = self.vel;
+        self.vel += acc * time;
+        self.pos += vel * time + acc * time.powi(2) / 2.0;
+        self.collisions.advance(time);
+    }
+
+    pub fn record_collision(&mut self, kind: CollisionKind) {
+        self.collisions.record(kind);
+    }
+}
+
+// What a carrier of the 3D structure hits next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Contact3 {
+    Ion(usize),
+    Electron(usize),
+    // Reflecting wall normal to y (axis 1) or z (axis 2), at the far side of the box when `upper`.
+    Wall { axis: usize, upper: bool },
+    // Periodic x border, crossed in the +x direction when `forward`.
+    Periodic { forward: bool },
+    // Nothing within the prediction horizon.
+    Horizon,
+}
+
+// Buckets of ion indices on a cubic grid, so only the ions a carrier can reach are tested.
+struct IonCells {
+    size: f64,
+    dims: [usize; 3],
+    buckets: Vec<Vec<usize>>,
+}
+
+impl IonCells {
+    fn new(ions: &[Vector3<f64>], bounds: Vector3<f64>, size: f64) -> IonCells {
+        let dims = [0, 1, 2].map(|axis| ((bounds[axis] / size).ceil() as usize).max(1));
+        let mut cells = IonCells {
+            size,
+            dims,
+            buckets: vec![Vec::new(); dims[0] * dims[1] * dims[2]],
+        };
+        for (i, ion) in ions.iter().enumerate() {
+            let [x, y, z] = [0, 1, 2].map(|axis| cells.clamp(ion[axis], axis));
+            let bucket = cells.bucket(x, y, z);
+            cells.buckets[bucket].push(i);
+        }
+        cells
+    }
+
+    fn clamp(&self, coordinate: f64, axis: usize) -> usize {
+        ((coordinate / self.size).floor().max(0.0) as usize).min(self.dims[axis] - 1)
+    }
+
+    fn bucket(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.dims[1] + y) * self.dims[0] + x
+    }
+
+    // Ions in every bucket touched by the cube of half side `reach` around `pos`, each with the
+    // x shift of its image. The images one `period` away are searched when the cube crosses x = 0
+    // or x = `period`.
+    fn near(&self, pos: Vector3<f64>, reach: f64, period: f64) -> Vec<(usize, f64)> {
+        let mut ions = Vec::new();
+        for shift in [0.0, -period, period] {
+            let centre = Vector3::new(pos.x - shift, pos.y, pos.z);
+            if centre.x + reach < 0.0 || centre.x - reach > period {
+                continue;
+            }
+            let low = [0, 1, 2].map(|axis| self.clamp(centre[axis] - reach, axis));
+            let high = [0, 1, 2].map(|axis| self.clamp(centre[axis] + reach, axis));
+            for z in low[2]..=high[2] {
+                for y in low[1]..=high[1] {
+                    for x in low[0]..=high[0] {
+                        let bucket = &self.buckets[self.bucket(x, y, z)];
+                        ions.extend(bucket.iter().map(|&ion| (ion, shift)));
+                    }
+                }
+            }
+        }
+        ions
+    }
+}
+
+// Three-dimensional counterpart of `CrystalStructure`: hard-sphere carriers in a box of ions
+// on a cubic lattice. The box is periodic along the field axis x and reflecting in y and z,
+// so the densities and conductivities are per unit volume as in real metals.
+pub struct CrystalStructure3 {
+    pub x_size: f64,
+    pub y_size: f64,
+    pub z_size: f64,
+    pub lattice: Lattice,
+    pub lattice_constant: f64,
+    pub ion_radius: f64,
+    pub acc: f64,
+    pub ions: Vec<Vector3<f64>>,
+    pub electrons: Vec<Electron3>,
+    pub species: Vec<Species>,
+    pub flux: Vec<Flux>,
+    pub time: f64,
+    pub next_collision: (usize, Contact3),
+    pub time_to_bounce: f64,
+    cells: IonCells,
+}
+
+impl CrystalStructure3 {
+    pub fn new(
+        x_size: f64,
+        y_size: f64,
+        z_size: f64,
+        lattice: Lattice,
+        lattice_constant: f64,
+        init_velocity: f64,
+        num_electrons: i32,
+    ) -> CrystalStructure3 {
+        let ions = lattice_sites(
+            Vector3::new(x_size, y_size, z_size),
+            lattice,
+            lattice_constant,
+            ION_RADIUS,
+        );
+        let cells = IonCells::new(
+            &ions,
+            Vector3::new(x_size, y_size, z_size),
+            lattice_constant,
+        );
+        let mut crystal_structure = CrystalStructure3 {
+            x_size,
+            y_size,
+            z_size,
+            lattice,
+            lattice_constant,
+            ion_radius: ION_RADIUS,
+            acc: 0.0,
+            ions,
+            electrons: Vec::new(),
+            species: Vec::new(),
+            flux: Vec::new(),
+            time: 0.0,
+            next_collision: (0, Contact3::Horizon),
+            time_to_bounce: HORIZON,
+            cells,
+        };
+        let electron = crystal_structure.add_species(Species::electron());
+        crystal_structure.add_carriers(electron, num_electrons, init_velocity);
+        crystal_structure
+    }
+
+    // Registers a new carrier species and returns its id.
+    pub fn add_species(&mut self, species: Species) -> usize {
+        self.species.push(species);
+        self.flux.push(Flux::default());
+        self.species.len() - 1
+    }
+
+    // Places carriers at random free positions moving in uniformly random directions.
+    // Carriers that do not fit after `INIT_ITERATIONS` attempts are skipped.
+    pub fn add_carriers(&mut self, species_id: usize, count: i32, init_velocity: f64) {
+        let field = field_from_acc(self.acc);
+        for _ in 0..count {
+            let species = &self.species[species_id];
+            let radius = species.radius;
+            for _ in 0..INIT_ITERATIONS {
+                let pos = Vector3::new(
+                    random() * self.x_size,
+                    radius + random() * (self.y_size - 2.0 * radius),
+                    radius + random() * (self.z_size - 2.0 * radius),
+                );
+                if self.is_free(pos, radius) {
+                    let mut electron = Electron3::of_species(
+                        species_id,
+                        species,
+                        pos,
+                        random_direction() * init_velocity,
+                    );
+                    electron.set_field(field);
+                    self.electrons.push(electron);
+                    break;
+                }
+            }
+        }
+        self.update_collidables();
+    }
+
+    pub fn add_carrier(&mut self, mut electron: Electron3) {
+        electron.set_field(field_from_acc(self.acc));
+        self.electrons.push(electron);
+        self.update_collidables();
+    }
+
+    fn is_free(&self, pos: Vector3<f64>, radius: f64) -> bool {
+        let ion_clearance = self.ion_radius + radius;
+        self.cells
+            .near(pos, ion_clearance, self.x_size)
+            .iter()
+            .all(|&(ion, _)| self.separation(pos, self.ions[ion]).magnitude() > ion_clearance)
+            && self.electrons.iter().all(|electron| {
+                self.separation(pos, electron.pos).magnitude() > electron.radius + radius
+            })
+    }
+
+    // Displacement from `from` to the nearest periodic image of `to`.
+    fn separation(&self, from: Vector3<f64>, to: Vector3<f64>) -> Vector3<f64> {
+        let mut separation = to - from;
+        separation.x -= self.x_size * (separation.x / self.x_size).round();
+        separation
+    }
+
+    pub fn update(&mut self, acc: f64, supp: f64) {
+        let mut time: f64 = 1.0;
+
+        if self.acc != acc {
+            self.acc = acc;
+            let field = field_from_acc(acc);
+            self.electrons
+                .iter_mut()
+                .for_each(|electron| electron.set_field(field));
+            self.update_collidables();
+        }
+
+        while time > 0.0 {
+            if self.time_to_bounce > time {
+                self.advance(time, supp);
+                break;
+            }
+
+            let time_to_bounce = self.time_to_bounce;
+            self.advance(time_to_bounce, supp);
+            self.resolve_next_collision();
+            time -= time_to_bounce;
+            self.update_collidables();
+        }
+    }
+
+    fn advance(&mut self, time: f64, supp: f64) {
+        self.electrons
+            .iter_mut()
+            .for_each(|electron| electron.update(time, supp));
+        self.time += time;
+        self.time_to_bounce -= time;
+    }
+
+    fn update_collidables(&mut self) {
+        self.next_collision = (0, Contact3::Horizon);
+        self.time_to_bounce = HORIZON;
+        for i in 0..self.electrons.len() {
+            let (contact, time) = self.predict(i);
+            if time < self.time_to_bounce {
+                self.next_collision = (i, contact);
+                self.time_to_bounce = time;
+            }
+        }
+    }
+
+    // Distance a carrier can cover within the horizon, ignoring the drag.
+    fn reach(electron: &Electron3) -> f64 {
+        electron.vel.magnitude() * HORIZON + electron.acc.magnitude() * HORIZON.powi(2) / 2.0
+    }
+
+    // Earliest contact of one carrier within the horizon.
+    fn predict(&self, i: usize) -> (Contact3, f64) {
+        let electron = &self.electrons[i];
+        let (pos, vel, acc) = (electron.pos, electron.vel, electron.acc);
+        let reach = CrystalStructure3::reach(electron);
+        let mut best = (Contact3::Horizon, HORIZON);
+        let mut consider = |contact: Contact3, time: f64| {
+            if time < best.1 {
+                best = (contact, time);
+            }
+        };
+
+        consider(
+            Contact3::Periodic { forward: true },
+            calc_time_to_border_collision(self.x_size - pos.x, vel.x, acc.x),
+        );
+        consider(
+            Contact3::Periodic { forward: false },
+            calc_time_to_border_collision(pos.x, -vel.x, -acc.x),
+        );
+        for (axis, size) in [(1, self.y_size), (2, self.z_size)] {
+            consider(
+                Contact3::Wall { axis, upper: false },
+                calc_time_to_border_collision(pos[axis] - electron.radius, -vel[axis], -acc[axis]),
+            );
+            consider(
+                Contact3::Wall { axis, upper: true },
+                calc_time_to_border_collision(
+                    size - electron.radius - pos[axis],
+                    vel[axis],
+                    acc[axis],
+                ),
+            );
+        }
+
+        let ion_clearance = self.ion_radius + electron.radius;
+        let zero = Vector3::new(0.0, 0.0, 0.0);
+        for (ion, shift) in self.cells.near(pos, reach + ion_clearance, self.x_size) {
+            let ion_pos = self.ions[ion] + Vector3::new(shift, 0.0, 0.0);
+            consider(
+                Contact3::Ion(ion),
+                calc_time_to_collision(pos, vel, acc, ion_pos, zero, zero, ion_clearance),
+            );
+        }
+
+        for (j, other) in self.electrons.iter().enumerate() {
+            let clearance = electron.radius + other.radius;
+            let other_pos = pos + self.separation(pos, other.pos);
+            if j == i
+                || (other_pos - pos).magnitude()
+                    > reach + CrystalStructure3::reach(other) + clearance
+            {
+                continue;
+            }
+            consider(
+                Contact3::Electron(j),
+                calc_time_to_collision(pos, vel, acc, other_pos, other.vel, other.acc, clearance),
+            );
+        }
+        best
+    }
+
+    // Whether the carrier already moves away from its partner. It then overlaps the partner and
+    // the predicted contact is the way out, which is not a collision.
+    fn is_separating(&self, i: usize, contact: Contact3) -> bool {
+        let electron = &self.electrons[i];
+        let (pos, vel) = match contact {
+            Contact3::Ion(ion) => (self.ions[ion], Vector3::new(0.0, 0.0, 0.0)),
+            Contact3::Electron(j) => (self.electrons[j].pos, self.electrons[j].vel),
+            _ => return false,
+        };
+        self.separation(pos, electron.pos)
+            .dot(&(electron.vel - vel))
+            > 0.0
+    }
+
+    fn resolve_next_collision(&mut self) {
+        let (i, contact) = self.next_collision;
+        if self.is_separating(i, contact) {
+            return;
+        }
+        match contact {
+            Contact3::Ion(ion) => {
+                let normal = self
+                    .separation(self.electrons[i].pos, self.ions[ion])
+                    .normalize();
+                let electron = &mut self.electrons[i];
+                electron.record_collision(CollisionKind::Ion);
+                let zero = Vector3::new(0.0, 0.0, 0.0);
+                electron.vel =
+                    elastic_collision(electron.vel, electron.mass, zero, f64::INFINITY, normal).0;
+            }
+            Contact3::Electron(j) => {
+                self.electrons[i].record_collision(CollisionKind::Electron);
+                self.electrons[j].record_collision(CollisionKind::Electron);
+                let (electron, other) = (self.electrons[i], self.electrons[j]);
+                let normal = self.separation(electron.pos, other.pos).normalize();
+                let (vel, other_vel) =
+                    elastic_collision(electron.vel, electron.mass, other.vel, other.mass, normal);
+                self.electrons[i].vel = vel;
+                self.electrons[j].vel = other_vel;
+            }
+            Contact3::Wall { axis, .. } => {
+                let electron = &mut self.electrons[i];
+                electron.record_collision(CollisionKind::Wall);
+                electron.vel[axis] = -electron.vel[axis];
+            }
+            Contact3::Periodic { forward } => {
+                let electron = &mut self.electrons[i];
+                let flux = &mut self.flux[electron.species];
+                // The drag is not part of the prediction, so the carrier may still be a little short of the border.
+                if forward {
+                    electron.pos.x = (electron.pos.x - self.x_size).max(0.0);
+                    flux.left += 1;
+                } else {
+                    electron.pos.x = (electron.pos.x + self.x_size).min(self.x_size);
+                    flux.right += 1;
+                }
+            }
+            Contact3::Horizon => {}
+        }
+    }
+
+    // Mean electron velocity along the field axis.
+    pub fn drift_velocity(&self) -> f64 {
+        if self.electrons.is_empty() {
+            return 0.0;
+        }
+        let sum = self
+            .electrons
+            .iter()
+            .fold(0.0, |acc, electron| acc + electron.vel.x);
+        sum / self.electrons.len() as f64
+    }
+
+    // Mean velocity along the field axis of the carriers of one species.
+    pub fn species_drift_velocity(&self, species: usize) -> f64 {
+        let (sum, count) = self
+            .electrons
+            .iter()
+            .filter(|electron| electron.species == species)
+            .fold((0.0, 0), |(sum, count), electron| {
+                (sum + electron.vel.x, count + 1)
+            });
+        if count == 0 {
+            return 0.0;
+        }
+        sum / count as f64
+    }
+
+    // Number of electrons per unit volume.
+    pub fn electron_density(&self) -> f64 {
+        self.electrons.len() as f64 / (self.x_size * self.y_size * self.z_size)
+    }
+
+    // Carriers of one species per unit volume.
+    pub fn species_density(&self, species: usize) -> f64 {
+        let count = self
+            .electrons
+            .iter()
+            .filter(|electron| electron.species == species)
+            .count();
+        count as f64 / (self.x_size * self.y_size * self.z_size)
+    }
+
+    // Drift velocity per unit field, positive for both electrons and holes.
+    pub fn mobility(&self, species: usize) -> f64 {
+        let field = field_from_acc(self.acc);
+        if field == 0.0 {
+            return 0.0;
+        }
+        self.species_drift_velocity(species) * self.species[species].charge.signum() / field
+    }
+
+    // sigma = n * |q| * mu of one species.
+    pub fn conductivity(&self, species: usize) -> f64 {
+        self.species_density(species) * self.species[species].charge.abs() * self.mobility(species)
+    }
+
+    // Charge carried per unit time in the +x direction by one species, counted at the periodic borders.
+    pub fn species_current(&self, species: usize) -> f64 {
+        if self.time == 0.0 {
+            return 0.0;
+        }
+        let flux = self.flux[species];
+        self.species[species].charge * (flux.left - flux.right) as f64 / self.time
+    }
+
+    // Sum of the contributions of every carrier species.
+    pub fn net_current(&self) -> f64 {
+        (0..self.species.len())
+            .map(|species| self.species_current(species))
+            .sum()
+    }
+
+    // Mean time between collisions of any kind, pooled over the carriers.
+    pub fn mean_free_time(&self) -> f64 {
+        self.collision_stats().mean_free_time()
+    }
+
+    // Collision counters of all carriers pooled, broken down by partner.
+    pub fn collision_stats(&self) -> CollisionStats {
+        self.electrons
+            .iter()
+            .fold(CollisionStats::default(), |stats, electron| {
+                stats.merge(electron.collisions)
+            })
+    }
+
+    pub fn kinetic_energy(&self) -> f64 {
+        self.electrons
+            .iter()
+            .map(|electron| electron.kinetic_energy())
+            .sum()
+    }
+}
+
+// Lattice sites offset by a quarter of the lattice constant, dropping those whose ions
+// would stick out of the y and z walls. The pattern is periodic along x when `a` divides `x_size`.
+fn lattice_sites(size: Vector3<f64>, lattice: Lattice, a: f64, radius: f64) -> Vec<Vector3<f64>> {
+    if a <= 0.0 {
+        return Vec::new();
+    }
+    let basis = lattice.basis();
+    let offset = Vector3::new(a, a, a) / 4.0;
+    let cells = [0, 1, 2].map(|axis| (size[axis] / a).ceil() as usize);
+    let mut sites = Vec::new();
+    for i in 0..cells[0] {
+        for j in 0..cells[1] {
+            for k in 0..cells[2] {
+                let cell = Vector3::new(i as f64, j as f64, k as f64);
+                for site in basis.iter() {
+                    let pos = (cell + site) * a + offset;
+                    let inside = pos.x < size.x
+                        && (1..3)
+                            .all(|axis| pos[axis] >= radius && pos[axis] + radius <= size[axis]);
+                    if inside {
+                        sites.push(pos);
+                    }
+                }
+            }
+        }
+    }
+    sites
+}
+
+// Uniformly random unit vector.
+fn random_direction() -> Vector3<f64> {
+    let z = 2.0 * random() - 1.0;
+    let angle = random() * 2.0 * std::f64::consts::PI;
+    let planar = (1.0 - z.powi(2)).sqrt();
+    Vector3::new(planar * angle.cos(), planar * angle.sin(), z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn lattice_neighbour_distances() {
+        let nearest = |lattice: Lattice| {
+            let mut distance = f64::INFINITY;
+            for site in lattice.basis() {
+                for other in lattice.basis() {
+                    for shift in 0..27 {
+                        let cell = Vector3::new(
+                            (shift % 3) as f64 - 1.0,
+                            (shift / 3 % 3) as f64 - 1.0,
+                            (shift / 9) as f64 - 1.0,
+                        );
+                        let d = (other + cell - site).magnitude();
+                        if d > 1e-12 {
+                            distance = distance.min(d);
+                        }
+                    }
+                }
+            }
+            distance
+        };
+
+        assert_eq!(nearest(Lattice::SimpleCubic), 1.0);
+        assert!((nearest(Lattice::BodyCentred) - 3.0f64.sqrt() / 2.0).abs() < 1e-12);
+        assert!((nearest(Lattice::FaceCentred) - 0.5f64.sqrt()).abs() < 1e-12);
+        assert_eq!(Lattice::FaceCentred.density(2.0), 0.5);
+    }
+
+    #[test]
+    fn ions_tile_box() {
+        let cs = CrystalStructure3::new(120.0, 120.0, 120.0, Lattice::FaceCentred, 40.0, 1.0, 0);
+        assert_eq!(cs.ions.len(), 4 * 27);
+        let cs = CrystalStructure3::new(120.0, 120.0, 120.0, Lattice::BodyCentred, 40.0, 1.0, 0);
+        assert_eq!(cs.ions.len(), 2 * 27);
+    }
+
+    #[test]
+    fn sphere_contact_is_exact() {
+        let mut cs = CrystalStructure3::new(80.0, 80.0, 80.0, Lattice::SimpleCubic, 40.0, 1.0, 0);
+        cs.add_carrier(Electron3::of_species(
+            0,
+            &Species::electron(),
+            Vector3::new(10.0, 10.0, 30.0),
+            Vector3::new(0.0, 0.0, -10.0),
+        ));
+        cs.update(0.0, 0.0);
+
+        // Contact at z = 10 + 13 after 0.7 ticks, then 0.3 ticks back up.
+        let electron = cs.electrons[0];
+        assert!((electron.vel - Vector3::new(0.0, 0.0, 10.0)).magnitude() < 1e-9);
+        assert!((electron.pos.z - 26.0).abs() < 1e-9);
+        assert_eq!(electron.collisions.mean_free_time(), 0.7);
+    }
+
+    #[test]
+    fn field_drives_current_without_losing_carriers() {
+        seed_random(81);
+        let mut cs =
+            CrystalStructure3::new(160.0, 160.0, 160.0, Lattice::BodyCentred, 40.0, 2.0, 30);
+        let energy = cs.kinetic_energy();
+        for _ in 0..30 {
+            cs.update(0.0, 0.0);
+        }
+        assert!((cs.kinetic_energy() - energy).abs() < 1e-6 * energy);
+        assert!(cs.mean_free_time() > 0.0);
+
+        for _ in 0..100 {
+            cs.update(0.05, 0.01);
+        }
+        assert!(cs.mobility(0) > 0.0);
+        assert!(cs.species_current(0) < 0.0);
+        assert!(cs.electrons.iter().all(|electron| {
+            (0.0..=160.0).contains(&electron.pos.x)
+                && (0.0..=160.0).contains(&electron.pos.y)
+                && (0.0..=160.0).contains(&electron.pos.z)
+        }));
+    }
+
+    #[test]
+    fn ions_are_hit_across_the_periodic_border() {
+        let mut cs = CrystalStructure3::new(40.0, 80.0, 80.0, Lattice::SimpleCubic, 40.0, 1.0, 0);
+        cs.add_carrier(Electron3::of_species(
+            0,
+            &Species::electron(),
+            Vector3::new(30.0, 50.0, 50.0),
+            Vector3::new(10.0, 0.0, 0.0),
+        ));
+        assert!(!cs.is_free(Vector3::new(38.0, 50.0, 50.0), 3.0));
+
+        // The image of the ion at x = 10 sits at x = 50, contact at x = 37 after 0.7 ticks.
+        let (contact, time) = cs.predict(0);
+        assert_eq!(
+            contact,
+            Contact3::Ion(
+                cs.ions
+                    .iter()
+                    .position(|&ion| ion == Vector3::new(10.0, 50.0, 50.0))
+                    .unwrap()
+            )
+        );
+        assert!((time - 0.7).abs() < 1e-9);
+
+        cs.update(0.0, 0.0);
+        let electron = cs.electrons[0];
+        assert!((electron.vel - Vector3::new(-10.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!((electron.pos.x - 34.0).abs() < 1e-9);
+        assert_eq!(cs.collision_stats().ion.count, 1);
+    }
+
+    #[test]
+    fn carriers_meet_across_the_periodic_border() {
+        let mut cs = CrystalStructure3::new(70.0, 80.0, 80.0, Lattice::SimpleCubic, 40.0, 1.0, 0);
+        for (x, vx) in [(5.0, -5.0), (65.0, 5.0)] {
+            cs.add_carrier(Electron3::of_species(
+                0,
+                &Species::electron(),
+                Vector3::new(x, 30.0, 30.0),
+                Vector3::new(vx, 0.0, 0.0),
+            ));
+        }
+
+        let (contact, time) = cs.predict(0);
+        assert_eq!(contact, Contact3::Electron(1));
+        assert!((time - 0.4).abs() < 1e-9);
+
+        cs.update(0.0, 0.0);
+        assert!((cs.electrons[0].vel.x - 5.0).abs() < 1e-9);
+        assert!((cs.electrons[1].vel.x + 5.0).abs() < 1e-9);
+        assert_eq!(cs.collision_stats().electron.count, 2);
+    }
+
+    #[test]
+    fn overlapping_partner_is_left_not_bounced() {
+        let mut cs = CrystalStructure3::new(80.0, 80.0, 80.0, Lattice::SimpleCubic, 40.0, 1.0, 0);
+        cs.add_carrier(Electron3::of_species(
+            0,
+            &Species::electron(),
+            Vector3::new(20.0, 10.0, 10.0),
+            Vector3::new(4.0, 0.0, 0.0),
+        ));
+        cs.update(0.0, 0.0);
+
+        // Inside the ion at (10, 10, 10) and moving out, the exit is not a collision.
+        assert_eq!(cs.electrons[0].vel, Vector3::new(4.0, 0.0, 0.0));
+        assert_eq!(cs.collision_stats().total(), 0);
+    }
+}
@@ -0,0 +1,116 @@
+use wasm_bindgen::prelude::*;
+
+use crate::crystal_structure_js::collision_kind;
+use crate::three_d::{CrystalStructure3, Lattice};
+
+#[wasm_bindgen(js_name = CrystalStructure3)]
+pub struct CrystalStructure3Js {
+    cs: CrystalStructure3,
+}
+
+#[wasm_bindgen(js_class = CrystalStructure3)]
+impl CrystalStructure3Js {
+    // Chooses the lattice by name: "simple_cubic", "body_centred" or "face_centred".
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        x_size: f64,
+        y_size: f64,
+        z_size: f64,
+        lattice: &str,
+        lattice_constant: f64,
+        init_velocity: f64,
+        num_electrons: i32,
+    ) -> Result<CrystalStructure3Js, JsError> {
+        let lattice = match lattice {
+            "simple_cubic" => Lattice::SimpleCubic,
+            "body_centred" => Lattice::BodyCentred,
+            "face_centred" => Lattice::FaceCentred,
+            _ => return Err(JsError::new(&format!("unknown lattice '{}'", lattice))),
+        };
+        Ok(CrystalStructure3Js {
+            cs: CrystalStructure3::new(
+                x_size,
+                y_size,
+                z_size,
+                lattice,
+                lattice_constant,
+                init_velocity,
+                num_electrons,
+            ),
+        })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn x_size(&self) -> f64 {
+        self.cs.x_size
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn y_size(&self) -> f64 {
+        self.cs.y_size
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn z_size(&self) -> f64 {
+        self.cs.z_size
+    }
+
+    pub fn update(&mut self, acc: f64, supp: f64) {
+        self.cs.update(acc, supp);
+    }
+
+    pub fn drift_velocity(&self) -> f64 {
+        self.cs.drift_velocity()
+    }
+
+    pub fn electron_density(&self) -> f64 {
+        self.cs.electron_density()
+    }
+
+    pub fn mobility(&self, species: usize) -> f64 {
+        self.cs.mobility(species)
+    }
+
+    pub fn conductivity(&self, species: usize) -> f64 {
+        self.cs.conductivity(species)
+    }
+
+    pub fn species_current(&self, species: usize) -> f64 {
+        self.cs.species_current(species)
+    }
+
+    pub fn net_current(&self) -> f64 {
+        self.cs.net_current()
+    }
+
+    // Pooled mean free time under the name the app has always used.
+    pub fn avg_ticks_between_bounces(&self) -> f64 {
+        self.cs.mean_free_time()
+    }
+
+    // Same partner names as `CrystalStructure.collision_count`, impurities never occur in 3D.
+    pub fn collision_count(&self, kind: &str) -> Result<i32, JsError> {
+        Ok(self.cs.collision_stats().get(collision_kind(kind)?).count)
+    }
+
+    pub fn partner_mean_free_time(&self, kind: &str) -> Result<f64, JsError> {
+        Ok(self.cs.collision_stats().get(collision_kind(kind)?).mean)
+    }
+
+    // Flattened `[x0, y0, z0, x1, ...]` so a renderer can upload it as one buffer.
+    pub fn ion_positions(&self) -> Vec<f64> {
+        self.cs
+            .ions
+            .iter()
+            .flat_map(|ion| ion.iter().copied().collect::<Vec<f64>>())
+            .collect()
+    }
+
+    pub fn electron_positions(&self) -> Vec<f64> {
+        self.cs
+            .electrons
+            .iter()
+            .flat_map(|electron| electron.pos.iter().copied().collect::<Vec<f64>>())
+            .collect()
+    }
+}
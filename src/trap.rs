@@ -0,0 +1,117 @@
+extern crate nalgebra as na;
+use na::Vector2;
+
+use crate::electron::Electron;
+use crate::utils::{calc_time_to_collision, random};
+
+// A trapping centre that holds one carrier for an exponentially distributed dwell time.
+// Carriers are captured as soon as their centre comes within `radius` of the trap.
+#[derive(Clone)]
+pub struct Trap {
+    pub pos: Vector2<f64>,
+    pub radius: f64,
+    pub mean_dwell: f64,
+    pub occupant: Option<Electron>,
+    pub release_at: f64,
+}
+
+impl Trap {
+    pub fn new(pos: Vector2<f64>, radius: f64, mean_dwell: f64) -> Trap {
+        Trap {
+            pos,
+            radius,
+            mean_dwell,
+            occupant: None,
+            release_at: f64::INFINITY,
+        }
+    }
+
+    pub fn is_occupied(&self) -> bool {
+        self.occupant.is_some()
+    }
+
+    pub fn calc_time_to_collision(&self, other: &Electron) -> f64 {
+        if self.is_occupied() {
+            return f64::INFINITY;
+        }
+        calc_time_to_collision(
+            other.pos,
+            other.vel,
+            other.acc,
+            self.pos,
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            self.radius,
+        )
+    }
+
+    pub fn capture(&mut self, electron: Electron, now: f64) {
+        self.occupant = Some(electron);
+        self.schedule_release(now);
+    }
+
+    pub fn schedule_release(&mut self, now: f64) {
+        self.release_at = now - self.mean_dwell * (1.0 - random()).ln();
+    }
+
+    // Frees the occupant at the trap centre, moving off in a random direction with its captured speed.
+    pub fn release(&mut self) -> Option<Electron> {
+        self.release_at = f64::INFINITY;
+        let mut electron = self.occupant.take()?;
+        let angle = random() * 2.0 * std::f64::consts::PI;
+        electron.pos = self.pos;
+        electron.vel = Vector2::new(angle.cos(), angle.sin()) * electron.vel.magnitude();
+        Some(electron)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_random;
+
+    #[test]
+    fn calc_time_to_capture() {
+        let trap = Trap::new(Vector2::new(100.0, 100.0), 5.0, 10.0);
+        let electron = Electron::new(
+            Vector2::new(90.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        assert_eq!(trap.calc_time_to_collision(&electron), 2.5);
+    }
+
+    #[test]
+    fn occupied_trap_is_transparent() {
+        let mut trap = Trap::new(Vector2::new(100.0, 100.0), 5.0, 10.0);
+        let electron = Electron::new(
+            Vector2::new(90.0, 100.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(0.0, 0.0),
+        );
+        trap.capture(electron.clone(), 3.0);
+
+        assert!(trap.release_at > 3.0);
+        assert_eq!(trap.calc_time_to_collision(&electron), f64::INFINITY);
+    }
+
+    #[test]
+    fn release_keeps_speed() {
+        seed_random(2);
+        let mut trap = Trap::new(Vector2::new(100.0, 100.0), 5.0, 10.0);
+        trap.capture(
+            Electron::new(
+                Vector2::new(95.0, 100.0),
+                Vector2::new(3.0, 4.0),
+                Vector2::new(0.0, 0.0),
+            ),
+            0.0,
+        );
+        let electron = trap.release().unwrap();
+
+        assert!(!trap.is_occupied());
+        assert_eq!(electron.pos, Vector2::new(100.0, 100.0));
+        assert!((electron.vel.magnitude() - 5.0).abs() < 1e-12);
+        assert_eq!(trap.release_at, f64::INFINITY);
+    }
+}
@@ -0,0 +1,22 @@
+use wasm_bindgen::prelude::*;
+
+use crate::trap::Trap;
+
+#[wasm_bindgen(js_name = Trap)]
+pub struct TrapJs {
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64,
+    pub occupied: bool,
+}
+
+impl TrapJs {
+    pub fn new(trap: &Trap) -> TrapJs {
+        TrapJs {
+            x: trap.pos.x,
+            y: trap.pos.y,
+            radius: trap.radius,
+            occupied: trap.is_occupied(),
+        }
+    }
+}
@@ -1,7 +1,5 @@
 extern crate nalgebra as na;
-use std::f64::INFINITY;
-
-use na::Vector2;
+use na::{Matrix2, SVector, Vector2};
 use roots::{find_roots_quadratic, find_roots_quartic};
 
 use crate::cfg::EPSILON;
@@ -13,13 +11,14 @@ use crate::cfg::EPSILON;
 // @param vel2 The velocity of the second object.
 // @param r_sum The sum of the radiuses of the two objects.
 // @returns The time till bounce.
-pub fn calc_time_to_collision(
-    pos1: Vector2<f64>,
-    vel1: Vector2<f64>,
-    acc1: Vector2<f64>,
-    pos2: Vector2<f64>,
-    vel2: Vector2<f64>,
-    acc2: Vector2<f64>,
+// Works in any dimension, the contact polynomial only depends on dot products.
+pub fn calc_time_to_collision<const D: usize>(
+    pos1: SVector<f64, D>,
+    vel1: SVector<f64, D>,
+    acc1: SVector<f64, D>,
+    pos2: SVector<f64, D>,
+    vel2: SVector<f64, D>,
+    acc2: SVector<f64, D>,
     r_sum: f64,
 ) -> f64 {
     let d_pos = pos2 - pos1;
@@ -27,7 +26,7 @@ pub fn calc_time_to_collision(
     let d_acc = acc2 - acc1;
 
     if d_pos.angle(&d_vel) < 0.0 {
-        return INFINITY;
+        return f64::INFINITY;
     }
 
     let acc_dot = d_acc.dot(&d_acc);
@@ -47,7 +46,7 @@ pub fn calc_time_to_collision(
     roots.retain(|&x| x > EPSILON);
     roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
     if roots.is_empty() {
-        return INFINITY;
+        return f64::INFINITY;
     }
     roots[0]
 }
@@ -59,12 +58,91 @@ pub fn calc_time_to_border_collision(dist: f64, vel: f64, acc: f64) -> f64 {
     roots.retain(|&x| x > EPSILON);
     roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
     if roots.is_empty() {
-        return INFINITY;
+        return f64::INFINITY;
     }
     roots[0]
 }
 
+// Resolves an elastic collision of two bodies touching along `normal`.
+// Only the velocity components along the normal are exchanged, momentum and kinetic energy are conserved.
+// An infinite mass acts as an immovable wall.
+pub fn elastic_collision<const D: usize>(
+    vel1: SVector<f64, D>,
+    mass1: f64,
+    vel2: SVector<f64, D>,
+    mass2: f64,
+    normal: SVector<f64, D>,
+) -> (SVector<f64, D>, SVector<f64, D>) {
+    let approach = (vel1 - vel2).dot(&normal);
+    let (share1, share2) = if mass2.is_infinite() {
+        (2.0, 0.0)
+    } else if mass1.is_infinite() {
+        (0.0, 2.0)
+    } else {
+        let total = mass1 + mass2;
+        (2.0 * mass2 / total, 2.0 * mass1 / total)
+    };
+    (
+        vel1 - normal * share1 * approach,
+        vel2 + normal * share2 * approach,
+    )
+}
+
+// Elastic collision of two bodies with mass tensors, given by their inverses.
+// The impulse acts along `normal` and is chosen so that the kinetic energy 1/2 v^T M v is conserved;
+// a zero inverse tensor acts as a fixed wall.
+pub fn tensor_collision(
+    vel1: Vector2<f64>,
+    inv_mass1: Matrix2<f64>,
+    vel2: Vector2<f64>,
+    inv_mass2: Matrix2<f64>,
+    normal: Vector2<f64>,
+) -> (Vector2<f64>, Vector2<f64>) {
+    let approach = (vel1 - vel2).dot(&normal);
+    let impulse = -2.0 * approach / normal.dot(&((inv_mass1 + inv_mass2) * normal));
+    (
+        vel1 + inv_mass1 * normal * impulse,
+        vel2 - inv_mass2 * normal * impulse,
+    )
+}
+
 pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+// Uniform random number in [0, 1).
+// The browser build uses `Math.random`, native builds use a seedable xorshift generator.
+#[cfg(target_arch = "wasm32")]
+pub fn random() -> f64 {
+    js_sys::Math::random()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+    static RNG_STATE: std::cell::Cell<u64> = const { std::cell::Cell::new(0x2545_f491_4f6c_dd1d) };
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn random() -> f64 {
+    RNG_STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn seed_random(seed: u64) {
+    RNG_STATE.with(|state| state.set(seed.max(1)));
+}
+
+// Standard normal random number (Box-Muller).
+pub fn random_normal() -> f64 {
+    let u = 1.0 - random();
+    let v = random();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}